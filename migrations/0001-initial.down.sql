@@ -0,0 +1,3 @@
+drop table if exists "database_version";
+drop table if exists "links";
+drop table if exists "friends";
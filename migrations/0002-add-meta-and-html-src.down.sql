@@ -0,0 +1,5 @@
+alter table links drop column http_headers;
+alter table links drop column last_processed;
+alter table links drop column last_fetched;
+alter table links drop column src;
+alter table links drop column meta;
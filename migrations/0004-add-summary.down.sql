@@ -0,0 +1,2 @@
+alter table links drop column summarized_at;
+alter table links drop column summary;
@@ -0,0 +1,2 @@
+alter table links add column summary text default(null);
+alter table links add column summarized_at int default(null);
@@ -0,0 +1,4 @@
+create table if not exists "tag_aliases" (
+  alias text not null primary key,
+  tag text not null
+) strict;
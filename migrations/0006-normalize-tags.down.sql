@@ -0,0 +1,13 @@
+alter table links add column tags text not null default('');
+
+update links set tags = (
+  select json_group_array(name) from (
+    select tags.name from link_tags
+    join tags on tags.id = link_tags.tag_id
+    where link_tags.link_id = links.id
+    order by link_tags.rowid
+  )
+);
+
+drop table if exists "link_tags";
+drop table if exists "tags";
@@ -0,0 +1,25 @@
+create table if not exists "tags" (
+  id integer primary key asc autoincrement,
+  name text not null unique
+) strict;
+
+create table if not exists "link_tags" (
+  link_id integer not null references links(id) on delete cascade,
+  tag_id integer not null references tags(id) on delete cascade,
+  primary key (link_id, tag_id)
+) strict;
+
+create index if not exists "link_tags_tag_id" on "link_tags" (tag_id);
+
+insert or ignore into tags (name)
+  select distinct tag.value
+  from links, json_each(case when json_valid(links.tags) then links.tags else '[]' end) as tag
+  where tag.value != '';
+
+insert or ignore into link_tags (link_id, tag_id)
+  select links.id, tags.id
+  from links, json_each(case when json_valid(links.tags) then links.tags else '[]' end) as tag
+  join tags on tags.name = tag.value
+  order by links.id, tag.key;
+
+alter table links drop column tags;
@@ -0,0 +1,4 @@
+drop index if exists "links_found_at";
+drop index if exists "links_read_at";
+drop index if exists "links_published_at";
+drop index if exists "links_last_fetched";
@@ -0,0 +1,4 @@
+create index if not exists "links_found_at" on "links" (found_at, id);
+create index if not exists "links_read_at" on "links" (read_at, id);
+create index if not exists "links_published_at" on "links" (published_at, id);
+create index if not exists "links_last_fetched" on "links" (last_fetched, id);
@@ -0,0 +1 @@
+alter table links drop column src_truncated;
@@ -0,0 +1 @@
+alter table links add column src_truncated text default(null);
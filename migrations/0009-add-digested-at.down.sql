@@ -0,0 +1 @@
+alter table links drop column digested_at;
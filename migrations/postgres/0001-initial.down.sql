@@ -0,0 +1,4 @@
+drop table if exists "tag_aliases";
+drop table if exists "link_tags";
+drop table if exists "tags";
+drop table if exists "links";
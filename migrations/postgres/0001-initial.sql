@@ -0,0 +1,46 @@
+-- Timestamps are milliseconds since the epoch, as in the SQLite schema, so that filters, sorts
+-- and cursors compile the same way for both stores.
+create table if not exists "links" (
+  id bigserial primary key,
+  url text not null unique,
+  title text default null,
+  via text default null,
+  notes text default null,
+  found_at bigint default null,
+  read_at bigint default null,
+  published_at bigint default null,
+  from_filename text default null,
+  image text default null,
+  src bytea default null,
+  meta text default null,
+  last_fetched bigint default null,
+  last_processed bigint default null,
+  http_headers bytea default null,
+  hidden boolean not null default false,
+  summary text default null,
+  summarized_at bigint default null
+);
+
+create table if not exists "tags" (
+  id bigserial primary key,
+  name text not null unique
+);
+
+create table if not exists "link_tags" (
+  link_id bigint not null references links(id) on delete cascade,
+  tag_id bigint not null references tags(id) on delete cascade,
+  position integer not null,
+  primary key (link_id, tag_id)
+);
+
+create index if not exists "link_tags_tag_id" on "link_tags" (tag_id);
+
+create table if not exists "tag_aliases" (
+  alias text not null primary key,
+  tag text not null
+);
+
+create index if not exists "links_found_at" on "links" (found_at, id);
+create index if not exists "links_read_at" on "links" (read_at, id);
+create index if not exists "links_published_at" on "links" (published_at, id);
+create index if not exists "links_last_fetched" on "links" (last_fetched, id);
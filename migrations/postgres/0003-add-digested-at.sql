@@ -0,0 +1 @@
+alter table "links" add column digested_at bigint default null;
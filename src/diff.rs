@@ -0,0 +1,167 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::{Link, Via};
+
+/// One field that differs between two versions of a link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// The user-visible differences between a stored link and the version an import would write,
+/// as printed by `likelike import --dry-run`.
+///
+/// Tags are compared as sets, since imports do not preserve tag order. Fetched data (source,
+/// headers, metadata) is not compared.
+#[derive(Debug, Clone)]
+pub struct LinkDiff {
+    pub url: String,
+    pub created: bool,
+    pub changes: Vec<FieldChange>,
+}
+
+fn date(date: Option<DateTime<Utc>>) -> Option<String> {
+    date.map(|date| date.to_rfc3339())
+}
+
+fn via(via: Option<&Via>) -> Option<String> {
+    via.map(|via| match via {
+        Via::Friend(xs) | Via::Link(xs) | Via::Freeform(xs) => xs.clone(),
+    })
+}
+
+fn tags(link: &Link) -> Option<String> {
+    let mut tags = link.tags().clone();
+    tags.sort();
+    tags.dedup();
+    Some(tags.join(", ")).filter(|xs| !xs.is_empty())
+}
+
+fn fields(link: &Link) -> [(&'static str, Option<String>); 9] {
+    [
+        ("title", link.title().map(str::to_string)),
+        ("via", via(link.via())),
+        ("tags", tags(link)),
+        ("notes", link.notes().map(str::to_string)),
+        ("found_at", date(link.found_at())),
+        ("read_at", date(link.read_at())),
+        ("published_at", date(link.published_at())),
+        ("from_filename", link.from_filename().map(str::to_string)),
+        ("hidden", link.hidden().then(|| "yes".to_string())),
+    ]
+}
+
+impl LinkDiff {
+    /// Compare a link against its stored version, if any. Returns `None` if nothing changed.
+    pub fn new(before: Option<&Link>, after: &Link) -> Option<Self> {
+        let changes: Vec<_> = match before {
+            Some(before) => fields(before)
+                .into_iter()
+                .zip(fields(after))
+                .filter(|((_, before), (_, after))| before != after)
+                .map(|((field, before), (_, after))| FieldChange {
+                    field,
+                    before,
+                    after,
+                })
+                .collect(),
+
+            None => fields(after)
+                .into_iter()
+                .filter(|(_, after)| after.is_some())
+                .map(|(field, after)| FieldChange {
+                    field,
+                    before: None,
+                    after,
+                })
+                .collect(),
+        };
+
+        if before.is_some() && changes.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            url: after.url().to_string(),
+            created: before.is_none(),
+            changes,
+        })
+    }
+
+    /// A digest of the fields a diff compares, for telling later whether a link has changed
+    /// without keeping a copy of it.
+    pub fn fingerprint(link: &Link) -> String {
+        let mut hasher = Sha256::new();
+        for (field, value) in fields(link) {
+            hasher.update(field.as_bytes());
+            match value {
+                Some(value) => {
+                    hasher.update([1]);
+                    hasher.update((value.len() as u64).to_le_bytes());
+                    hasher.update(value.as_bytes());
+                }
+                None => hasher.update([0]),
+            }
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+impl Display for LinkDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {}", if self.created { '+' } else { '~' }, self.url)?;
+
+        for change in &self.changes {
+            for (sign, value) in [('-', &change.before), ('+', &change.after)] {
+                let Some(value) = value else { continue };
+                let mut lines = value.lines();
+                writeln!(
+                    f,
+                    "    {sign} {}: {}",
+                    change.field,
+                    lines.next().unwrap_or_default()
+                )?;
+                for line in lines {
+                    writeln!(f, "    {sign}   {line}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_only_changed_fields() {
+        let mut before = Link::new("https://a.com/", "A");
+        *before.tags_mut() = vec!["b".to_string(), "a".to_string()];
+
+        let mut after = before.clone();
+        *after.tags_mut() = vec!["a".to_string(), "b".to_string()];
+        assert!(LinkDiff::new(Some(&before), &after).is_none());
+
+        after.tags_mut().push("c".to_string());
+        *after.notes_mut() = Some("first\nsecond".to_string());
+        let diff = LinkDiff::new(Some(&before), &after).expect("link changed");
+        assert_eq!(
+            diff.to_string(),
+            "~ https://a.com/\n    - tags: a, b\n    + tags: a, b, c\n    + notes: first\n    +   second\n"
+        );
+
+        let diff = LinkDiff::new(None, &before).expect("link is new");
+        assert!(diff.created);
+        assert_eq!(
+            diff.to_string(),
+            "+ https://a.com/\n    + title: A\n    + tags: a, b\n"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+use chrono::{DateTime, Duration, Months, NaiveDate, Utc};
+use slugify::slugify;
+use std::collections::BTreeMap;
+
+use crate::{
+    query::link_host, DateField, DateFilter, DisplayTimezone, Frontmatter, FrontmatterDigest,
+    FrontmatterExtra, Link, LinkQuery, LinkReader, LinkWriter, ListParams, Predicate, Query, Sort,
+    SortKey, Term, Via, LIST_PAGE_SIZE,
+};
+
+/// The longest notes excerpt, in characters, before it is cut off at a word boundary.
+const EXCERPT_LENGTH: usize = 280;
+
+/// The section that links without tags are listed under, after every tagged section.
+const UNTAGGED: &str = "elsewhere";
+
+/// The period of reading a digest covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestPeriod {
+    /// The given span of time up to now, parsed from e.g. `7d`, `2w` or `36h`.
+    Since(Duration),
+    /// A calendar month, parsed from e.g. `2024-03`.
+    Month(NaiveDate),
+}
+
+impl DigestPeriod {
+    pub fn since(span: &str) -> eyre::Result<Self> {
+        let (count, unit) =
+            span.split_at(span.len() - span.chars().last().map_or(0, char::len_utf8));
+        let count: i64 = count
+            .parse()
+            .map_err(|_| eyre::eyre!("expected a span like 7d, 2w or 36h; got {:?}", span))?;
+
+        Ok(DigestPeriod::Since(match unit {
+            "h" => Duration::hours(count),
+            "d" => Duration::days(count),
+            "w" => Duration::weeks(count),
+            _ => eyre::bail!("expected a span like 7d, 2w or 36h; got {:?}", span),
+        }))
+    }
+
+    pub fn month(month: &str) -> eyre::Result<Self> {
+        let first = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+            .map_err(|_| eyre::eyre!("expected a month like 2024-03; got {:?}", month))?;
+        Ok(DigestPeriod::Month(first))
+    }
+
+    /// The half-open range of read times the period covers. Months run from midnight to
+    /// midnight in `timezone`.
+    fn range(
+        &self,
+        now: DateTime<Utc>,
+        timezone: DisplayTimezone,
+    ) -> eyre::Result<(DateTime<Utc>, DateTime<Utc>)> {
+        match *self {
+            DigestPeriod::Since(span) => Ok((now - span, now)),
+            DigestPeriod::Month(first) => {
+                let midnight = |date: NaiveDate| {
+                    timezone
+                        .midnight(date)
+                        .ok_or_else(|| eyre::eyre!("{} has no midnight in {}", date, timezone))
+                };
+                Ok((midnight(first)?, midnight(first + Months::new(1))?))
+            }
+        }
+    }
+}
+
+/// A single post of links read over a period, grouped into a section for each link's first tag.
+pub struct Digest {
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub links: Vec<Link>,
+    /// The timezone the post's dates are written in.
+    pub timezone: DisplayTimezone,
+}
+
+impl Digest {
+    /// Collect the links read during `period`, with dates in `timezone`, that are not hidden,
+    /// oldest first. With `unposted`, links that an earlier digest marked with
+    /// [`Digest::mark_digested`] are left out.
+    pub async fn collect<S>(
+        store: &S,
+        period: DigestPeriod,
+        unposted: bool,
+        now: DateTime<Utc>,
+        timezone: DisplayTimezone,
+    ) -> eyre::Result<Self>
+    where
+        S: LinkQuery + Send + Sync,
+    {
+        let (start, end) = period.range(now, timezone)?;
+        let mut links = store
+            .list_all(ListParams {
+                query: Some(Query {
+                    terms: vec![Term {
+                        negated: false,
+                        predicate: Predicate::Date(
+                            DateField::Read,
+                            DateFilter::Range {
+                                start: Some(start),
+                                end: Some(end),
+                            },
+                        ),
+                    }],
+                }),
+                tag: None,
+                hidden: Some(false),
+                sort: Sort {
+                    key: SortKey::Read,
+                    descending: false,
+                },
+                cursor: None,
+                offset: 0,
+                limit: LIST_PAGE_SIZE,
+            })
+            .await?;
+
+        if unposted {
+            links.retain(|link| link.digested_at().is_none());
+        }
+
+        let title = match period {
+            DigestPeriod::Since(_) => format!(
+                "Reading from {} to {}",
+                timezone.date(start),
+                timezone.date(end)
+            ),
+            DigestPeriod::Month(first) => format!("Reading for {}", first.format("%B %Y")),
+        };
+
+        Ok(Self {
+            title,
+            start,
+            end,
+            links,
+            timezone,
+        })
+    }
+
+    /// Render the digest as a markdown post with TOML frontmatter. The frontmatter is a
+    /// [`Frontmatter`] whose `extra.digest` holds the period and the digested urls, in place of a
+    /// single link's metadata.
+    pub fn render(&self) -> eyre::Result<String> {
+        let mut tags: Vec<String> = self
+            .links
+            .iter()
+            .flat_map(|link| link.tags().iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+
+        // The last moment of a month digest is the end of its last day.
+        let date = self.timezone.date(self.end - Duration::seconds(1));
+        let frontmatter = Frontmatter {
+            title: self.title.clone(),
+            slug: slugify!(self.title.as_str()),
+            date: date.to_string(),
+            taxonomies: BTreeMap::from([("tags".to_string(), tags)]),
+            extra: FrontmatterExtra::digest(FrontmatterDigest {
+                since: self.timezone.localize(self.start).to_rfc3339(),
+                until: self.timezone.localize(self.end).to_rfc3339(),
+                links: self
+                    .links
+                    .iter()
+                    .map(|link| link.url().to_string())
+                    .collect(),
+            }),
+            notes: String::new(),
+        };
+
+        let mut sections: BTreeMap<&str, Vec<&Link>> = BTreeMap::new();
+        let mut untagged = Vec::new();
+        for link in &self.links {
+            match link.tags().first() {
+                Some(tag) => sections.entry(tag.as_str()).or_default().push(link),
+                None => untagged.push(link),
+            }
+        }
+
+        let mut output = format!("+++\n{}+++\n", toml::to_string(&frontmatter)?);
+        let untagged = (!untagged.is_empty()).then_some((UNTAGGED, untagged));
+        for (section, links) in sections.into_iter().chain(untagged) {
+            output.push_str(&format!("\n## {}\n\n", section));
+            for link in links {
+                output.push_str(&entry(link));
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Set the digested links' `digested_at` to `at`, so that the next digest collected with
+    /// `unposted` leaves them out.
+    pub async fn mark_digested<S>(&self, store: &S, at: DateTime<Utc>) -> eyre::Result<()>
+    where
+        S: LinkReader + LinkWriter + Send + Sync,
+    {
+        for link in &self.links {
+            // Listed links omit their source data, so fetch each one whole before writing it.
+            let Some(mut link) = store.get(link.url()).await? else {
+                continue;
+            };
+            link.digested_at = Some(at);
+            store.write(link).await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn entry(link: &Link) -> String {
+    let mut entry = format!(
+        "- [{}]({})",
+        escape_link_text(link.title().unwrap_or_else(|| link.url())),
+        link.url()
+    );
+
+    if let Some(host) = link_host(link) {
+        entry.push_str(&format!(" ({})", host));
+    }
+
+    match link.via() {
+        Some(Via::Link(url)) => entry.push_str(&format!(", via <{}>", url)),
+        Some(Via::Friend(xs) | Via::Freeform(xs)) => entry.push_str(&format!(", via {}", xs)),
+        None => {}
+    }
+    entry.push('\n');
+
+    if let Some(excerpt) = link.notes().and_then(excerpt) {
+        entry.push_str(&format!("\n  {}\n", excerpt));
+    }
+
+    entry
+}
+
+/// Escape the characters that would end a markdown link's text early.
+pub(crate) fn escape_link_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for xs in text.chars() {
+        if matches!(xs, '\\' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(xs);
+    }
+    escaped
+}
+
+/// The first paragraph of some notes on one line, cut off at a word boundary if it is long.
+fn excerpt(notes: &str) -> Option<String> {
+    let paragraph = notes
+        .split("\n\n")
+        .map(|paragraph| paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|paragraph| !paragraph.is_empty())?;
+
+    if paragraph.chars().count() <= EXCERPT_LENGTH {
+        return Some(paragraph);
+    }
+
+    let cut: String = paragraph.chars().take(EXCERPT_LENGTH).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(head, _)| head);
+    Some(format!(
+        "{}…",
+        cut.trim_end_matches(|xs: char| xs.is_ascii_punctuation())
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStore;
+
+    fn link(url: &str, tags: &[&str], read_at: &str) -> Link {
+        let mut link = Link::new(url, url.trim_start_matches("https://"));
+        link.tags = tags.iter().map(|xs| xs.to_string()).collect();
+        link.read_at = Some(read_at.parse().unwrap());
+        link
+    }
+
+    #[test]
+    fn parses_periods() -> eyre::Result<()> {
+        assert_eq!(
+            DigestPeriod::since("7d")?,
+            DigestPeriod::Since(Duration::days(7))
+        );
+        assert_eq!(
+            DigestPeriod::since("2w")?,
+            DigestPeriod::Since(Duration::weeks(2))
+        );
+        assert!(DigestPeriod::since("7").is_err());
+        assert!(DigestPeriod::since("d").is_err());
+        assert!(DigestPeriod::month("2024-13").is_err());
+
+        let now = "2024-03-20T12:00:00Z".parse()?;
+        let (start, end) = DigestPeriod::month("2024-02")?.range(now, "UTC".parse()?)?;
+        assert_eq!(start.to_rfc3339(), "2024-02-01T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2024-03-01T00:00:00+00:00");
+
+        // Months run from midnight to midnight in the display timezone.
+        let (start, end) = DigestPeriod::month("2024-03")?.range(now, "Europe/Berlin".parse()?)?;
+        assert_eq!(start.to_rfc3339(), "2024-02-29T23:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2024-03-31T22:00:00+00:00");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn groups_links_by_tag_and_marks_them_digested() -> eyre::Result<()> {
+        let mut rust = link(
+            "https://example.com/rust",
+            &["rust", "async"],
+            "2024-03-02T00:00:00Z",
+        );
+        rust.notes = Some(format!("{}\n\nsecond paragraph", "word ".repeat(100)));
+        rust.via = Some(Via::Friend("alice".to_string()));
+        rust.title = Some("[RFC] async [closures]".to_string());
+        let mut old = link("https://example.com/old", &["rust"], "2024-03-01T00:00:00Z");
+        old.published_at = Some("2010-01-01T00:00:00Z".parse()?);
+        // Pages can be published, or updated, after they were read.
+        let mut untagged = link("https://example.com/untagged", &[], "2024-03-03T00:00:00Z");
+        untagged.published_at = Some("2024-03-10T00:00:00Z".parse()?);
+        let mut hidden = link(
+            "https://example.com/hidden",
+            &["rust"],
+            "2024-03-04T00:00:00Z",
+        );
+        hidden.hidden = true;
+        // Read at half past midnight on April 1 in Berlin.
+        let april = link(
+            "https://example.com/april",
+            &["rust"],
+            "2024-03-31T22:30:00Z",
+        );
+        let store = InMemoryStore::with_links([rust, old, untagged, hidden, april]);
+
+        let now = "2024-05-01T00:00:00Z".parse()?;
+        let period = DigestPeriod::month("2024-03")?;
+        let berlin: DisplayTimezone = "Europe/Berlin".parse()?;
+        let digest = Digest::collect(&store, period, true, now, berlin).await?;
+        let urls: Vec<_> = digest.links.iter().map(Link::url).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://example.com/old",
+                "https://example.com/rust",
+                "https://example.com/untagged"
+            ]
+        );
+
+        let post = digest.render()?;
+        assert!(post.starts_with("+++\ntitle = \"Reading for March 2024\"\n"));
+        assert!(post.contains("date = \"2024-03-31\"\n"));
+        assert!(post.contains("tags = [\"async\", \"rust\"]\n"));
+        let rust_section = post.find("## rust").unwrap();
+        let untagged_section = post.find("## elsewhere").unwrap();
+        assert!(rust_section < untagged_section);
+        assert!(post.contains(
+            "- [\\[RFC\\] async \\[closures\\]](https://example.com/rust) (example.com), via alice\n\n  word word"
+        ));
+        assert!(post.contains("word…\n"));
+        assert!(!post.contains("second paragraph"));
+
+        let frontmatter: Frontmatter = post.parse()?;
+        assert!(frontmatter.is_digest());
+        assert_eq!(frontmatter.taxonomies["tags"], ["async", "rust"]);
+
+        digest.mark_digested(&store, now).await?;
+        let digest = Digest::collect(&store, period, true, now, berlin).await?;
+        assert!(digest.links.is_empty());
+        assert_eq!(
+            Digest::collect(&store, period, false, now, berlin)
+                .await?
+                .links
+                .len(),
+            3
+        );
+
+        let old = store.get("https://example.com/old").await?.unwrap();
+        assert_eq!(old.digested_at(), Some(now));
+        assert_eq!(old.published_at(), Some("2010-01-01T00:00:00Z".parse()?));
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
-use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Serialize, Deserialize};
 use slugify::slugify;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::read_to_string;
 use std::str::FromStr;
 use std::{borrow::Cow, fmt::Debug, path::Path};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::{DisplayTimezone, RelatedLink};
+
 #[derive(Debug)]
 pub struct LinkSource<'a> {
     pub(crate) filename: Option<Cow<'a, str>>,
@@ -41,10 +43,9 @@ impl<'a> LinkSource<'a> {
             let Some(filename) = filename.to_str() else { break 'created_from_filename };
             let Some(maybe_date) = filename.split('-').next() else { break 'created_from_filename };
             let Ok(date) = NaiveDate::parse_from_str(maybe_date, "%Y%m%d") else { break 'created_from_filename };
-            let Some(datetime) = date.and_hms_milli_opt(0, 0, 0, 0) else { break 'created_from_filename };
-            let Some(datetime) = Local.from_local_datetime(&datetime).latest() else { break 'created_from_filename };
+            let Some(datetime) = DisplayTimezone::from_env().midnight(date) else { break 'created_from_filename };
 
-            created.replace(DateTime::<Utc>::from(datetime));
+            created.replace(datetime);
         }
 
         'modified_from_fs: {
@@ -100,6 +101,8 @@ pub struct Link {
 
     pub(crate) meta: Option<HashMap<String, Vec<String>>>,
     pub(crate) src: Option<Vec<u8>>,
+    /// Why `src` holds less than the whole response body, if it does.
+    pub(crate) src_truncated: Option<String>,
     pub(crate) extracted_text: Option<String>,
 
     pub(crate) last_fetched: Option<DateTime<Utc>>,
@@ -109,6 +112,13 @@ pub struct Link {
     pub(crate) http_headers: Option<HashMap<String, Vec<String>>>,
 
     pub(crate) hidden: bool,
+
+    pub(crate) summary: Option<String>,
+
+    pub(crate) summarized_at: Option<DateTime<Utc>>,
+
+    /// When a digest last listed this link.
+    pub(crate) digested_at: Option<DateTime<Utc>>,
 }
 
 impl Link {
@@ -250,6 +260,10 @@ impl Link {
         self.src.as_deref()
     }
 
+    pub fn src_truncated(&self) -> Option<&str> {
+        self.src_truncated.as_deref()
+    }
+
     pub fn extract_text(&self) -> Option<&str> {
         self.extracted_text.as_deref()
     }
@@ -285,6 +299,36 @@ impl Link {
     pub fn hidden_mut(&mut self) -> &mut bool {
         &mut self.hidden
     }
+
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    pub fn summary_mut(&mut self) -> &mut Option<String> {
+        &mut self.summary
+    }
+
+    pub fn summarized_at(&self) -> Option<DateTime<Utc>> {
+        self.summarized_at
+    }
+
+    pub fn summarized_at_mut(&mut self) -> &mut Option<DateTime<Utc>> {
+        &mut self.summarized_at
+    }
+
+    pub fn digested_at(&self) -> Option<DateTime<Utc>> {
+        self.digested_at
+    }
+
+    pub fn digested_at_mut(&mut self) -> &mut Option<DateTime<Utc>> {
+        &mut self.digested_at
+    }
+
+    /// A summary is "fresh" if it was produced after the link's text was last extracted.
+    pub fn has_fresh_summary(&self) -> bool {
+        let (Some(_), Some(summarized_at)) = (self.summary.as_deref(), self.summarized_at) else { return false };
+        !matches!(self.last_processed, Some(last_processed) if last_processed > summarized_at)
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -296,14 +340,14 @@ pub enum Via {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Frontmatter {
-    title: String,
-    slug: String,
-    date: String,
-    taxonomies: HashMap<String, Vec<String>>,
-    extra: FrontmatterExtra,
+    pub(crate) title: String,
+    pub(crate) slug: String,
+    pub(crate) date: String,
+    pub(crate) taxonomies: BTreeMap<String, Vec<String>>,
+    pub(crate) extra: FrontmatterExtra,
 
     #[serde(skip)]
-    notes: String,
+    pub(crate) notes: String,
 }
 
 static TOML_RE: Lazy<Regex> = Lazy::new(|| {
@@ -317,8 +361,13 @@ impl FromStr for Frontmatter {
     type Err = eyre::Report;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let caps = TOML_RE.captures(s).ok_or_else(|| eyre::eyre!(""))?;
-        let front_matter = caps.get(1).ok_or_else(|| eyre::eyre!(""))?.as_str();
+        let caps = TOML_RE
+            .captures(s)
+            .ok_or_else(|| eyre::eyre!("expected +++ fenced TOML frontmatter"))?;
+        let front_matter = caps
+            .get(1)
+            .ok_or_else(|| eyre::eyre!("expected +++ fenced TOML frontmatter"))?
+            .as_str();
         let notes = caps.get(2).map_or("", |m| m.as_str());
 
         let mut frontmatter: Frontmatter = toml::from_str(front_matter)?;
@@ -357,13 +406,19 @@ impl From<FrontmatterVia> for Via {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct FrontmatterUrl {
     url: String,
     host: String,
     path_segments: Vec<String>,
     path: String,
-    query: HashMap<String, String>,
+    query: BTreeMap<String, String>,
+}
+
+impl FrontmatterUrl {
+    fn is_empty(&self) -> bool {
+        self.url.is_empty()
+    }
 }
 
 impl From<url::Url> for FrontmatterUrl {
@@ -387,7 +442,7 @@ impl From<url::Url> for FrontmatterUrl {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct FrontmatterExtra {
     title: Option<String>,
 
@@ -396,11 +451,39 @@ pub struct FrontmatterExtra {
     published_at: Option<String>,
     from_filename: Option<String>,
     image: Option<String>,
+    #[serde(default, skip_serializing_if = "FrontmatterUrl::is_empty")]
     url: FrontmatterUrl,
     via: Option<FrontmatterVia>,
+    #[serde(default)]
     hidden: bool,
+    summary: Option<String>,
+
+    #[serde(default)]
+    meta: BTreeMap<String, String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    related: Vec<RelatedLink>,
 
-    meta: HashMap<String, String>,
+    /// Set on digest posts, which list links rather than describe one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digest: Option<FrontmatterDigest>,
+}
+
+impl FrontmatterExtra {
+    pub(crate) fn digest(digest: FrontmatterDigest) -> Self {
+        Self {
+            digest: Some(digest),
+            ..Default::default()
+        }
+    }
+}
+
+/// The period a digest post covers and the urls it lists.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FrontmatterDigest {
+    pub(crate) since: String,
+    pub(crate) until: String,
+    pub(crate) links: Vec<String>,
 }
 
 impl Frontmatter {
@@ -412,6 +495,19 @@ impl Frontmatter {
         self.notes.as_str()
     }
 
+    pub fn url(&self) -> &str {
+        self.extra.url.url.as_str()
+    }
+
+    /// Whether this is a digest post rather than a link's page.
+    pub fn is_digest(&self) -> bool {
+        self.extra.digest.is_some()
+    }
+
+    pub fn set_related(&mut self, related: Vec<RelatedLink>) {
+        self.extra.related = related;
+    }
+
     pub fn update_link(self, link: &mut Link) {
         let Self {
             title,
@@ -426,6 +522,7 @@ impl Frontmatter {
                 read_at,
                 published_at,
                 hidden,
+                summary,
 
                 meta,
 
@@ -440,33 +537,42 @@ impl Frontmatter {
         link.hidden = hidden;
         link.tags = taxonomies.remove("tags").unwrap_or_else(Vec::new);
         link.notes = if notes.trim().is_empty() { None } else { Some(notes) };
-
-        'found_at: {
-            if let Some(found_at) = found_at {
-                let Some(found_at) = NaiveDate::parse_from_str(found_at.as_str(), "%Y-%m-%d").ok() else { break 'found_at };
-                let Some(found_at) = found_at.and_hms_milli_opt(0, 0, 0, 0) else { break 'found_at };
-                let Some(found_at) = found_at.and_local_timezone(Utc).earliest() else { break 'found_at };
-                link.found_at = Some(found_at);
-            }
-        }
-
-        'read_at: {
-            if let Some(read_at) = read_at {
-                let Some(read_at) = NaiveDate::parse_from_str(read_at.as_str(), "%Y-%m-%d").ok() else { break 'read_at };
-                let Some(read_at) = read_at.and_hms_milli_opt(0, 0, 0, 0) else { break 'read_at };
-                let Some(read_at) = read_at.and_local_timezone(Utc).earliest() else { break 'read_at };
-                link.read_at = Some(read_at);
+        link.summary = summary.filter(|xs| !xs.trim().is_empty());
+        link.from_filename = from_filename.or(link.from_filename.take());
+        link.image = image.or(link.image.take());
+
+        let timezone = DisplayTimezone::from_env();
+        for (value, field) in [
+            (found_at, &mut link.found_at),
+            (read_at, &mut link.read_at),
+            (published_at, &mut link.published_at),
+        ] {
+            if let Some(at) = value.and_then(|value| parse_timestamp(&value, *field, timezone)) {
+                *field = Some(at);
             }
         }
+    }
+}
 
-        'published_at: {
-            if let Some(published_at) = published_at {
-                let Some(published_at) = NaiveDate::parse_from_str(published_at.as_str(), "%Y-%m-%d").ok() else { break 'published_at };
-                let Some(published_at) = published_at.and_hms_milli_opt(0, 0, 0, 0) else { break 'published_at };
-                let Some(published_at) = published_at.and_local_timezone(Utc).earliest() else { break 'published_at };
-                link.published_at = Some(published_at);
-            }
+/// Read a frontmatter timestamp, written in RFC 3339 or, by older exports or by hand, as a bare
+/// `%Y-%m-%d` date. A bare date that falls on the day of the `current` timestamp, in UTC (as older
+/// exports wrote it) or in `timezone`, keeps that timestamp; any other is midnight in `timezone`.
+/// Anything else is ignored.
+fn parse_timestamp(
+    value: &str,
+    current: Option<DateTime<Utc>>,
+    timezone: DisplayTimezone,
+) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value.trim()) {
+        return Some(at.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()?;
+    match current {
+        Some(current) if current.date_naive() == date || timezone.date(current) == date => {
+            Some(current)
         }
+        _ => timezone.midnight(date),
     }
 }
 
@@ -481,8 +587,10 @@ impl TryFrom<Link> for Frontmatter {
             .or_else(|| link.found_at())
             .unwrap_or_else(Utc::now);
 
-        let date = date.format("%Y-%m-%d").to_string();
-        let mut taxonomies = HashMap::new();
+        let timezone = DisplayTimezone::from_env();
+        let date = timezone.date(date).format("%Y-%m-%d").to_string();
+        let timestamp = |at: DateTime<Utc>| timezone.localize(at).to_rfc3339();
+        let mut taxonomies = BTreeMap::new();
 
         // This is a little redundant since we do this on import now, but older link entries might contain
         // empty strings in their tags.
@@ -505,11 +613,9 @@ impl TryFrom<Link> for Frontmatter {
                 url: link.url.parse::<url::Url>()?.into(),
                 title: link.title,
                 via: link.via.map(|xs| xs.into()),
-                found_at: link.found_at.map(|xs| xs.format("%Y-%m-%d").to_string()),
-                read_at: link.read_at.map(|xs| xs.format("%Y-%m-%d").to_string()),
-                published_at: link
-                    .published_at
-                    .map(|xs| xs.format("%Y-%m-%d").to_string()),
+                found_at: link.found_at.map(timestamp),
+                read_at: link.read_at.map(timestamp),
+                published_at: link.published_at.map(timestamp),
 
                 meta: link
                     .meta
@@ -521,7 +627,50 @@ impl TryFrom<Link> for Frontmatter {
                 from_filename: link.from_filename,
                 image: link.image,
                 hidden: link.hidden,
+                summary: link.summary,
+                related: Vec::new(),
+                digest: None,
             },
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontmatter_round_trips_timestamps() -> eyre::Result<()> {
+        let mut link = Link::new("https://example.com/post", "A Post");
+        link.read_at = Some("2023-04-05T23:07:08.125Z".parse()?);
+        link.found_at = Some("2023-04-01T12:00:00Z".parse()?);
+
+        let frontmatter: Frontmatter = link.clone().try_into()?;
+        let document = format!("+++\n{}\n+++\n", toml::to_string_pretty(&frontmatter)?);
+
+        let mut edited = link.clone();
+        edited.read_at = None;
+        document.parse::<Frontmatter>()?.update_link(&mut edited);
+        assert_eq!(edited.read_at, link.read_at);
+
+        // Older exports wrote bare dates, which keep the time of a timestamp on that day.
+        let document = document
+            .lines()
+            .map(|line| match line.split_once(" = ") {
+                Some(("found_at", _)) => "found_at = \"2023-04-01\"".to_string(),
+                Some(("read_at", _)) => "read_at = \"2023-04-07\"".to_string(),
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut edited = link.clone();
+        document.parse::<Frontmatter>()?.update_link(&mut edited);
+        assert_eq!(edited.found_at, link.found_at);
+        let read_at = edited.read_at.expect("read_at was kept");
+        assert_eq!(
+            DisplayTimezone::from_env().date(read_at),
+            NaiveDate::from_ymd_opt(2023, 4, 7).unwrap()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+};
+
+use crate::{
+    enrichment::merge_link, src_key, stores::LinkFixture, text_key, BlobStore, Link, LinkQuery,
+    LinkReader, LinkWriter,
+};
+
+/// Archives are zstd-compressed, which is how they are told apart from plain JSON lines.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Where the JSON lines are kept in an archive. Each link's source data and extracted text are
+/// kept at `sources/<name>` and `text/<name>`, named by the sha256 of its url.
+const LINKS_PATH: &str = "links.jsonl";
+
+/// One line of a dump: a link, or a tag alias.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum DumpRecord {
+    Link(Box<LinkFixture>),
+    TagAlias { alias: String, tag: String },
+}
+
+/// How many links, tag aliases, and blobs of source data or extracted text were dumped or
+/// restored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DumpSummary {
+    pub links: u64,
+    pub tag_aliases: u64,
+    pub blobs: u64,
+}
+
+/// Write every link and tag alias in the store to `out` as JSON lines. Source data and extracted
+/// text are left out; [`dump_archive`] includes them.
+pub async fn dump_jsonl<S>(store: &S, out: impl Write) -> eyre::Result<DumpSummary>
+where
+    S: LinkReader + LinkQuery + Send + Sync,
+{
+    let mut out = BufWriter::new(out);
+    let (summary, _) = write_jsonl(store, &mut out).await?;
+    out.flush()?;
+    Ok(summary)
+}
+
+/// Write a zstd-compressed tar archive holding the JSON lines [`dump_jsonl`] writes, along with
+/// each link's source data and extracted text from the blob store.
+pub async fn dump_archive<S>(
+    store: &S,
+    blobs: &(dyn BlobStore + Send + Sync),
+    out: impl Write,
+) -> eyre::Result<DumpSummary>
+where
+    S: LinkReader + LinkQuery + Send + Sync,
+{
+    let mut links = Vec::new();
+    let (mut summary, urls) = write_jsonl(store, &mut links).await?;
+
+    let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+    let mut archive = tar::Builder::new(zstd::Encoder::new(out, 0)?);
+    append_file(&mut archive, LINKS_PATH, &links, mtime)?;
+    for url in urls {
+        for (directory, key) in [("sources", src_key(&url)), ("text", text_key(&url))] {
+            match blobs.read(&key).await {
+                Ok(Some(data)) => {
+                    let path = format!("{}/{}", directory, blob_name(&url));
+                    append_file(&mut archive, &path, &data, mtime)?;
+                    summary.blobs += 1;
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("skipping unreadable {}: {}", key, e),
+            }
+        }
+    }
+    archive.into_inner()?.finish()?;
+
+    Ok(summary)
+}
+
+/// Load a dump written by [`dump_jsonl`] or [`dump_archive`]. Links that are already stored are
+/// merged the way importing them again would merge them: the dump's titles, notes, tags, and via
+/// take precedence, while stored timestamps and fetched data are kept. Tag aliases and blobs that
+/// are already stored are kept as well.
+///
+/// With `replace`, stored links, tag aliases, and blobs are instead replaced by the dump's, so
+/// restoring brings them back to the state they were dumped in.
+pub async fn restore<S>(
+    store: &S,
+    blobs: &(dyn BlobStore + Send + Sync),
+    input: impl Read,
+    replace: bool,
+) -> eyre::Result<DumpSummary>
+where
+    S: LinkReader + LinkWriter + LinkQuery + Send + Sync,
+{
+    let mut input = BufReader::new(input);
+    if !input.fill_buf()?.starts_with(&ZSTD_MAGIC) {
+        let (summary, _) = read_jsonl(store, input, replace).await?;
+        return Ok(summary);
+    }
+
+    let mut archive = tar::Archive::new(zstd::Decoder::new(input)?);
+    let mut summary = DumpSummary::default();
+    let mut urls = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+
+        if path == LINKS_PATH {
+            let restored;
+            (restored, urls) = read_jsonl(store, data.as_slice(), replace).await?;
+            summary.links += restored.links;
+            summary.tag_aliases += restored.tag_aliases;
+            continue;
+        }
+
+        // Archives written by `dump_archive` always list their links first.
+        let key = match path.split_once('/') {
+            Some(("sources", name)) => urls.get(name).map(|url: &String| src_key(url)),
+            Some(("text", name)) => urls.get(name).map(|url: &String| text_key(url)),
+            _ => None,
+        };
+        let Some(key) = key else {
+            continue;
+        };
+
+        if replace || blobs.read(&key).await.ok().flatten().is_none() {
+            blobs.write(&key, &data).await?;
+            summary.blobs += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Write links and then tag aliases as JSON lines, returning the urls written.
+async fn write_jsonl<S>(store: &S, out: &mut impl Write) -> eyre::Result<(DumpSummary, Vec<String>)>
+where
+    S: LinkReader + LinkQuery + Send + Sync,
+{
+    let mut summary = DumpSummary::default();
+    let mut urls = Vec::new();
+
+    let mut links = store.values().await?;
+    while let Some(mut link) = links.next().await {
+        link.src = None;
+        urls.push(link.url().to_string());
+        serde_json::to_writer(&mut *out, &DumpRecord::Link(Box::new(link.into())))?;
+        out.write_all(b"\n")?;
+        summary.links += 1;
+    }
+    drop(links);
+
+    for (alias, tag) in store.tag_aliases().await?.iter() {
+        let record = DumpRecord::TagAlias {
+            alias: alias.to_string(),
+            tag: tag.to_string(),
+        };
+        serde_json::to_writer(&mut *out, &record)?;
+        out.write_all(b"\n")?;
+        summary.tag_aliases += 1;
+    }
+
+    Ok((summary, urls))
+}
+
+/// Restore JSON lines, returning the urls of the restored links keyed by their [`blob_name`].
+async fn read_jsonl<S>(
+    store: &S,
+    input: impl BufRead,
+    replace: bool,
+) -> eyre::Result<(DumpSummary, HashMap<String, String>)>
+where
+    S: LinkReader + LinkWriter + LinkQuery + Send + Sync,
+{
+    let aliases: HashSet<String> = if replace {
+        HashSet::new()
+    } else {
+        store
+            .tag_aliases()
+            .await?
+            .iter()
+            .map(|(alias, _)| alias.to_string())
+            .collect()
+    };
+
+    let mut summary = DumpSummary::default();
+    let mut urls = HashMap::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record: DumpRecord = serde_json::from_str(&line).map_err(|e| {
+            eyre::eyre!(
+                "line {} of the dump is not a link or tag alias: {}",
+                number + 1,
+                e
+            )
+        })?;
+
+        match record {
+            DumpRecord::Link(link) => {
+                let link = Link::from(*link);
+                urls.insert(blob_name(link.url()), link.url().to_string());
+                let link = match store.get(link.url()).await? {
+                    Some(known_link) if !replace => merge_link(known_link, link),
+                    _ => link,
+                };
+                store.write(link).await?;
+                summary.links += 1;
+            }
+
+            DumpRecord::TagAlias { alias, tag } => {
+                if !aliases.contains(&alias.to_lowercase()) {
+                    store.set_tag_alias(&alias, &tag).await?;
+                    summary.tag_aliases += 1;
+                }
+            }
+        }
+    }
+
+    Ok((summary, urls))
+}
+
+fn append_file(
+    archive: &mut tar::Builder<impl Write>,
+    path: &str,
+    data: &[u8],
+    mtime: u64,
+) -> eyre::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    archive.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+fn blob_name(url: &str) -> String {
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|xs| format!("{:02x}", xs))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CacacheBlobStore, InMemoryStore, Via};
+
+    fn links() -> Vec<Link> {
+        let mut article = Link::new("https://example.com/article", "an article");
+        article.via = Some(Via::Friend("alice".to_string()));
+        article.tags = vec!["lang/rust".to_string(), "reading".to_string()];
+        article.found_at = Some("2023-01-02T03:04:05.678Z".parse().unwrap());
+        article.meta = Some(HashMap::from([(
+            "og:title".to_string(),
+            vec!["An Article".to_string()],
+        )]));
+        article.http_headers = Some(HashMap::from([(
+            "content-type".to_string(),
+            vec!["text/html".to_string()],
+        )]));
+        article.src_truncated = Some("over the limit".to_string());
+
+        let mut hidden = Link::new("https://example.com/hidden", "hidden");
+        hidden.hidden = true;
+
+        vec![article, hidden]
+    }
+
+    #[tokio::test]
+    async fn archives_round_trip_links_and_blobs() -> eyre::Result<()> {
+        let store = InMemoryStore::with_links(links());
+        store.set_tag_alias("golang", "lang/go").await?;
+        let dir = tempfile::tempdir()?;
+        let blobs = CacacheBlobStore::new(dir.path().join("before"));
+        let url = "https://example.com/article";
+        blobs.write(&src_key(url), b"<html>source</html>").await?;
+        blobs.write(&text_key(url), b"source").await?;
+
+        let mut archive = Vec::new();
+        let dumped = dump_archive(&store, &blobs, &mut archive).await?;
+        assert_eq!(
+            dumped,
+            DumpSummary {
+                links: 2,
+                tag_aliases: 1,
+                blobs: 2,
+            }
+        );
+
+        let restored_store = InMemoryStore::new();
+        let restored_blobs = CacacheBlobStore::new(dir.path().join("after"));
+        let restored = restore(&restored_store, &restored_blobs, archive.as_slice(), false).await?;
+        assert_eq!(restored, dumped);
+        assert_eq!(restored_store.to_json().await?, store.to_json().await?);
+        assert_eq!(
+            restored_blobs.read(&src_key(url)).await?.as_deref(),
+            Some(&b"<html>source</html>"[..])
+        );
+        assert_eq!(
+            restored_blobs.read(&text_key(url)).await?.as_deref(),
+            Some(&b"source"[..])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restoring_merges_into_existing_links() -> eyre::Result<()> {
+        let dumped = InMemoryStore::with_links(links());
+        dumped.set_tag_alias("golang", "lang/go").await?;
+        let mut jsonl = Vec::new();
+        dump_jsonl(&dumped, &mut jsonl).await?;
+
+        let mut existing = Link::new("https://example.com/article", "an old title");
+        existing.tags = vec!["old".to_string()];
+        existing.found_at = Some("2020-01-01T00:00:00Z".parse().unwrap());
+        let store = InMemoryStore::with_links([existing]);
+        store.set_tag_alias("golang", "lang/golang").await?;
+
+        let dir = tempfile::tempdir()?;
+        let blobs = CacacheBlobStore::new(dir.path());
+        let restored = restore(&store, &blobs, jsonl.as_slice(), false).await?;
+        assert_eq!(restored.links, 2);
+        assert_eq!(restored.tag_aliases, 0);
+        assert_eq!(
+            store.tag_aliases().await?.normalize("golang").as_deref(),
+            Some("lang/golang")
+        );
+
+        let link = store.get("https://example.com/article").await?.unwrap();
+        let mut tags = link.tags.clone();
+        tags.sort();
+        assert_eq!(link.title(), Some("an article"));
+        assert_eq!(tags, ["lang/rust", "old", "reading"]);
+        assert_eq!(link.found_at, Some("2020-01-01T00:00:00Z".parse().unwrap()));
+        assert!(link.meta.is_some());
+        assert!(
+            store
+                .get("https://example.com/hidden")
+                .await?
+                .unwrap()
+                .hidden
+        );
+
+        assert!(
+            restore(&store, &blobs, &b"{\"title\": \"no url\"}\n"[..], false)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restoring_replaces_existing_links() -> eyre::Result<()> {
+        let dumped = InMemoryStore::with_links(links());
+        dumped.set_tag_alias("golang", "lang/go").await?;
+        let mut jsonl = Vec::new();
+        dump_jsonl(&dumped, &mut jsonl).await?;
+
+        let mut existing = Link::new("https://example.com/article", "an old title");
+        existing.tags = vec!["old".to_string()];
+        existing.found_at = Some("2020-01-01T00:00:00Z".parse().unwrap());
+        existing.hidden = true;
+        let store = InMemoryStore::with_links([existing]);
+        store.set_tag_alias("golang", "lang/golang").await?;
+
+        let dir = tempfile::tempdir()?;
+        let blobs = CacacheBlobStore::new(dir.path());
+        let restored = restore(&store, &blobs, jsonl.as_slice(), true).await?;
+        assert_eq!(restored.links, 2);
+        assert_eq!(restored.tag_aliases, 1);
+        assert_eq!(store.to_json().await?, dumped.to_json().await?);
+
+        let link = store.get("https://example.com/article").await?.unwrap();
+        assert_eq!(link.title(), Some("an article"));
+        assert_eq!(link.tags, ["lang/rust", "reading"]);
+        assert!(!link.hidden);
+        assert_eq!(
+            store.tag_aliases().await?.normalize("golang").as_deref(),
+            Some("lang/go")
+        );
+
+        Ok(())
+    }
+}
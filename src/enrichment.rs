@@ -12,38 +12,21 @@ pub(crate) async fn enrich_link<Store>(
 where
     Store: LinkReader + Send + Sync,
 {
-    if let Some(mut known_link) = store.get(link.url.as_str()).await? {
-        known_link.read_at = known_link.read_at.or(link.read_at).or_else(|| {
+    if let Some(known_link) = store.get(link.url.as_str()).await? {
+        if link.read_at.is_none() {
             if let Some(notes) = link.notes() {
                 if !notes.trim().is_empty() {
-                    known_link
-                        .read_at
-                        .or(link_source.modified)
-                        .or_else(|| Some(Utc::now()))
-                } else {
-                    None
+                    link.read_at = link_source.modified.or_else(|| Some(Utc::now()));
                 }
-            } else {
-                None
             }
-        });
-
-        known_link.found_at = known_link
-            .found_at
-            .or(link.found_at)
-            .or(link_source.created);
+        }
 
-        known_link.from_filename = known_link
+        link.found_at = link.found_at.or(link_source.created);
+        link.from_filename = link
             .from_filename
-            .or(link.from_filename)
             .or_else(|| link_source.filename_string());
 
-        known_link.title = link.title.or(known_link.title);
-        known_link.notes = link.notes.or(known_link.notes);
-        known_link.tags = link.tags.into_iter().chain(known_link.tags.into_iter()).collect::<HashSet<_>>().into_iter().collect();
-        known_link.via = link.via.or(known_link.via);
-
-        link = known_link;
+        link = merge_link(known_link, link);
     } else {
         link.found_at = link_source.modified.or(link_source.created);
         link.from_filename = link_source.filename_string();
@@ -57,3 +40,37 @@ where
 
     Ok(link)
 }
+
+/// Merge a link into the stored version of itself. What the incoming link says about the link
+/// itself -- its title, notes, tags, and provenance -- takes precedence, while the stored link's
+/// record of when it was found and read, and everything learned by fetching it, is kept. Stored
+/// fields that are missing are filled in from the incoming link.
+pub(crate) fn merge_link(mut known_link: Link, link: Link) -> Link {
+    known_link.read_at = known_link.read_at.or(link.read_at);
+    known_link.found_at = known_link.found_at.or(link.found_at);
+    known_link.from_filename = known_link.from_filename.or(link.from_filename);
+
+    known_link.title = link.title.or(known_link.title);
+    known_link.notes = link.notes.or(known_link.notes);
+    known_link.tags = link.tags.into_iter().chain(known_link.tags.into_iter()).collect::<HashSet<_>>().into_iter().collect();
+    known_link.via = link.via.or(known_link.via);
+
+    known_link.published_at = known_link.published_at.or(link.published_at);
+    known_link.image = known_link.image.or(link.image);
+    known_link.meta = known_link.meta.or(link.meta);
+    if known_link.src.is_none() {
+        known_link.src = link.src;
+        known_link.src_truncated = link.src_truncated;
+    }
+    known_link.extracted_text = known_link.extracted_text.or(link.extracted_text);
+    known_link.last_fetched = known_link.last_fetched.or(link.last_fetched);
+    known_link.last_processed = known_link.last_processed.or(link.last_processed);
+    known_link.http_headers = known_link.http_headers.or(link.http_headers);
+    known_link.hidden |= link.hidden;
+    if known_link.summary.is_none() {
+        known_link.summary = link.summary;
+        known_link.summarized_at = link.summarized_at;
+    }
+
+    known_link
+}
@@ -0,0 +1,430 @@
+mod format;
+mod reconcile;
+mod vault;
+
+pub use format::*;
+pub use reconcile::*;
+pub use vault::EDIT_MARKER;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+/// The name of the manifest an export keeps in its output directory.
+pub(crate) const MANIFEST: &str = ".likelike-export.json";
+
+/// The files an export wrote, so the next export knows which files are its own to remove.
+#[derive(Default, Serialize, Deserialize)]
+struct Manifest {
+    files: BTreeSet<String>,
+
+    /// What each link's file held when it was written, so that `import-export` can tell whether
+    /// the file, the link, or both changed since.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    links: BTreeMap<String, ExportedLink>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ExportedLink {
+    /// The link's url as stored, which the file may hold normalized. Older manifests lack it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    /// The sha256 of the file's contents.
+    sha256: String,
+    /// The [`crate::LinkDiff::fingerprint`] of the link the file was rendered from.
+    fingerprint: String,
+}
+
+impl Manifest {
+    fn read(path: &Path) -> eyre::Result<Self> {
+        match std::fs::read(path) {
+            Ok(manifest) => Ok(serde_json::from_slice(&manifest)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Whether `filename` stays inside the directory it's relative to: it must not be absolute or
+/// climb out with `..`.
+fn is_relative(filename: &str) -> bool {
+    Path::new(filename)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+fn sha256(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// What an export changed in its output directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: u64,
+}
+
+impl Display for ExportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (marker, files) in [
+            ("+", &self.added),
+            ("~", &self.updated),
+            ("-", &self.removed),
+        ] {
+            for file in files {
+                writeln!(f, "{} {}", marker, file)?;
+            }
+        }
+
+        writeln!(
+            f,
+            "{} added, {} updated, {} removed, {} unchanged",
+            self.added.len(),
+            self.updated.len(),
+            self.removed.len(),
+            self.unchanged
+        )
+    }
+}
+
+/// Writes exported files into a directory incrementally. Files whose content is unchanged are
+/// left alone, so their modification times stay put, and files that the previous export wrote
+/// but this one did not are removed. Files the export never wrote are never touched.
+pub struct ExportWriter {
+    directory: PathBuf,
+    manifest: PathBuf,
+    previous: BTreeSet<String>,
+    previous_links: BTreeMap<String, ExportedLink>,
+    written: BTreeSet<String>,
+    links: BTreeMap<String, ExportedLink>,
+    summary: ExportSummary,
+    marker: Option<&'static str>,
+}
+
+impl ExportWriter {
+    /// Write into `directory`, keeping the manifest of what was written inside it.
+    pub fn open(directory: impl Into<PathBuf>) -> eyre::Result<Self> {
+        let directory = directory.into();
+        let manifest = directory.join(MANIFEST);
+        Self::open_with_manifest(directory, manifest)
+    }
+
+    /// Write into `directory`, keeping the manifest at `manifest` instead, for directories that
+    /// are published as they are.
+    pub fn open_with_manifest(
+        directory: impl Into<PathBuf>,
+        manifest: impl Into<PathBuf>,
+    ) -> eyre::Result<Self> {
+        let (directory, manifest) = (directory.into(), manifest.into());
+        std::fs::create_dir_all(&directory)?;
+
+        let previous = Manifest::read(&manifest)?;
+
+        Ok(Self {
+            directory,
+            manifest,
+            previous: previous.files,
+            previous_links: previous.links,
+            written: BTreeSet::new(),
+            links: BTreeMap::new(),
+            summary: ExportSummary::default(),
+            marker: None,
+        })
+    }
+
+    /// Keep whatever follows the line holding `marker` in files being rewritten, appending it to
+    /// their new contents, which should end with that line. Stale files with anything below the
+    /// marker are left in place rather than removed, and are no longer tracked.
+    pub fn preserving_edits(mut self, marker: &'static str) -> Self {
+        self.marker = Some(marker);
+        self
+    }
+
+    /// Write a file, relative to the output directory, if its content has changed.
+    pub fn write(&mut self, filename: &str, contents: &[u8]) -> eyre::Result<()> {
+        self.write_contents(filename, contents)?;
+        Ok(())
+    }
+
+    /// Write a link's file like [`ExportWriter::write`], recording the link it was rendered from
+    /// by its url and [`crate::LinkDiff::fingerprint`].
+    pub fn write_link(
+        &mut self,
+        filename: &str,
+        contents: &[u8],
+        url: &str,
+        fingerprint: String,
+    ) -> eyre::Result<()> {
+        let contents = self.write_contents(filename, contents)?;
+        self.links.insert(
+            filename.to_string(),
+            ExportedLink {
+                url: Some(url.to_string()),
+                sha256: sha256(&contents),
+                fingerprint,
+            },
+        );
+        Ok(())
+    }
+
+    /// Keep the file the previous export wrote for the link at `url`, and its record, as they
+    /// are, for a link that could not be rendered this time. Returns whether there was one.
+    pub fn keep_link(&mut self, url: &str) -> bool {
+        let previous = self
+            .previous_links
+            .iter()
+            .find(|(_, exported)| exported.url.as_deref() == Some(url));
+        let Some((filename, exported)) = previous else {
+            return false;
+        };
+
+        self.written.insert(filename.clone());
+        self.links.insert(filename.clone(), exported.clone());
+        self.summary.unchanged += 1;
+        true
+    }
+
+    /// Returns the file's contents as written, including any edits kept below the marker.
+    fn write_contents(&mut self, filename: &str, contents: &[u8]) -> eyre::Result<Vec<u8>> {
+        if !is_relative(filename) {
+            return Err(eyre::eyre!(
+                "refusing to write {}, which is outside the output directory",
+                filename
+            ));
+        }
+
+        if !self.written.insert(filename.to_string()) {
+            eprintln!(
+                "more than one link exports to {}; keeping the last",
+                filename
+            );
+        }
+
+        let path = self.directory.join(filename);
+        let existing = match std::fs::read(&path) {
+            Ok(existing) => Some(existing),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut contents = contents.to_vec();
+        if let Some(edits) = existing
+            .as_deref()
+            .and_then(|existing| self.edits(existing))
+        {
+            contents.extend_from_slice(edits);
+        }
+
+        match existing {
+            Some(existing) if existing == contents => {
+                self.summary.unchanged += 1;
+                return Ok(contents);
+            }
+            Some(_) => self.summary.updated.push(filename.to_string()),
+            None => self.summary.added.push(filename.to_string()),
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &contents)?;
+        Ok(contents)
+    }
+
+    /// Remove the previous export's files that were not written this time and record what was.
+    pub fn finish(mut self) -> eyre::Result<ExportSummary> {
+        for filename in self.previous.difference(&self.written) {
+            // The manifest may have been edited by hand.
+            if !is_relative(filename) {
+                eprintln!(
+                    "not removing {}, which is outside the output directory",
+                    filename
+                );
+                continue;
+            }
+
+            let path = self.directory.join(filename);
+            if self.marker.is_some() {
+                let edited = std::fs::read(&path)
+                    .ok()
+                    .and_then(|existing| {
+                        self.edits(&existing)
+                            .map(|edits| !edits.trim_ascii().is_empty())
+                    })
+                    .unwrap_or_default();
+                if edited {
+                    eprintln!("keeping {}, which has edits below the marker", filename);
+                    continue;
+                }
+            }
+
+            match std::fs::remove_file(path) {
+                Ok(()) => self.summary.removed.push(filename.clone()),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let manifest = Manifest {
+            files: self.written,
+            links: self.links,
+        };
+        std::fs::write(&self.manifest, serde_json::to_vec_pretty(&manifest)?)?;
+
+        Ok(self.summary)
+    }
+
+    /// Whatever follows the line holding the marker, if there is a marker and the file has it.
+    fn edits<'a>(&self, existing: &'a [u8]) -> Option<&'a [u8]> {
+        let marker = self.marker?.as_bytes();
+        let start = existing
+            .windows(marker.len())
+            .position(|window| window == marker)?;
+        let end = existing[start..]
+            .iter()
+            .position(|&xs| xs == b'\n')
+            .map_or(existing.len(), |newline| start + newline + 1);
+        Some(&existing[end..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_touches_changed_and_stale_files() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("_index.md"), "not ours")?;
+
+        let mut export = ExportWriter::open(dir.path())?;
+        export.write("a.md", b"a")?;
+        export.write("b.md", b"b")?;
+        export.write("c.md", b"c")?;
+        let summary = export.finish()?;
+        assert_eq!(summary.added, ["a.md", "b.md", "c.md"]);
+
+        let modified = std::fs::metadata(dir.path().join("a.md"))?.modified()?;
+        let mut export = ExportWriter::open(dir.path())?;
+        export.write("a.md", b"a")?;
+        export.write("b.md", b"b, changed")?;
+        export.write("d.md", b"d")?;
+        let summary = export.finish()?;
+        assert_eq!(
+            summary,
+            ExportSummary {
+                added: vec!["d.md".to_string()],
+                updated: vec!["b.md".to_string()],
+                removed: vec!["c.md".to_string()],
+                unchanged: 1,
+            }
+        );
+
+        assert_eq!(
+            std::fs::metadata(dir.path().join("a.md"))?.modified()?,
+            modified
+        );
+        assert!(!dir.path().join("c.md").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("_index.md"))?,
+            "not ours"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_edits_below_the_marker() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let marker = "<!-- marker -->";
+        let open = || -> eyre::Result<ExportWriter> {
+            Ok(ExportWriter::open(dir.path())?.preserving_edits(marker))
+        };
+
+        let mut export = open()?;
+        export.write("a.md", b"a\n<!-- marker -->\n")?;
+        export.write("b.md", b"b\n<!-- marker -->\n")?;
+        export.write("c.md", b"c\n<!-- marker -->\n")?;
+        export.finish()?;
+
+        let edit = |filename: &str, edit: &str| -> eyre::Result<()> {
+            let path = dir.path().join(filename);
+            let contents = std::fs::read_to_string(&path)?;
+            Ok(std::fs::write(path, contents + edit)?)
+        };
+        edit("a.md", "mine\n")?;
+        edit("c.md", "also mine\n")?;
+
+        let mut export = open()?;
+        export.write("a.md", b"a, changed\n<!-- marker -->\n")?;
+        let summary = export.finish()?;
+        assert_eq!(summary.updated, ["a.md"]);
+        assert_eq!(summary.removed, ["b.md"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.md"))?,
+            "a, changed\n<!-- marker -->\nmine\n"
+        );
+        assert!(dir.path().join("c.md").exists());
+
+        let mut export = open()?;
+        export.write("a.md", b"a, changed\n<!-- marker -->\n")?;
+        assert_eq!(export.finish()?.unchanged, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn stays_inside_the_output_directory() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("output");
+        let outside = dir.path().join("outside.md");
+        std::fs::write(&outside, "not ours")?;
+
+        let mut export = ExportWriter::open(&output)?;
+        assert!(export.write("../outside.md", b"ours").is_err());
+        assert!(export.write(outside.to_str().unwrap(), b"ours").is_err());
+        export.write("a.md", b"a")?;
+        export.finish()?;
+
+        let manifest = Manifest {
+            files: BTreeSet::from([
+                "a.md".to_string(),
+                "../outside.md".to_string(),
+                outside.to_string_lossy().into_owned(),
+            ]),
+            links: BTreeMap::new(),
+        };
+        std::fs::write(output.join(MANIFEST), serde_json::to_vec(&manifest)?)?;
+        let summary = ExportWriter::open(&output)?.finish()?;
+        assert_eq!(summary.removed, ["a.md"]);
+        assert_eq!(std::fs::read_to_string(&outside)?, "not ours");
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_files_of_links_that_fail_to_render() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut export = ExportWriter::open(dir.path())?;
+        export.write_link("a.md", b"a", "https://a.com/", "a".to_string())?;
+        export.write_link("b.md", b"b", "https://b.com/", "b".to_string())?;
+        export.finish()?;
+
+        let mut export = ExportWriter::open(dir.path())?;
+        export.write_link("a.md", b"a", "https://a.com/", "a".to_string())?;
+        assert!(export.keep_link("https://b.com/"));
+        assert!(!export.keep_link("https://c.com/"));
+        let summary = export.finish()?;
+        assert!(summary.removed.is_empty());
+        assert_eq!(std::fs::read_to_string(dir.path().join("b.md"))?, "b");
+
+        let manifest = Manifest::read(&dir.path().join(MANIFEST))?;
+        assert!(manifest.files.contains("b.md"));
+        assert_eq!(manifest.links["b.md"].fingerprint, "b");
+        Ok(())
+    }
+}
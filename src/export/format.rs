@@ -0,0 +1,458 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use slugify::slugify;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display},
+    path::Path,
+    str::FromStr,
+};
+use tera::{Context, Tera};
+
+use super::vault::{Vault, EDIT_MARKER};
+use crate::{
+    query::link_host, DisplayTimezone, Frontmatter, FrontmatterExtra, Link, RelatedLink, Via,
+};
+
+const FILENAME_TEMPLATE: &str = "filename";
+
+/// The static site generators links can be exported for, plus user-supplied templates.
+///
+/// Zola documents use TOML frontmatter with tags under `taxonomies` and link metadata under
+/// `extra`. Hugo documents use TOML (or YAML) frontmatter with link metadata under `params`.
+/// Jekyll and Eleventy documents use YAML frontmatter with link metadata under `link`, since both
+/// reserve `url` for themselves.
+///
+/// Obsidian and Logseq exports write a note per link into a vault, with link metadata as note
+/// properties, tags as `#tags`, and wikilinks to a note for each friend links came via and to the
+/// link dump each link was imported from. Everything below [`EDIT_MARKER`] in a note is kept
+/// when exporting again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Zola,
+    Hugo,
+    HugoYaml,
+    Jekyll,
+    Eleventy,
+    Obsidian,
+    Logseq,
+    Template,
+}
+
+impl ExportFormat {
+    /// The filename template used unless another is given. Jekyll only picks up posts named
+    /// after their date.
+    fn default_filename(&self) -> &'static str {
+        match self {
+            ExportFormat::Jekyll => "_posts/{{ date }}-{{ url_slug }}.md",
+            _ => match self.vault() {
+                Some(vault) => vault.default_filename(),
+                None => "{{ url_slug }}.md",
+            },
+        }
+    }
+
+    fn vault(&self) -> Option<Vault> {
+        match self {
+            ExportFormat::Obsidian => Some(Vault::Obsidian),
+            ExportFormat::Logseq => Some(Vault::Logseq),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "zola" => ExportFormat::Zola,
+            "hugo" | "hugo-toml" => ExportFormat::Hugo,
+            "hugo-yaml" => ExportFormat::HugoYaml,
+            "jekyll" => ExportFormat::Jekyll,
+            "eleventy" | "11ty" => ExportFormat::Eleventy,
+            "obsidian" => ExportFormat::Obsidian,
+            "logseq" => ExportFormat::Logseq,
+            "template" => ExportFormat::Template,
+            _ => eyre::bail!(
+                "unknown export format {:?}; expected zola, hugo, hugo-yaml, jekyll, eleventy, obsidian, logseq, or template",
+                s
+            ),
+        })
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportFormat::Zola => "zola",
+            ExportFormat::Hugo => "hugo",
+            ExportFormat::HugoYaml => "hugo-yaml",
+            ExportFormat::Jekyll => "jekyll",
+            ExportFormat::Eleventy => "eleventy",
+            ExportFormat::Obsidian => "obsidian",
+            ExportFormat::Logseq => "logseq",
+            ExportFormat::Template => "template",
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct HugoFrontmatter<'a> {
+    title: &'a str,
+    slug: &'a str,
+    date: &'a str,
+    tags: &'a [String],
+    params: &'a FrontmatterExtra,
+}
+
+#[derive(Serialize)]
+struct PostFrontmatter<'a> {
+    title: &'a str,
+    date: &'a str,
+    tags: &'a [String],
+    link: &'a FrontmatterExtra,
+}
+
+/// Renders links into documents for an [`ExportFormat`].
+///
+/// Filenames are rendered from a [Tera](https://keats.github.io/tera/) template, as are the
+/// documents of [`ExportFormat::Template`]. Both templates see:
+///
+/// - `link`: every field of the link except its source data, including `extracted_text`
+/// - `related`: related links, each with a `url`, `title`, `score`, and `reasons`
+/// - `slug` and `url_slug`: the link's title and normalized url, slugified
+/// - `date`, `year`, `month`, and `day`: when the link was published, or else found
+/// - `host`: the host of the link's url
+///
+/// A `slugify` filter is available as well. As in Zola, templates whose names end in `.html` or
+/// `.xml` are autoescaped.
+pub struct Exporter {
+    format: ExportFormat,
+    tera: Tera,
+    document: Option<String>,
+    timezone: DisplayTimezone,
+}
+
+impl Exporter {
+    /// `filename` is a template for each document's path, relative to the output directory. By
+    /// default, documents are named after their slugified url, under `_posts/` and prefixed with
+    /// their date for Jekyll. `template` is the document template for
+    /// [`ExportFormat::Template`], and is not used otherwise.
+    pub fn new(
+        format: ExportFormat,
+        filename: Option<&str>,
+        template: Option<&Path>,
+    ) -> eyre::Result<Self> {
+        let mut tera = Tera::default();
+        tera.register_filter("slugify", slugify_filter);
+        tera.add_raw_template(
+            FILENAME_TEMPLATE,
+            filename.unwrap_or_else(|| format.default_filename()),
+        )?;
+
+        let document = match (format, template) {
+            (ExportFormat::Template, Some(template)) => {
+                let name = template
+                    .file_name()
+                    .map(|xs| xs.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "document".to_string());
+                tera.add_template_file(template, Some(name.as_str()))?;
+                Some(name)
+            }
+            (ExportFormat::Template, None) => eyre::bail!("template exports need a template file"),
+            (_, Some(_)) => eyre::bail!("only template exports use a template file"),
+            (_, None) => None,
+        };
+
+        Ok(Self {
+            format,
+            tera,
+            document,
+            timezone: DisplayTimezone::from_env(),
+        })
+    }
+
+    /// Render filename dates in `timezone` rather than the one `LIKELIKE_TIMEZONE` names.
+    pub fn with_timezone(mut self, timezone: DisplayTimezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// The marker below which user edits to exported documents are kept, for formats that keep
+    /// them. See [`ExportWriter::preserving_edits`](crate::ExportWriter::preserving_edits).
+    pub fn edit_marker(&self) -> Option<&'static str> {
+        self.format.vault().map(|_| EDIT_MARKER)
+    }
+
+    /// Documents exported alongside the links' own: for vaults, a note for each friend the links
+    /// came via. Returns each document's path and contents.
+    pub fn extra_documents<'a>(
+        &self,
+        links: impl Iterator<Item = &'a Link>,
+    ) -> Vec<(String, String)> {
+        match self.format.vault() {
+            Some(vault) => vault.friend_notes(links),
+            None => Vec::new(),
+        }
+    }
+
+    /// Render a link, returning the document's path relative to the output directory and its
+    /// contents.
+    pub fn render(&self, link: Link, related: Vec<RelatedLink>) -> eyre::Result<(String, String)> {
+        let context = template_context(&link, &related, self.timezone)?;
+        let filename = self.tera.render(FILENAME_TEMPLATE, &context)?;
+        let filename = filename.trim().to_string();
+        if filename.is_empty() {
+            eyre::bail!("the filename template rendered nothing for {}", link.url());
+        }
+
+        if let Some(document) = &self.document {
+            return Ok((filename, self.tera.render(document, &context)?));
+        }
+
+        if let Some(vault) = self.format.vault() {
+            return Ok((filename, vault.render(&link, &related)?));
+        }
+
+        let mut frontmatter = Frontmatter::try_from(link)?;
+        frontmatter.set_related(related);
+
+        let tags = frontmatter
+            .taxonomies
+            .get("tags")
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let hugo = HugoFrontmatter {
+            title: &frontmatter.title,
+            slug: &frontmatter.slug,
+            date: &frontmatter.date,
+            tags,
+            params: &frontmatter.extra,
+        };
+        let post = PostFrontmatter {
+            title: &frontmatter.title,
+            date: &frontmatter.date,
+            tags,
+            link: &frontmatter.extra,
+        };
+
+        let (fence, header) = match self.format {
+            ExportFormat::Zola => ("+++", toml::to_string_pretty(&frontmatter)?),
+            ExportFormat::Hugo => ("+++", toml::to_string_pretty(&hugo)?),
+            ExportFormat::HugoYaml => ("---", serde_yaml::to_string(&hugo)?),
+            ExportFormat::Jekyll | ExportFormat::Eleventy => ("---", serde_yaml::to_string(&post)?),
+            ExportFormat::Obsidian | ExportFormat::Logseq => {
+                unreachable!("vaults are rendered above")
+            }
+            ExportFormat::Template => unreachable!("template exports always have a template"),
+        };
+
+        Ok((
+            filename,
+            format!("{fence}\n{}\n{fence}\n{}", header, frontmatter.notes()),
+        ))
+    }
+}
+
+/// Every field of a link but its source data, with missing values written as nulls so that
+/// templates can test for them.
+#[derive(Serialize)]
+struct LinkContext<'a> {
+    url: &'a str,
+    title: Option<&'a str>,
+    via: Option<&'a Via>,
+    tags: &'a [String],
+    notes: Option<&'a str>,
+    found_at: Option<DateTime<Utc>>,
+    read_at: Option<DateTime<Utc>>,
+    published_at: Option<DateTime<Utc>>,
+    from_filename: Option<&'a str>,
+    image: Option<&'a str>,
+    meta: BTreeMap<&'a str, &'a [String]>,
+    src_truncated: Option<&'a str>,
+    extracted_text: Option<&'a str>,
+    last_fetched: Option<DateTime<Utc>>,
+    last_processed: Option<DateTime<Utc>>,
+    http_headers: BTreeMap<&'a str, &'a [String]>,
+    hidden: bool,
+    summary: Option<&'a str>,
+    summarized_at: Option<DateTime<Utc>>,
+    digested_at: Option<DateTime<Utc>>,
+}
+
+impl<'a> From<&'a Link> for LinkContext<'a> {
+    fn from(link: &'a Link) -> Self {
+        let sorted = |map: Option<&'a HashMap<String, Vec<String>>>| {
+            map.into_iter()
+                .flatten()
+                .map(|(key, values)| (key.as_str(), values.as_slice()))
+                .collect()
+        };
+
+        Self {
+            url: &link.url,
+            title: link.title.as_deref(),
+            via: link.via.as_ref(),
+            tags: &link.tags,
+            notes: link.notes.as_deref(),
+            found_at: link.found_at,
+            read_at: link.read_at,
+            published_at: link.published_at,
+            from_filename: link.from_filename.as_deref(),
+            image: link.image.as_deref(),
+            meta: sorted(link.meta.as_ref()),
+            src_truncated: link.src_truncated.as_deref(),
+            extracted_text: link.extracted_text.as_deref(),
+            last_fetched: link.last_fetched,
+            last_processed: link.last_processed,
+            http_headers: sorted(link.http_headers.as_ref()),
+            hidden: link.hidden,
+            summary: link.summary.as_deref(),
+            summarized_at: link.summarized_at,
+            digested_at: link.digested_at,
+        }
+    }
+}
+
+fn template_context(
+    link: &Link,
+    related: &[RelatedLink],
+    timezone: DisplayTimezone,
+) -> eyre::Result<Context> {
+    let date = timezone.localize(
+        link.published_at()
+            .or_else(|| link.found_at())
+            .unwrap_or_else(Utc::now),
+    );
+    // Slugify the url the way frontmatter writes it, so filenames don't change between exports.
+    let url = url::Url::parse(link.url())
+        .map(String::from)
+        .unwrap_or_else(|_| link.url().to_string());
+
+    let mut context = Context::new();
+    context.insert("link", &LinkContext::from(link));
+    context.insert("related", related);
+    context.insert(
+        "slug",
+        &slugify!(link.title().unwrap_or_else(|| link.url())),
+    );
+    context.insert("url_slug", &slugify!(url.as_str()));
+    context.insert("date", &date.format("%Y-%m-%d").to_string());
+    context.insert("year", &date.format("%Y").to_string());
+    context.insert("month", &date.format("%m").to_string());
+    context.insert("day", &date.format("%d").to_string());
+    context.insert("host", &link_host(link).unwrap_or_default());
+    Ok(context)
+}
+
+fn slugify_filter(
+    value: &tera::Value,
+    _: &HashMap<String, tera::Value>,
+) -> tera::Result<tera::Value> {
+    let value = tera::try_get_value!("slugify", "value", String, value);
+    Ok(slugify!(value.as_str()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link() -> Link {
+        let mut link = Link::new("https://example.com/posts/hello?page=2", "Hello, World");
+        link.tags = vec!["lang/rust".to_string(), "reading".to_string()];
+        link.via = Some(Via::Friend("alice".to_string()));
+        link.found_at = Some("2023-04-05T06:07:08Z".parse().unwrap());
+        link.notes = Some("some notes".to_string());
+        link.extracted_text = Some("the text".to_string());
+        link
+    }
+
+    fn related() -> Vec<RelatedLink> {
+        vec![RelatedLink {
+            url: "https://example.com/other".to_string(),
+            title: Some("Other".to_string()),
+            score: 0.5,
+            reasons: vec!["shared tags: reading".to_string()],
+        }]
+    }
+
+    #[test]
+    fn renders_each_format() -> eyre::Result<()> {
+        let zola = Exporter::new(ExportFormat::Zola, None, None)?;
+        let (filename, document) = zola.render(link(), related())?;
+        assert_eq!(filename, "https-example-com-posts-hello-page-2.md");
+        let frontmatter: Frontmatter = document.parse()?;
+        assert_eq!(frontmatter.taxonomies["tags"], ["lang/rust", "reading"]);
+        assert_eq!(frontmatter.notes(), "some notes");
+
+        let (filename, _) = zola.render(Link::new("https://example.com:443/a", "A"), vec![])?;
+        assert_eq!(filename, "https-example-com-a.md");
+
+        let hugo = Exporter::new(ExportFormat::Hugo, None, None)?;
+        let (_, document) = hugo.render(link(), related())?;
+        let header = document.split("+++").nth(1).unwrap();
+        let header: toml::Value = toml::from_str(header)?;
+        assert_eq!(header["tags"][0].as_str(), Some("lang/rust"));
+        assert_eq!(
+            header["params"]["url"]["host"].as_str(),
+            Some("example.com")
+        );
+
+        for format in [ExportFormat::HugoYaml, ExportFormat::Eleventy] {
+            let exporter = Exporter::new(format, None, None)?;
+            let (_, document) = exporter.render(link(), related())?;
+            let header = document.split("---").nth(1).unwrap();
+            let header: serde_yaml::Value = serde_yaml::from_str(header)?;
+            assert_eq!(header["title"].as_str(), Some("Hello, World"));
+            assert_eq!(header["tags"][1].as_str(), Some("reading"));
+            assert!(document.ends_with("---\nsome notes"));
+        }
+
+        // Dates in filenames are in the display timezone, where this link was found the day
+        // before.
+        let jekyll = Exporter::new(ExportFormat::Jekyll, None, None)?
+            .with_timezone("America/Los_Angeles".parse()?);
+        let (filename, document) = jekyll.render(link(), related())?;
+        assert_eq!(
+            filename,
+            "_posts/2023-04-04-https-example-com-posts-hello-page-2.md"
+        );
+        let header = document.split("---").nth(1).unwrap();
+        let header: serde_yaml::Value = serde_yaml::from_str(header)?;
+        assert_eq!(header["link"]["via"]["content"].as_str(), Some("alice"));
+        Ok(())
+    }
+
+    #[test]
+    fn renders_custom_templates() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let template = dir.path().join("link.md");
+        std::fs::write(
+            &template,
+            "<a href=\"{{ link.url }}\">{{ link.title }}</a> via {{ link.via.Friend }}\n\
+             {% for tag in link.tags %}#{{ tag | slugify }} {% endfor %}\n\
+             {{ link.extracted_text }}; {{ related | length }} related\n\
+             {% if link.image %}{{ link.image }}{% else %}no image{% endif %}",
+        )?;
+
+        let exporter = Exporter::new(
+            ExportFormat::Template,
+            Some("{{ host }}/{{ year }}/{{ slug }}.html"),
+            Some(&template),
+        )?;
+        let (filename, document) = exporter.render(link(), related())?;
+        assert_eq!(filename, "example.com/2023/hello-world.html");
+        assert_eq!(
+            document,
+            "<a href=\"https://example.com/posts/hello?page=2\">Hello, World</a> via alice\n\
+             #lang-rust #reading \nthe text; 1 related\nno image"
+        );
+
+        assert!(Exporter::new(ExportFormat::Template, None, None).is_err());
+        assert!(Exporter::new(ExportFormat::Zola, None, Some(&template)).is_err());
+        assert!("gatsby".parse::<ExportFormat>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    fmt::{self, Display},
+    path::Path,
+};
+
+use super::{sha256, ExportedLink, Manifest, MANIFEST};
+use crate::{Frontmatter, Link, LinkDiff, LinkReader, LinkWriter};
+
+/// Reads a Zola export back into the store, e.g. after the database was lost or the exported
+/// files were edited by hand.
+///
+/// The export's manifest records what each file held and which version of its link it was
+/// rendered from. A file that is unchanged since the export has nothing to contribute. An edited
+/// file is applied to its link, unless the link also changed since the export: that is a
+/// conflict, which is reported and left alone unless `overwrite` is set. Files the manifest does
+/// not know about are treated as edited, and their links as changed.
+pub struct ExportReconciler {
+    /// Report what would change without writing to the store.
+    pub dry_run: bool,
+    /// Apply edited files over links that also changed since the export.
+    pub overwrite: bool,
+}
+
+/// What reconciling an export changed, or would change, in the store.
+#[derive(Debug, Default, Clone)]
+pub struct ReconcileSummary {
+    pub added: Vec<LinkDiff>,
+    pub updated: Vec<LinkDiff>,
+    /// Each conflicting file, with the changes it would make to its link.
+    pub conflicts: Vec<(String, LinkDiff)>,
+    /// Files that are unchanged since the export, while their links are not.
+    pub stale: Vec<String>,
+    /// Files that could not be read as link pages, with the reason.
+    pub skipped: Vec<(String, String)>,
+    pub unchanged: u64,
+}
+
+impl Display for ReconcileSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diff in self.added.iter().chain(&self.updated) {
+            write!(f, "{}", diff)?;
+        }
+
+        for (filename, diff) in &self.conflicts {
+            writeln!(
+                f,
+                "! {} and its link both changed since the export; keeping the link",
+                filename
+            )?;
+            write!(f, "{}", diff)?;
+        }
+
+        for (filename, reason) in &self.skipped {
+            writeln!(f, "skipped {}: {}", filename, reason)?;
+        }
+
+        writeln!(
+            f,
+            "{} added, {} updated, {} conflicts, {} stale, {} skipped, {} unchanged",
+            self.added.len(),
+            self.updated.len(),
+            self.conflicts.len(),
+            self.stale.len(),
+            self.skipped.len(),
+            self.unchanged
+        )
+    }
+}
+
+impl ExportReconciler {
+    pub async fn reconcile<S>(&self, store: &S, directory: &Path) -> eyre::Result<ReconcileSummary>
+    where
+        S: LinkReader + LinkWriter + Send + Sync,
+    {
+        let manifest = Manifest::read(&directory.join(MANIFEST))?;
+        let mut files = Vec::new();
+        markdown_files(directory, "", &mut files)?;
+        files.sort();
+
+        let mut summary = ReconcileSummary::default();
+        for filename in files {
+            let contents = std::fs::read_to_string(directory.join(&filename))?;
+            let frontmatter: Frontmatter = match contents.parse() {
+                Ok(frontmatter) => frontmatter,
+                Err(e) => {
+                    summary.skipped.push((filename, format!("{:#}", e)));
+                    continue;
+                }
+            };
+
+            // Digest posts list links rather than describe one.
+            if frontmatter.is_digest() {
+                continue;
+            }
+
+            let url = frontmatter.url().to_string();
+            if url.is_empty() {
+                summary
+                    .skipped
+                    .push((filename, "the frontmatter has no url".to_string()));
+                continue;
+            }
+
+            let exported = manifest.links.get(&filename);
+            let Some(stored) = find_stored(store, exported, &url).await? else {
+                let mut link = Link::new(&url, "");
+                frontmatter.update_link(&mut link);
+                keep_what_the_file_cannot_hold(None, &mut link);
+
+                summary.added.extend(LinkDiff::new(None, &link));
+                if !self.dry_run {
+                    store.write(link).await?;
+                }
+                continue;
+            };
+
+            let mut link = stored.clone();
+            frontmatter.update_link(&mut link);
+            keep_what_the_file_cannot_hold(Some(&stored), &mut link);
+
+            let Some(diff) = LinkDiff::new(Some(&stored), &link) else {
+                summary.unchanged += 1;
+                continue;
+            };
+
+            let edited =
+                exported.is_none_or(|exported| exported.sha256 != sha256(contents.as_bytes()));
+            if !edited {
+                summary.stale.push(filename);
+                continue;
+            }
+
+            let changed = exported
+                .is_none_or(|exported| exported.fingerprint != LinkDiff::fingerprint(&stored));
+            if changed && !self.overwrite {
+                summary.conflicts.push((filename, diff));
+                continue;
+            }
+
+            summary.updated.push(diff);
+            if !self.dry_run {
+                store.write(link).await?;
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Find the link a file was exported from. Files hold urls normalized, which the stored url may
+/// not be (`https://example.com` is written as `https://example.com/`), so the url the manifest
+/// recorded is tried first, then the file's url with and without a trailing slash.
+async fn find_stored<S>(
+    store: &S,
+    exported: Option<&ExportedLink>,
+    url: &str,
+) -> eyre::Result<Option<Link>>
+where
+    S: LinkReader + Send + Sync,
+{
+    let recorded = exported
+        .and_then(|exported| exported.url.as_deref())
+        .filter(|recorded| {
+            url::Url::parse(recorded).is_ok_and(|recorded| recorded.as_str() == url)
+        });
+    let trimmed = url.strip_suffix('/');
+
+    for candidate in recorded.into_iter().chain([url]).chain(trimmed) {
+        if let Some(stored) = store.get(candidate).await? {
+            return Ok(Some(stored));
+        }
+    }
+    Ok(None)
+}
+
+/// Undo what exporting loses, so that a file nobody touched does not read as a change: untitled
+/// links are titled with their url, and notes lose their leading whitespace. Summaries are
+/// generated rather than written, so the stored one is kept.
+fn keep_what_the_file_cannot_hold(stored: Option<&Link>, link: &mut Link) {
+    let untitled = stored.is_none_or(|stored| stored.title.is_none());
+    if untitled && link.title.as_deref() == Some(link.url.as_str()) {
+        link.title = None;
+    }
+
+    let Some(stored) = stored else { return };
+
+    let trimmed = |notes: Option<&String>| notes.map(|notes| notes.trim().to_string());
+    if trimmed(stored.notes.as_ref()) == trimmed(link.notes.as_ref()) {
+        link.notes = stored.notes.clone();
+    }
+    link.summary = stored.summary.clone();
+}
+
+/// Collect the `.md` files below `directory`, relative to it and `/`-separated like an export's
+/// filenames. Hidden files and directories are left out.
+fn markdown_files(directory: &Path, prefix: &str, output: &mut Vec<String>) -> eyre::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let filename = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            markdown_files(&entry.path(), &format!("{}/", filename), output)?;
+        } else if name.ends_with(".md") {
+            output.push(filename);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Digest, ExportFormat, ExportWriter, Exporter, InMemoryStore, Via};
+
+    fn link(url: &str, title: &str) -> Link {
+        let mut link = Link::new(url, title);
+        link.tags = vec!["rust".to_string()];
+        link.via = Some(Via::Friend("alice".to_string()));
+        link.read_at = Some("2023-04-05T06:07:08Z".parse().unwrap());
+        link.notes = Some("some notes".to_string());
+        link.summary = Some("a summary".to_string());
+        link
+    }
+
+    #[tokio::test]
+    async fn applies_edits_and_reports_conflicts() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let links = [
+            link("https://example.com/edited", "Edited"),
+            link("https://example.com/both", "Both"),
+            link("https://example.com/stale", "Stale"),
+            link("https://example.com/lost", "Lost"),
+            link("https://example.com/same", "Same"),
+        ];
+
+        let exporter = Exporter::new(ExportFormat::Zola, None, None)?;
+        let mut export = ExportWriter::open(dir.path())?;
+        let mut filenames = Vec::new();
+        for link in &links {
+            let fingerprint = LinkDiff::fingerprint(link);
+            let (filename, document) = exporter.render(link.clone(), Vec::new())?;
+            export.write_link(&filename, document.as_bytes(), link.url(), fingerprint)?;
+            filenames.push(filename);
+        }
+        export.finish()?;
+        std::fs::write(
+            dir.path().join("_index.md"),
+            "+++\ntitle = \"links\"\n+++\n",
+        )?;
+        let digest = Digest {
+            title: "Reading".to_string(),
+            start: "2023-04-01T00:00:00Z".parse()?,
+            end: "2023-05-01T00:00:00Z".parse()?,
+            links: links.to_vec(),
+            timezone: "UTC".parse()?,
+        };
+        std::fs::write(dir.path().join("reading.md"), digest.render()?)?;
+
+        let edit = |filename: &str| -> eyre::Result<()> {
+            let path = dir.path().join(filename);
+            let contents = std::fs::read_to_string(&path)?;
+            Ok(std::fs::write(
+                path,
+                contents.replace("some notes", "edited notes"),
+            )?)
+        };
+        edit(&filenames[0])?;
+        edit(&filenames[1])?;
+
+        let [edited, mut both, mut stale, _, same] = links;
+        both.tags.push("changed".to_string());
+        stale.title = Some("Retitled".to_string());
+        let store = InMemoryStore::with_links([edited, both, stale, same]);
+
+        let reconciler = ExportReconciler {
+            dry_run: false,
+            overwrite: false,
+        };
+        let summary = reconciler.reconcile(&store, dir.path()).await?;
+        let urls = |diffs: &[LinkDiff]| -> Vec<String> {
+            diffs.iter().map(|diff| diff.url.clone()).collect()
+        };
+        assert_eq!(urls(&summary.added), ["https://example.com/lost"]);
+        assert_eq!(urls(&summary.updated), ["https://example.com/edited"]);
+        assert_eq!(summary.conflicts.len(), 1);
+        assert_eq!(summary.conflicts[0].0, filenames[1]);
+        assert_eq!(summary.stale, [filenames[2].clone()]);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.skipped[0].0, "_index.md");
+        assert_eq!(summary.unchanged, 1);
+
+        let edited = store.get("https://example.com/edited").await?.unwrap();
+        assert_eq!(edited.notes(), Some("edited notes"));
+        assert_eq!(edited.read_at, Some("2023-04-05T06:07:08Z".parse()?));
+        let both = store.get("https://example.com/both").await?.unwrap();
+        assert_eq!(both.notes(), Some("some notes"));
+        let stale = store.get("https://example.com/stale").await?.unwrap();
+        assert_eq!(stale.title(), Some("Retitled"));
+        let lost = store.get("https://example.com/lost").await?.unwrap();
+        assert_eq!(lost.title(), Some("Lost"));
+        assert_eq!(lost.tags(), &["rust"]);
+
+        let reconciler = ExportReconciler {
+            dry_run: false,
+            overwrite: true,
+        };
+        let summary = reconciler.reconcile(&store, dir.path()).await?;
+        assert_eq!(urls(&summary.updated), ["https://example.com/both"]);
+        let both = store.get("https://example.com/both").await?.unwrap();
+        assert_eq!(both.notes(), Some("edited notes"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn finds_links_stored_without_a_trailing_slash() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let links = [
+            link("https://example.com", "Recorded"),
+            link("https://example.org", "Unrecorded"),
+        ];
+
+        let exporter = Exporter::new(ExportFormat::Zola, None, None)?;
+        let mut export = ExportWriter::open(dir.path())?;
+        let (filename, document) = exporter.render(links[0].clone(), Vec::new())?;
+        let fingerprint = LinkDiff::fingerprint(&links[0]);
+        export.write_link(&filename, document.as_bytes(), links[0].url(), fingerprint)?;
+        // Files the manifest doesn't know the link of, as from older exports.
+        let (filename, document) = exporter.render(links[1].clone(), Vec::new())?;
+        export.write(&filename, document.as_bytes())?;
+        export.finish()?;
+
+        for entry in std::fs::read_dir(dir.path())? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|xs| xs == "md") {
+                let contents = std::fs::read_to_string(&path)?;
+                assert!(contents.contains("/\"\n"));
+                std::fs::write(&path, contents.replace("some notes", "edited notes"))?;
+            }
+        }
+
+        let store = InMemoryStore::with_links(links);
+        let reconciler = ExportReconciler {
+            dry_run: false,
+            overwrite: true,
+        };
+        let summary = reconciler.reconcile(&store, dir.path()).await?;
+        assert!(summary.added.is_empty());
+        let mut urls: Vec<_> = summary.updated.iter().map(|diff| &diff.url).collect();
+        urls.sort();
+        assert_eq!(urls, ["https://example.com", "https://example.org"]);
+        assert!(store.get("https://example.com/").await?.is_none());
+        Ok(())
+    }
+}
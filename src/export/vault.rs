@@ -0,0 +1,305 @@
+use serde::Serialize;
+use std::{collections::BTreeSet, path::Path};
+
+use crate::{digest::escape_link_text, query::link_host, Link, RelatedLink, Via};
+
+/// The line that ends what an export writes into a vault note. Anything below it is the vault
+/// owner's, and is kept when the note is exported again.
+pub const EDIT_MARKER: &str =
+    "<!-- likelike: edits below this line are kept when exporting again -->";
+
+/// The vault applications links can be exported to as notes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Vault {
+    Obsidian,
+    Logseq,
+}
+
+/// Obsidian note properties, which are YAML frontmatter.
+#[derive(Serialize)]
+struct Properties<'a> {
+    title: &'a str,
+    aliases: [&'a str; 1],
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    via: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    found: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    read: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    hidden: bool,
+}
+
+impl Vault {
+    pub(crate) fn default_filename(&self) -> &'static str {
+        match self {
+            Vault::Obsidian => "links/{{ url_slug }}.md",
+            Vault::Logseq => "pages/{{ url_slug }}.md",
+        }
+    }
+
+    /// Render a link's note, ending with [`EDIT_MARKER`].
+    pub(crate) fn render(&self, link: &Link, related: &[RelatedLink]) -> eyre::Result<String> {
+        let title = link.title().unwrap_or_else(|| link.url());
+        let host = link_host(link);
+        let via = link.via().map(|via| match via {
+            Via::Friend(friend) => wikilink(&note_name(friend)),
+            Via::Link(xs) | Via::Freeform(xs) => xs.to_string(),
+        });
+        let source = link
+            .from_filename()
+            .and_then(source_note)
+            .map(|xs| wikilink(&xs));
+        let tags = link
+            .tags()
+            .iter()
+            .map(|tag| hashtag(tag))
+            .collect::<Vec<_>>();
+
+        let mut body = vec![format!("[{}]({})", escape_link_text(title), link.url())];
+        if !tags.is_empty() {
+            body.push(tags.join(" "));
+        }
+        let credits: Vec<String> = via
+            .iter()
+            .map(|via| format!("via {}", via))
+            .chain(source.iter().map(|source| format!("from {}", source)))
+            .collect();
+        if !credits.is_empty() {
+            body.push(credits.join(" · "));
+        }
+        if let Some(notes) = link.notes().filter(|notes| !notes.trim().is_empty()) {
+            body.push(notes.trim().to_string());
+        }
+        if !related.is_empty() {
+            let related: Vec<String> = related
+                .iter()
+                .map(|related| {
+                    format!(
+                        "related: [{}]({})",
+                        escape_link_text(related.title.as_deref().unwrap_or(&related.url)),
+                        related.url
+                    )
+                })
+                .collect();
+            body.push(related.join("\n"));
+        }
+
+        let date = |xs: Option<chrono::DateTime<chrono::Utc>>| xs.map(|xs| xs.to_rfc3339());
+        let mut note = match self {
+            Vault::Obsidian => {
+                let properties = Properties {
+                    title,
+                    aliases: [title],
+                    url: link.url(),
+                    host,
+                    tags: link.tags().iter().map(|tag| tag_name(tag)).collect(),
+                    via,
+                    source,
+                    found: date(link.found_at()),
+                    read: date(link.read_at()),
+                    published: date(link.published_at()),
+                    image: link.image(),
+                    summary: link.summary(),
+                    hidden: link.hidden(),
+                };
+
+                format!(
+                    "---\n{}---\n\n{}\n",
+                    serde_yaml::to_string(&properties)?,
+                    body.join("\n\n")
+                )
+            }
+
+            Vault::Logseq => {
+                let mut properties = vec![
+                    ("title", title.replace('\n', " ")),
+                    ("url", link.url().to_string()),
+                ];
+                if !link.tags().is_empty() {
+                    properties.push(("tags", link.tags().join(", ")));
+                }
+                properties.extend(via.map(|via| ("via", via)));
+                properties.extend(source.map(|source| ("source", source)));
+                properties.extend(date(link.found_at()).map(|found| ("found", found)));
+                properties.extend(date(link.read_at()).map(|read| ("read", read)));
+                properties
+                    .extend(date(link.published_at()).map(|published| ("published", published)));
+
+                let mut note: String = properties
+                    .into_iter()
+                    .map(|(key, value)| format!("{}:: {}\n", key, value))
+                    .collect();
+                note.push('\n');
+
+                // Every paragraph is a block, with the lines after its first indented under it.
+                for paragraph in body.iter().flat_map(|xs| xs.split("\n\n")) {
+                    let mut lines = paragraph.lines();
+                    if let Some(first) = lines.next() {
+                        note.push_str(&format!("- {}\n", first));
+                    }
+                    for line in lines {
+                        note.push_str(&format!("  {}\n", line));
+                    }
+                }
+                note
+            }
+        };
+
+        note.push('\n');
+        note.push_str(&self.marker_line());
+        Ok(note)
+    }
+
+    /// Notes for each friend links came via, for the links' `via` wikilinks to point at. Each is
+    /// otherwise left for the vault owner to fill in below the marker.
+    pub(crate) fn friend_notes<'a>(
+        &self,
+        links: impl Iterator<Item = &'a Link>,
+    ) -> Vec<(String, String)> {
+        let friends: BTreeSet<String> = links
+            .filter_map(|link| match link.via() {
+                Some(Via::Friend(friend)) => Some(note_name(friend)),
+                _ => None,
+            })
+            .collect();
+
+        friends
+            .into_iter()
+            .map(|friend| {
+                let (filename, note) = match self {
+                    Vault::Obsidian => (
+                        format!("via/{}.md", friend),
+                        "---\ntags: [friend]\n---\n\n".to_string(),
+                    ),
+                    Vault::Logseq => (
+                        format!("pages/{}.md", friend),
+                        "tags:: friend\n\n".to_string(),
+                    ),
+                };
+                (filename, note + &self.marker_line())
+            })
+            .collect()
+    }
+
+    /// Logseq keeps every line of a page in a block, so its marker is a block of its own.
+    fn marker_line(&self) -> String {
+        match self {
+            Vault::Obsidian => format!("{}\n", EDIT_MARKER),
+            Vault::Logseq => format!("- {}\n", EDIT_MARKER),
+        }
+    }
+}
+
+fn wikilink(name: &str) -> String {
+    format!("[[{}]]", name)
+}
+
+fn hashtag(tag: &str) -> String {
+    format!("#{}", tag_name(tag))
+}
+
+/// Tags cannot hold whitespace, so it becomes `-`. Hierarchical tags are nested tags in both
+/// Obsidian and Logseq as they are.
+fn tag_name(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+/// Drop characters that either application refuses in note names or reads as link syntax.
+fn note_name(name: &str) -> String {
+    name.chars()
+        .map(|xs| match xs {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => '-',
+            xs => xs,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// The link dump a link was imported from, which lives in the vault as a note of the same name.
+fn source_note(filename: &str) -> Option<String> {
+    Path::new(filename)
+        .file_stem()
+        .map(|stem| note_name(&stem.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link() -> Link {
+        let mut link = Link::new("https://example.com/post", "A Post");
+        link.tags = vec!["lang/rust".to_string(), "to read".to_string()];
+        link.via = Some(Via::Friend("alice".to_string()));
+        link.from_filename = Some("/notes/20230405-links.md".to_string());
+        link.read_at = Some("2023-04-05T06:07:08Z".parse().unwrap());
+        link.notes = Some("first\n\nsecond\nline".to_string());
+        link
+    }
+
+    #[test]
+    fn renders_obsidian_notes() -> eyre::Result<()> {
+        let note = Vault::Obsidian.render(&link(), &[])?;
+        let properties = note.split("---").nth(1).unwrap();
+        let properties: serde_yaml::Value = serde_yaml::from_str(properties)?;
+        assert_eq!(properties["title"].as_str(), Some("A Post"));
+        assert_eq!(properties["via"].as_str(), Some("[[alice]]"));
+        assert_eq!(properties["source"].as_str(), Some("[[20230405-links]]"));
+        assert_eq!(
+            properties["read"].as_str(),
+            Some("2023-04-05T06:07:08+00:00")
+        );
+        assert_eq!(properties["tags"][0].as_str(), Some("lang/rust"));
+        assert_eq!(properties["tags"][1].as_str(), Some("to-read"));
+
+        assert!(note.contains("\n#lang/rust #to-read\n"));
+        assert!(note.contains("\nvia [[alice]] · from [[20230405-links]]\n"));
+        assert!(note.ends_with(&format!("first\n\nsecond\nline\n\n{}\n", EDIT_MARKER)));
+
+        let friends = Vault::Obsidian.friend_notes([link(), link()].iter());
+        assert_eq!(friends.len(), 1);
+        assert_eq!(friends[0].0, "via/alice.md");
+        Ok(())
+    }
+
+    #[test]
+    fn escapes_brackets_in_link_titles() -> eyre::Result<()> {
+        let mut link = link();
+        link.title = Some("[draft] A Post".to_string());
+        let related = RelatedLink {
+            url: "https://example.com/other".to_string(),
+            title: Some("Arrays [part 2]".to_string()),
+            score: 1.0,
+            reasons: vec![],
+        };
+
+        let note = Vault::Obsidian.render(&link, &[related])?;
+        assert!(note.contains("\n[\\[draft\\] A Post](https://example.com/post)\n"));
+        assert!(note.contains("\nrelated: [Arrays \\[part 2\\]](https://example.com/other)\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn renders_logseq_pages() -> eyre::Result<()> {
+        let note = Vault::Logseq.render(&link(), &[])?;
+        assert!(note.starts_with("title:: A Post\nurl:: https://example.com/post\n"));
+        assert!(note.contains("tags:: lang/rust, to read\nvia:: [[alice]]\n"));
+        assert!(note.contains("\n- first\n- second\n  line\n"));
+        assert!(note.ends_with(&format!("\n- {}\n", EDIT_MARKER)));
+        Ok(())
+    }
+}
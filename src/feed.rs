@@ -0,0 +1,378 @@
+use chrono::{DateTime, Utc};
+use comrak::{markdown_to_html, ComrakOptions};
+use serde::Serialize;
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    DateField, DateFilter, Link, LinkQuery, ListParams, Predicate, Query, Sort, SortKey, TagFilter,
+    Term, Via,
+};
+
+/// The feed formats links can be published in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    #[default]
+    Atom,
+    Rss,
+    Json,
+}
+
+impl FromStr for FeedFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "atom" => FeedFormat::Atom,
+            "rss" | "rss2" => FeedFormat::Rss,
+            "json" | "jsonfeed" => FeedFormat::Json,
+            _ => eyre::bail!("unknown feed format {:?}; expected atom, rss, or json", s),
+        })
+    }
+}
+
+impl Display for FeedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FeedFormat::Atom => "atom",
+            FeedFormat::Rss => "rss",
+            FeedFormat::Json => "json",
+        })
+    }
+}
+
+/// A feed of recently read links. Each entry links to the page that was read, carries the link's
+/// notes rendered as HTML along with whoever or whatever the link came via, and lists the link's
+/// tags as categories.
+#[derive(Debug, Clone)]
+pub struct Feed {
+    /// The feed's title.
+    pub title: String,
+    /// The site the feed belongs to.
+    pub home_page_url: String,
+    /// Where the feed itself is published, if known.
+    pub feed_url: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Feed {
+    pub fn new(title: impl Into<String>, home_page_url: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            home_page_url: home_page_url.into(),
+            feed_url: None,
+            description: None,
+        }
+    }
+
+    /// Fetch up to `limit` of the most recently read links that are not hidden, optionally
+    /// narrowed to those matching `tag`.
+    pub async fn links<S>(store: &S, tag: Option<TagFilter>, limit: i64) -> eyre::Result<Vec<Link>>
+    where
+        S: LinkQuery + Send + Sync,
+    {
+        let params = ListParams {
+            query: Some(Query {
+                terms: vec![Term {
+                    negated: false,
+                    predicate: Predicate::Date(DateField::Read, DateFilter::Present(true)),
+                }],
+            }),
+            tag,
+            hidden: Some(false),
+            sort: Sort {
+                key: SortKey::Read,
+                descending: true,
+            },
+            cursor: None,
+            offset: 0,
+            limit,
+        };
+
+        Ok(store.list(&params).await?.links)
+    }
+
+    pub fn render(&self, format: FeedFormat, links: &[Link]) -> eyre::Result<String> {
+        Ok(match format {
+            FeedFormat::Atom => self.atom(links).to_string(),
+            FeedFormat::Rss => self.rss(links).to_string(),
+            FeedFormat::Json => serde_json::to_string_pretty(&self.json(links))?,
+        })
+    }
+
+    fn atom(&self, links: &[Link]) -> atom_syndication::Feed {
+        let mut feed_links = vec![atom_syndication::Link {
+            href: self.home_page_url.clone(),
+            ..Default::default()
+        }];
+        if let Some(feed_url) = &self.feed_url {
+            feed_links.push(atom_syndication::Link {
+                href: feed_url.clone(),
+                rel: "self".to_string(),
+                mime_type: Some("application/atom+xml".to_string()),
+                ..Default::default()
+            });
+        }
+
+        atom_syndication::Feed {
+            title: self.title.as_str().into(),
+            id: self
+                .feed_url
+                .clone()
+                .unwrap_or_else(|| self.home_page_url.clone()),
+            updated: updated(links).into(),
+            subtitle: self.description.as_deref().map(Into::into),
+            links: feed_links,
+            entries: links
+                .iter()
+                .map(|link| atom_syndication::Entry {
+                    title: entry_title(link).into(),
+                    id: link.url().to_string(),
+                    updated: read_at(link).into(),
+                    published: link.published_at().map(Into::into),
+                    links: vec![atom_syndication::Link {
+                        href: link.url().to_string(),
+                        ..Default::default()
+                    }],
+                    categories: link
+                        .tags()
+                        .iter()
+                        .map(|tag| atom_syndication::Category {
+                            term: tag.to_string(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    content: entry_content(link).map(|html| atom_syndication::Content {
+                        value: Some(html),
+                        content_type: Some("html".to_string()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn rss(&self, links: &[Link]) -> rss::Channel {
+        rss::Channel {
+            title: self.title.clone(),
+            link: self.home_page_url.clone(),
+            description: self
+                .description
+                .clone()
+                .unwrap_or_else(|| self.title.clone()),
+            last_build_date: Some(updated(links).to_rfc2822()),
+            items: links
+                .iter()
+                .map(|link| rss::Item {
+                    title: Some(entry_title(link).to_string()),
+                    link: Some(link.url().to_string()),
+                    guid: Some(rss::Guid {
+                        value: link.url().to_string(),
+                        permalink: true,
+                    }),
+                    pub_date: Some(read_at(link).to_rfc2822()),
+                    categories: link
+                        .tags()
+                        .iter()
+                        .map(|tag| rss::Category {
+                            name: tag.to_string(),
+                            domain: None,
+                        })
+                        .collect(),
+                    description: entry_content(link),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn json<'a>(&'a self, links: &'a [Link]) -> JsonFeed<'a> {
+        JsonFeed {
+            version: "https://jsonfeed.org/version/1.1",
+            title: &self.title,
+            home_page_url: &self.home_page_url,
+            feed_url: self.feed_url.as_deref(),
+            description: self.description.as_deref(),
+            items: links
+                .iter()
+                .map(|link| JsonFeedItem {
+                    id: link.url(),
+                    url: link.url(),
+                    title: entry_title(link),
+                    content_html: entry_content(link).unwrap_or_default(),
+                    date_published: read_at(link),
+                    tags: link.tags(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A [JSON Feed](https://www.jsonfeed.org/version/1.1/).
+#[derive(Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: &'a str,
+    home_page_url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    feed_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    items: Vec<JsonFeedItem<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonFeedItem<'a> {
+    id: &'a str,
+    url: &'a str,
+    title: &'a str,
+    content_html: String,
+    date_published: DateTime<Utc>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+}
+
+fn entry_title(link: &Link) -> &str {
+    link.title().unwrap_or_else(|| link.url())
+}
+
+/// Feeds only hold read links, so this only falls back for links listed some other way.
+fn read_at(link: &Link) -> DateTime<Utc> {
+    link.read_at()
+        .or_else(|| link.found_at())
+        .unwrap_or_else(Utc::now)
+}
+
+/// The most recent read time, which is when the feed last changed.
+fn updated(links: &[Link]) -> DateTime<Utc> {
+    links.iter().map(read_at).max().unwrap_or_else(Utc::now)
+}
+
+/// The link's notes rendered from markdown, or an empty string if it has none. Raw HTML in notes
+/// is left out, as comrak does by default.
+pub(crate) fn notes_html(link: &Link) -> String {
+    link.notes()
+        .filter(|notes| !notes.trim().is_empty())
+        .map(|notes| markdown_to_html(notes, &ComrakOptions::default()))
+        .unwrap_or_default()
+}
+
+/// The link's notes, followed by a credit for whoever or whatever it came via.
+fn entry_content(link: &Link) -> Option<String> {
+    let mut html = notes_html(link);
+
+    match link.via() {
+        Some(Via::Link(url)) => html.push_str(&format!(
+            "<p>via <a href=\"{}\">{}</a></p>\n",
+            escape(url),
+            escape(url)
+        )),
+        Some(Via::Friend(xs) | Via::Freeform(xs)) => {
+            html.push_str(&format!("<p>via {}</p>\n", escape(xs)))
+        }
+        None => {}
+    }
+
+    (!html.is_empty()).then_some(html)
+}
+
+/// Escape text for HTML. Unlike Tera's default, this leaves `/` alone so that paths stay readable.
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryStore;
+
+    fn links() -> Vec<Link> {
+        let mut rust = Link::new("https://example.com/rust", "Rust & <friends>");
+        rust.tags = vec!["lang/rust".to_string()];
+        rust.notes = Some("worth *reading*\n\n<script>alert(1)</script>".to_string());
+        rust.via = Some(Via::Link("https://example.org/?a=1&b=2".to_string()));
+        rust.read_at = Some("2023-02-01T00:00:00Z".parse().unwrap());
+
+        let mut go = Link::new("https://example.com/go", "Go");
+        go.tags = vec!["lang/go".to_string()];
+        go.via = Some(Via::Friend("alice".to_string()));
+        go.read_at = Some("2023-03-01T00:00:00Z".parse().unwrap());
+
+        let mut hidden = Link::new("https://example.com/hidden", "hidden");
+        hidden.tags = vec!["lang/rust".to_string()];
+        hidden.read_at = Some("2023-04-01T00:00:00Z".parse().unwrap());
+        hidden.hidden = true;
+
+        let mut unread = Link::new("https://example.com/unread", "unread");
+        unread.tags = vec!["lang/rust".to_string()];
+
+        vec![rust, go, hidden, unread]
+    }
+
+    #[tokio::test]
+    async fn lists_recently_read_visible_links() -> eyre::Result<()> {
+        let store = InMemoryStore::with_links(links());
+
+        let all = Feed::links(&store, None, 10).await?;
+        let urls: Vec<_> = all.iter().map(Link::url).collect();
+        assert_eq!(urls, ["https://example.com/go", "https://example.com/rust"]);
+
+        let rust = Feed::links(&store, Some("lang/rust".parse()?), 10).await?;
+        let urls: Vec<_> = rust.iter().map(Link::url).collect();
+        assert_eq!(urls, ["https://example.com/rust"]);
+
+        assert_eq!(Feed::links(&store, None, 1).await?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renders_each_format() -> eyre::Result<()> {
+        let store = InMemoryStore::with_links(links());
+        let links = Feed::links(&store, None, 10).await?;
+        let mut feed = Feed::new("Reading", "https://blog.example.com/");
+        feed.feed_url = Some("https://blog.example.com/feed.xml".to_string());
+
+        let atom: atom_syndication::Feed = feed.render(FeedFormat::Atom, &links)?.parse()?;
+        assert_eq!(atom.entries.len(), 2);
+        assert_eq!(atom.updated.to_rfc3339(), "2023-03-01T00:00:00+00:00");
+        let entry = &atom.entries[1];
+        assert_eq!(entry.title.value, "Rust & <friends>");
+        assert_eq!(entry.categories[0].term, "lang/rust");
+        let content = entry.content.as_ref().and_then(|c| c.value.as_deref());
+        assert!(content.unwrap().contains("<em>reading</em>"));
+        assert!(!content.unwrap().contains("<script>"));
+        assert!(content
+            .unwrap()
+            .contains("via <a href=\"https://example.org/?a=1&amp;b=2\">"));
+
+        let rss: rss::Channel = feed.render(FeedFormat::Rss, &links)?.parse()?;
+        assert_eq!(rss.items.len(), 2);
+        assert_eq!(
+            rss.items[0].pub_date.as_deref(),
+            Some("Wed, 1 Mar 2023 00:00:00 +0000")
+        );
+        assert_eq!(rss.items[0].categories[0].name, "lang/go");
+        assert_eq!(
+            rss.items[0].description.as_deref(),
+            Some("<p>via alice</p>")
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&feed.render(FeedFormat::Json, &links)?)?;
+        assert_eq!(json["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(json["feed_url"], "https://blog.example.com/feed.xml");
+        assert_eq!(json["items"][1]["url"], "https://example.com/rust");
+        assert_eq!(json["items"][1]["tags"][0], "lang/rust");
+        assert_eq!(json["items"][1]["date_published"], "2023-02-01T00:00:00Z");
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+mod live;
+mod record;
+mod replay;
+
+pub use live::*;
+pub use record::*;
+pub use replay::*;
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+pub type BodyStream = Pin<Box<dyn Stream<Item = eyre::Result<Bytes>> + Send>>;
+
+/// The parts of an HTTP response that link processing looks at. The body is streamed, so callers
+/// that decide not to keep it never download it.
+pub struct FetchResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: BodyStream,
+}
+
+impl FetchResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Read the rest of the body into memory.
+    pub async fn bytes(mut self) -> eyre::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        while let Some(chunk) = self.body.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Fetch urls on behalf of [`crate::HttpClientWrap`].
+#[async_trait::async_trait]
+pub trait Fetcher {
+    /// Fetch a url. `Ok(None)` means the url could not be reached right now, which is not
+    /// treated as a problem with the link.
+    async fn fetch(&self, url: &str) -> eyre::Result<Option<FetchResponse>>;
+}
+
+#[async_trait::async_trait]
+impl<T: Fetcher + Send + Sync + ?Sized> Fetcher for Box<T> {
+    async fn fetch(&self, url: &str) -> eyre::Result<Option<FetchResponse>> {
+        (**self).fetch(url).await
+    }
+}
+
+/// Replay from `LIKELIKE_HTTP_REPLAY_DIR` if set, otherwise fetch over the network, recording to
+/// `LIKELIKE_HTTP_RECORD_DIR` if set.
+pub fn fetcher_from_env() -> Box<dyn Fetcher + Send + Sync> {
+    if let Ok(dir) = env::var("LIKELIKE_HTTP_REPLAY_DIR") {
+        return Box::new(ReplayFetcher::new(dir));
+    }
+
+    let live = LiveFetcher::from_env();
+    match env::var("LIKELIKE_HTTP_RECORD_DIR") {
+        Ok(dir) => Box::new(RecordingFetcher::new(dir, live)),
+        Err(_) => Box::new(live),
+    }
+}
+
+/// A recorded response, stored as `<sha256 of url>.json` next to a `<sha256 of url>.body` file
+/// holding the response body.
+#[derive(Serialize, Deserialize)]
+struct Recording {
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+fn recording_paths(dir: &Path, url: &str) -> (PathBuf, PathBuf) {
+    let key: String = Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|xs| format!("{:02x}", xs))
+        .collect();
+
+    (
+        dir.join(format!("{key}.json")),
+        dir.join(format!("{key}.body")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header, routing::get, Router};
+
+    async fn stub_server() -> eyre::Result<String> {
+        let app = Router::new()
+            .route(
+                "/page",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+                        "<html><head><title>stub page</title></head></html>",
+                    )
+                }),
+            )
+            .route(
+                "/missing",
+                get(|| async { (axum::http::StatusCode::NOT_FOUND, "nope") }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Ok(base)
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() -> eyre::Result<()> {
+        let base = stub_server().await?;
+        let dir = tempfile::tempdir()?;
+        let recorder = RecordingFetcher::new(dir.path(), LiveFetcher::from_env());
+        let replay = ReplayFetcher::new(dir.path());
+
+        for path in ["/page", "/missing"] {
+            let url = format!("{base}{path}");
+            let live = recorder.fetch(&url).await?.expect("stub server is up");
+            let (status, headers) = (live.status, live.headers.clone());
+            let body = live.bytes().await?;
+
+            let replayed = replay.fetch(&url).await?.expect("response was recorded");
+            assert_eq!(replayed.status, status);
+            assert_eq!(replayed.headers, headers);
+            assert_eq!(replayed.bytes().await?, body);
+        }
+
+        let url = format!("{base}/never-fetched");
+        assert!(replay.fetch(&url).await?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use futures::StreamExt;
+use reqwest::{redirect::Policy, Client, ClientBuilder};
+use std::time::Duration;
+
+use super::{FetchResponse, Fetcher};
+
+/// Fetch urls over the network.
+pub struct LiveFetcher {
+    client: Client,
+}
+
+impl LiveFetcher {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// Build a client that follows up to `LIKELIKE_MAX_REDIRECTS` redirects (default 10) and
+    /// gives up after `LIKELIKE_REQUEST_TIMEOUT_SECONDS` (default 15.)
+    pub fn from_env() -> Self {
+        let agent = concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION"),
+            " (github.com/chrisdickinson/likelike)"
+        );
+
+        let max_redirects: usize = std::env::var("LIKELIKE_MAX_REDIRECTS")
+            .ok()
+            .and_then(|xs| xs.parse().ok())
+            .unwrap_or(10);
+
+        let timeout: u64 = std::env::var("LIKELIKE_REQUEST_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|xs| xs.parse().ok())
+            .unwrap_or(15);
+
+        let client = ClientBuilder::new()
+            .redirect(Policy::limited(max_redirects))
+            .user_agent(agent)
+            .timeout(Duration::new(timeout, 0))
+            .gzip(true)
+            .brotli(true)
+            .deflate(true)
+            .build()
+            .expect("default reqwest client could not be constructed");
+
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl Fetcher for LiveFetcher {
+    async fn fetch(&self, url: &str) -> eyre::Result<Option<FetchResponse>> {
+        let response = match self.client.get(url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                // We don't know if there's _really_ a problem if we can't connect: it could be our
+                // local network or the site could be temporarily unavailable. We only really want
+                // to throw up our hands if we're getting "oh no this site is complete garbage!"
+                if e.is_connect() {
+                    return Ok(None);
+                } else {
+                    return Err(e.into());
+                }
+            }
+        };
+
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+
+        Ok(Some(FetchResponse {
+            status: response.status().as_u16(),
+            headers,
+            body: Box::pin(response.bytes_stream().map(|xs| xs.map_err(Into::into))),
+        }))
+    }
+}
@@ -0,0 +1,58 @@
+use futures::StreamExt;
+use std::{io::Write, path::PathBuf};
+
+use super::{recording_paths, FetchResponse, Fetcher, Recording};
+
+/// Fetch urls through another fetcher, recording each response to a directory that a
+/// [`crate::ReplayFetcher`] can serve later. Only as much of the body as the caller reads is
+/// recorded, so replaying gives the caller the same bytes it saw.
+pub struct RecordingFetcher<F> {
+    directory: PathBuf,
+    inner: F,
+}
+
+impl<F> RecordingFetcher<F> {
+    pub fn new(directory: impl Into<PathBuf>, inner: F) -> Self {
+        Self {
+            directory: directory.into(),
+            inner,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Fetcher + Send + Sync> Fetcher for RecordingFetcher<F> {
+    async fn fetch(&self, url: &str) -> eyre::Result<Option<FetchResponse>> {
+        let Some(response) = self.inner.fetch(url).await? else {
+            return Ok(None);
+        };
+
+        std::fs::create_dir_all(&self.directory)?;
+        let (recording_path, body_path) = recording_paths(&self.directory, url);
+        let recording = Recording {
+            url: url.to_string(),
+            status: response.status,
+            headers: response.headers.clone(),
+        };
+        std::fs::write(recording_path, serde_json::to_vec_pretty(&recording)?)?;
+
+        let mut file = std::fs::File::create(body_path)?;
+        let mut body = response.body;
+        let body = async_stream::stream! {
+            while let Some(chunk) = body.next().await {
+                if let Ok(bytes) = &chunk {
+                    if let Err(e) = file.write_all(bytes) {
+                        yield Err(e.into());
+                        return;
+                    }
+                }
+                yield chunk;
+            }
+        };
+
+        Ok(Some(FetchResponse {
+            body: Box::pin(body),
+            ..response
+        }))
+    }
+}
@@ -0,0 +1,46 @@
+use bytes::Bytes;
+use std::{io::ErrorKind, path::PathBuf};
+
+use super::{recording_paths, FetchResponse, Fetcher, Recording};
+
+/// Serve responses recorded by a [`crate::RecordingFetcher`] without touching the network. Urls
+/// that were never recorded are treated as unreachable.
+pub struct ReplayFetcher {
+    directory: PathBuf,
+}
+
+impl ReplayFetcher {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Fetcher for ReplayFetcher {
+    async fn fetch(&self, url: &str) -> eyre::Result<Option<FetchResponse>> {
+        let (recording_path, body_path) = recording_paths(&self.directory, url);
+        let recording = match std::fs::read(recording_path) {
+            Ok(recording) => recording,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                eprintln!("no recorded response for {}", url);
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let recording: Recording = serde_json::from_slice(&recording)?;
+
+        let body = match std::fs::read(body_path) {
+            Ok(body) => body,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Some(FetchResponse {
+            status: recording.status,
+            headers: recording.headers,
+            body: Box::pin(futures::stream::once(async move { Ok(Bytes::from(body)) })),
+        }))
+    }
+}
@@ -13,17 +13,50 @@ use std::{
     collections::{HashMap, HashSet},
 };
 
+mod diff;
+mod digest;
 mod domain;
+mod dump;
 mod enrichment;
+mod export;
+mod feed;
+mod fetchers;
 mod processors;
+mod query;
+mod related;
 pub mod server;
+mod similarity;
+mod site;
 mod stores;
+mod suggest;
+mod summarizers;
+mod tags;
+mod timezone;
 
+pub use crate::diff::*;
+pub use crate::digest::*;
 pub use crate::domain::*;
+pub use crate::dump::*;
+pub use crate::export::*;
+pub use crate::feed::*;
+pub use crate::fetchers::*;
 pub use crate::processors::*;
+pub use crate::query::*;
+pub use crate::related::*;
+pub use crate::site::*;
 pub use crate::stores::*;
-
-pub async fn process_input<'a, S, Store>(input: S, store: &Store) -> eyre::Result<()>
+pub use crate::suggest::*;
+pub use crate::summarizers::*;
+pub use crate::tags::*;
+pub use crate::timezone::*;
+
+/// Parse links out of a link dump and write them to the store. Tags are normalized through the
+/// given aliases.
+pub async fn process_input<'a, S, Store>(
+    input: S,
+    store: &Store,
+    aliases: &TagAliases,
+) -> eyre::Result<()>
 where
     S: Into<LinkSource<'a>> + Send + Sync,
     Store: LinkReader + LinkWriter + Send + Sync,
@@ -70,7 +103,7 @@ where
                 continue;
             }
 
-            if extract_metadata_from_child_list(link, child).is_err() {
+            if extract_metadata_from_child_list(link, child, aliases).is_err() {
                 continue;
             }
         }
@@ -89,6 +122,7 @@ where
 fn extract_metadata_from_child_list<'a>(
     link: &mut Link,
     list: &'a Node<'a, RefCell<Ast>>,
+    aliases: &TagAliases,
 ) -> eyre::Result<()> {
     for list_item_node in list.children() {
         if !matches!(
@@ -141,7 +175,11 @@ fn extract_metadata_from_child_list<'a>(
                     }
                 }
 
-                link.tags.extend(tags);
+                for tag in tags.iter().filter_map(|tag| aliases.normalize(tag)) {
+                    if !link.tags.contains(&tag) {
+                        link.tags.push(tag);
+                    }
+                }
             }
 
             Some("via") => {
@@ -267,37 +305,50 @@ mod tests {
     use sqlx::sqlite::SqliteConnectOptions;
     use sqlx::Sqlite;
 
-    #[sqlx::test]
-    async fn test_parse_tags(
-        pool_opts: PoolOptions<Sqlite>,
-        connect_opts: SqliteConnectOptions,
-    ) -> eyre::Result<()> {
-        todo!()
-
-        /*
-                let store =
-                    crate::DummyWrap::new(SqliteStore::with_connection_options(connect_opts).await?);
-
-                process_input(
-                    r#"
-        - plain text link title: https://a.com/
-            - tags: hello, there, gawrsh, this is great, yep, ok
-        - [markdown style](https://b.com/)
-            - tags:
-                - hello
-                - there
-                - gawrsh
-                - this is great
-                - yep, ok
-        "#,
-                    &store,
-                )
-                .await?;
+    #[tokio::test]
+    async fn test_parse_tags() -> eyre::Result<()> {
+        let store = InMemoryStore::new();
+
+        process_input(
+            r#"
+- plain text link title: https://a.com/
+    - tags: hello, there, gawrsh, this is great, yep, ok
+- [markdown style](https://b.com/)
+    - tags:
+        - hello
+        - there
+        - gawrsh
+        - this is great
+        - yep, ok
+"#,
+            &store,
+            &TagAliases::default(),
+        )
+        .await?;
+
+        for url in ["https://a.com/", "https://b.com/"] {
+            let link = store.get(url).await?.expect("link was imported");
+            let mut tags = link.tags().clone();
+            tags.sort();
+            assert_eq!(
+                tags,
+                ["gawrsh", "hello", "ok", "there", "this is great", "yep"],
+                "{url}"
+            );
+        }
 
-                let link_a = store.get("https://a.com/").await?;
+        let link = store
+            .get("https://a.com/")
+            .await?
+            .expect("link was imported");
+        assert_eq!(link.title(), Some("plain text link title"));
+        let link = store
+            .get("https://b.com/")
+            .await?
+            .expect("link was imported");
+        assert_eq!(link.title(), Some("markdown style"));
 
-                Ok(())
-                */
+        Ok(())
     }
 
     #[sqlx::test]
@@ -308,28 +359,62 @@ mod tests {
         Ok(())
     }
 
-    #[sqlx::test]
-    async fn test_create_then_update_leaves_enriched_metadata_in_place(
-        pool_opts: PoolOptions<Sqlite>,
-        connect_opts: SqliteConnectOptions,
-    ) -> eyre::Result<()> {
-        todo!()
-        /*
-        let store =
-            crate::DummyWrap::new(SqliteStore::with_connection_options(connect_opts).await?);
-        // Make sure that:
-        // - title
-        // - image
-        // - TKTK
-        //
-        // are preserved when updating a previously-created link
+    #[tokio::test]
+    async fn test_create_then_update_leaves_enriched_metadata_in_place() -> eyre::Result<()> {
+        let store = InMemoryStore::from_json(
+            r#"{
+                "links": [{
+                    "url": "https://a.com/",
+                    "title": "A, as fetched",
+                    "tags": ["old"],
+                    "image": "https://a.com/a.png",
+                    "src": "<html><title>A, as fetched</title></html>",
+                    "meta": { "og:title": ["A"] },
+                    "found_at": "2023-01-01T00:00:00Z",
+                    "last_fetched": "2023-01-02T00:00:00Z"
+                }]
+            }"#,
+        )?;
+
+        process_input(
+            r#"
+- https://a.com/
+    - tags: new
+"#,
+            &store,
+            &TagAliases::default(),
+        )
+        .await?;
+
+        let link = store.get("https://a.com/").await?.expect("link exists");
+        assert_eq!(link.title(), Some("A, as fetched"));
+        assert_eq!(link.image(), Some("https://a.com/a.png"));
+        assert_eq!(
+            link.src(),
+            Some(&b"<html><title>A, as fetched</title></html>"[..])
+        );
+        assert_eq!(
+            link.meta().and_then(|meta| meta.get("og:title")),
+            Some(&vec!["A".to_string()])
+        );
+        assert_eq!(
+            link.found_at().map(|xs| xs.to_rfc3339()).as_deref(),
+            Some("2023-01-01T00:00:00+00:00")
+        );
+        assert!(link.last_fetched().is_some());
+
+        let mut tags = link.tags().clone();
+        tags.sort();
+        assert_eq!(tags, ["new", "old"]);
+
         Ok(())
-            */
     }
 
     #[tokio::test]
     async fn it_works() -> eyre::Result<()> {
-        let store = super::HttpClientWrap::wrap(
+        let recordings = tempfile::tempdir()?;
+        let store = super::HttpClientWrap::with_fetcher(
+            ReplayFetcher::new(recordings.path()),
             SqliteStore::with_connection_string("sqlite::memory:").await?,
         );
 
@@ -359,6 +444,7 @@ mod tests {
         - hello world
 "#,
             &store,
+            &TagAliases::default(),
         )
         .await?;
 
@@ -375,4 +461,64 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn imports_fetches_and_exports_from_recordings() -> eyre::Result<()> {
+        use axum::{http::header, routing::get, Router};
+
+        let app = Router::new().route(
+            "/post",
+            get(|| async {
+                (
+                    [(header::CONTENT_TYPE, "text/html")],
+                    r#"<html><head>
+                        <title>A post</title>
+                        <meta property="og:image" content="https://img.example/post.png">
+                    </head><body><p>Hello from the post.</p></body></html>"#,
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/post", listener.local_addr()?);
+        let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let input = format!("- {url}\n    - tags: example\n");
+        let recordings = tempfile::tempdir()?;
+        let recorder = HttpClientWrap::with_fetcher(
+            RecordingFetcher::new(recordings.path(), LiveFetcher::from_env()),
+            InMemoryStore::new(),
+        );
+        process_input(input.as_str(), &recorder, &TagAliases::default()).await?;
+        server.abort();
+
+        // With the server gone, the whole pipeline runs from the recording.
+        let cache = tempfile::tempdir()?;
+        let store = HttpClientWrap::with_fetcher(
+            ReplayFetcher::new(recordings.path()),
+            HtmlProcessorWrap::wrap(ExternalWrap::new(
+                cache.path().to_path_buf(),
+                InMemoryStore::new(),
+            )),
+        );
+        process_input(input.as_str(), &store, &TagAliases::default()).await?;
+
+        let link = store.get(url.as_str()).await?.expect("link was imported");
+        assert_eq!(link.title(), Some("A post"));
+        assert_eq!(link.image(), Some("https://img.example/post.png"));
+        assert!(link.last_fetched().is_some());
+        assert!(link
+            .extract_text()
+            .is_some_and(|text| text.contains("Hello from the post.")));
+
+        let frontmatter: Frontmatter = link.try_into()?;
+        let toml_out = toml::to_string_pretty(&frontmatter)?;
+        assert!(toml_out.contains(r#"title = "A post""#), "{toml_out}");
+        assert!(toml_out.contains(r#"tags = ["example"]"#), "{toml_out}");
+        assert!(
+            toml_out.contains("https://img.example/post.png"),
+            "{toml_out}"
+        );
+
+        Ok(())
+    }
 }
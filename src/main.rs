@@ -2,16 +2,24 @@
 
 use futures::{future::join_all, StreamExt};
 
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
 use std::path::PathBuf;
 use std::process::Command;
-use std::{collections::BTreeSet, io::Write};
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use likelike::{
-    process_input, ExternalWrap, Frontmatter, HtmlProcessorWrap, HttpClientWrap, LinkReader,
-    LinkSource, LinkWriter, TextProcessorWrap, PdfProcessorWrap, SqliteStore,
+    process_input, AnyStore, CacacheBlobStore, Digest, DigestPeriod, DisplayTimezone, ExportFormat,
+    ExportReconciler, ExportWriter, Exporter, ExternalWrap, FeedFormat, Frontmatter,
+    HtmlProcessorWrap, HttpClientWrap, InMemoryStore, Link, LinkDiff, LinkQuery, LinkReader,
+    LinkSource, LinkWriter, ListParams, MigrationState, OpenAiSummarizer, PdfProcessorWrap,
+    Predicate, Query, RelatedIndex, Site, Sort, SqliteStore, Summarizer, TagFilter, TagOperation,
+    TagSuggester, Term, TextProcessorWrap,
 };
 
+#[cfg(feature = "llm")]
+use likelike::LocalSummarizer;
+
 /// Process markdown-formatted linkdump files and store them in a sqlite database.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -20,7 +28,8 @@ struct Args {
     command: Commands,
 
     /// If not given, defaults to the local data dir per the "dirs" crate. E.g., on macOS, this
-    /// will be "sqlite:///Users/foo/Library/Application Support/likelike.sqlite3".
+    /// will be "sqlite:///Users/foo/Library/Application Support/likelike.sqlite3". A
+    /// "postgres://" url stores links in a (possibly shared) Postgres database instead.
     #[arg(short, long)]
     database_url: Option<String>,
 }
@@ -34,7 +43,6 @@ enum ShowMode {
 
     Attributions,
 
-    #[cfg(feature = "llm")]
     Summary,
 
     Metadata,
@@ -48,9 +56,30 @@ impl std::fmt::Display for ShowMode {
             ShowMode::Raw => f.write_str("raw"),
             ShowMode::Metadata => f.write_str("metadata"),
             ShowMode::Attributions => f.write_str("attributions"),
+            ShowMode::Summary => f.write_str("summary"),
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, ValueEnum)]
+enum SummaryBackend {
+    /// Use a GGML model loaded from `LIKELIKE_GGML`.
+    #[cfg(feature = "llm")]
+    Local,
+
+    /// Use an OpenAI-compatible chat completions server, configured by `LIKELIKE_OPENAI_BASE_URL`,
+    /// `LIKELIKE_OPENAI_MODEL`, and `LIKELIKE_OPENAI_API_KEY`.
+    #[default]
+    #[value(name = "openai")]
+    OpenAi,
+}
 
+impl std::fmt::Display for SummaryBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
             #[cfg(feature = "llm")]
-            ShowMode::Summary => f.write_str("summary"),
+            SummaryBackend::Local => f.write_str("local"),
+            SummaryBackend::OpenAi => f.write_str("openai"),
         }
     }
 }
@@ -80,22 +109,196 @@ enum Commands {
         /// Pass this argument to display imported link data.
         #[arg(long)]
         display_links: bool,
+
+        /// Process the files into memory, without fetching anything, and print what would change
+        /// in the database instead of writing to it.
+        #[arg(long)]
+        dry_run: bool,
     },
 
     Edit {
         url: String
     },
 
-    Tags,
+    /// List, rename, merge, or delete tags, and manage the aliases used to normalize tags on
+    /// import. Tags may be hierarchical, e.g. "lang/rust".
+    Tags {
+        #[command(subcommand)]
+        command: Option<TagsCommand>,
+    },
+
+    /// Suggest existing tags for untagged links by comparing their text against links that
+    /// already carry each tag, then interactively accept or reject them. Accepts globstar patterns.
+    SuggestTags {
+        #[arg(default_value_t=String::from("*"))]
+        url: String,
+
+        /// The maximum number of suggestions to offer per link.
+        #[arg(short, long, default_value_t = 5)]
+        limit: usize,
+
+        /// Also suggest tags for links that already have some.
+        #[arg(long)]
+        all: bool,
+
+        /// Print suggestions without prompting.
+        #[arg(long)]
+        no_prompt: bool,
+    },
 
     Rebuild,
 
+    /// Fetch links that have no source data. Set `LIKELIKE_HTTP_RECORD_DIR` to record every
+    /// response to a directory, or `LIKELIKE_HTTP_REPLAY_DIR` to serve responses from one
+    /// without touching the network; both apply to `import` as well.
     Refetch,
 
-    /// Export links from the database as zola markdown documents with Link metadata included in
-    /// frontmatter.
+    /// Export links from the database as markdown documents for a static site generator, with Link
+    /// metadata included in frontmatter, or as documents rendered from a template. Only changed
+    /// documents are rewritten, and documents a previous export wrote for links that are no longer
+    /// exported are removed.
     Export {
         output: PathBuf,
+
+        /// One of zola, hugo, hugo-yaml, jekyll, eleventy, obsidian, logseq, or template. Obsidian
+        /// and Logseq exports write notes into a vault, keeping anything below the marker line in
+        /// each note.
+        #[arg(short, long, default_value_t = ExportFormat::Zola)]
+        format: ExportFormat,
+
+        /// A Tera template for each document's path within the output directory, e.g.
+        /// "{{ year }}/{{ slug }}.md". Defaults to the slugified url, under `_posts/` and prefixed
+        /// with the date for jekyll.
+        #[arg(long)]
+        filename: Option<String>,
+
+        /// The Tera template to render each link with, for the template format.
+        #[arg(long)]
+        template: Option<PathBuf>,
+
+        /// The number of related links to include in each document's frontmatter. Pass 0 to skip
+        /// computing related links.
+        #[arg(long, default_value_t = 5)]
+        related: usize,
+
+        /// Only export links matching this query, e.g. `read:yes hidden:no tag:rust`.
+        #[arg(short, long, default_value = "read:yes hidden:no")]
+        query: Query,
+    },
+
+    /// Read a Zola export back into the database, adding links it is missing and applying edits
+    /// made to the exported files. A link that changed since it was exported is not overwritten
+    /// by an edited file; the conflict is reported instead.
+    ImportExport {
+        directory: PathBuf,
+
+        /// Print what would change without writing to the database.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Apply edited files even to links that changed since they were exported.
+        #[arg(long)]
+        overwrite: bool,
+    },
+
+    /// Show links related to the given link by content, tags, "via", and host.
+    Related {
+        url: String,
+
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Write an Atom, RSS, or JSON feed of the most recently read links that are not hidden, with
+    /// notes rendered as HTML, tags as categories, and "via" credited. Pass `--tag` to publish a
+    /// feed for a single topic.
+    Feed {
+        /// Write the feed to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// One of atom, rss, or json.
+        #[arg(short, long, default_value_t = FeedFormat::Atom)]
+        format: FeedFormat,
+
+        /// The url of the site the feed belongs to.
+        #[arg(long)]
+        url: String,
+
+        /// The url the feed itself will be published at.
+        #[arg(long)]
+        feed_url: Option<String>,
+
+        #[arg(long, default_value = "likelike")]
+        title: String,
+
+        #[arg(long)]
+        description: Option<String>,
+
+        /// Only include links with these tags, e.g. "lang/rust" or "+rust,-draft".
+        #[arg(short, long)]
+        tag: Option<TagFilter>,
+
+        /// The number of links to include.
+        #[arg(short = 'n', long, default_value_t = 50)]
+        limit: i64,
+    },
+
+    /// Render a static link blog: index pages paginated by read date, pages for each tag, each
+    /// friend links came via, and each link, along with Atom, RSS, and JSON feeds and a search
+    /// index. Hidden links are left out. Only changed files are rewritten, and files a previous
+    /// run wrote for pages that no longer exist are removed; what was written is recorded in
+    /// `.<output>.likelike-export.json` beside the output directory.
+    Site {
+        output: PathBuf,
+
+        /// The url the site will be published at, used for feeds.
+        #[arg(long)]
+        url: String,
+
+        #[arg(long, default_value = "likelike")]
+        title: String,
+
+        #[arg(long)]
+        description: Option<String>,
+
+        /// A directory of templates overriding the built-in theme: base.html, list.html,
+        /// link.html, terms.html, and files under static/.
+        #[arg(long)]
+        templates: Option<PathBuf>,
+
+        /// The number of links on each index, tag, and via page.
+        #[arg(long, default_value_t = 20)]
+        per_page: usize,
+
+        /// Only publish links matching this query.
+        #[arg(short, long, default_value = "read:yes hidden:no")]
+        query: Query,
+    },
+
+    /// Write a markdown post, with Zola frontmatter, of the links read over a period, grouped by
+    /// tag, with each link's host, an excerpt of its notes, and who or what it came via.
+    Digest {
+        /// Cover this span of time up to now, e.g. 7d, 2w, or 36h.
+        #[arg(long, conflicts_with = "month", required_unless_present = "month")]
+        since: Option<String>,
+
+        /// Cover a calendar month, e.g. 2024-03.
+        #[arg(long)]
+        month: Option<String>,
+
+        /// Write the post to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Record when each included link was digested, and leave out links an earlier digest
+        /// recorded, so that they aren't repeated. This is kept apart from a link's
+        /// `published_at`, which is when the linked page itself was published.
+        #[arg(long, alias = "mark-published")]
+        mark_digested: bool,
     },
 
     /// Show information about a given link. Accepts globstar patterns (be sure to single-quote
@@ -108,8 +311,33 @@ enum Commands {
         #[arg(short, long, default_value_t=ShowMode::List)]
         mode: ShowMode,
 
+        /// Filter by tag: `rust,go` matches either, `+rust,+async` requires both, and `-draft`
+        /// excludes a tag. Patterns may use `*` wildcards and also match child tags.
+        #[arg(short, long)]
+        tag: Option<TagFilter>,
+
+        /// Filter with a query, e.g. `tag:rust -tag:draft read:>2024-01 has:notes`.
+        #[arg(short, long)]
+        query: Option<Query>,
+
+        /// Sort by found, read, published, fetched, title or host, optionally followed by `:asc`
+        /// or `:desc`.
         #[arg(short, long)]
-        tag: Option<String>,
+        sort: Option<Sort>,
+    },
+
+    /// Summarize the extracted text of links and store the summaries. Links that already have a
+    /// summary newer than their last processing are skipped. Accepts globstar patterns.
+    Summarize {
+        #[arg(default_value_t=String::from("*"))]
+        url: String,
+
+        #[arg(short, long, default_value_t=SummaryBackend::OpenAi)]
+        backend: SummaryBackend,
+
+        /// Regenerate summaries even if they are fresh.
+        #[arg(long)]
+        force: bool,
     },
 
     /// Start a local web server for browsing and editing links.
@@ -117,29 +345,316 @@ enum Commands {
         #[arg(short, long, default_value_t = 3000)]
         port: u16,
     },
+
+    /// Inspect and migrate the database schema. Other commands apply pending migrations
+    /// automatically.
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+
+    /// Write every link and tag alias to a portable dump: JSON lines, one link or alias per line,
+    /// or with `--archive`, a zstd-compressed tar archive that also holds each link's cached source
+    /// data and extracted text.
+    Dump {
+        /// Write the dump to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[arg(long)]
+        archive: bool,
+    },
+
+    /// Load a dump written by `dump` into the database. Links that already exist are merged the
+    /// way importing them again would merge them.
+    Restore {
+        input: PathBuf,
+
+        /// Replace links, tag aliases, and cached blobs that already exist with the dump's,
+        /// instead of merging into them.
+        #[arg(long)]
+        replace: bool,
+    },
+
+    /// Inspect and clean up the cache directory holding link source data and extracted text.
+    /// Set `LIKELIKE_CACHE_MAX_BYTES` to cap its size; imports and refetches then evict the
+    /// least recently used source data, keeping extracted text.
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// List migrations and whether they have been applied.
+    Status,
+
+    /// Apply pending migrations.
+    Migrate,
+
+    /// Revert the most recently applied migrations.
+    Rollback {
+        #[arg(default_value_t = 1)]
+        steps: usize,
+    },
+
+    /// Move link source data stored in the database by older versions out to the cache
+    /// directory, then reclaim the space, leaving only link metadata in the database.
+    Compact,
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Remove cached data for links that no longer exist, evict source data if the cache is over
+    /// its size limit, and delete content that nothing refers to.
+    Gc {
+        /// Override `LIKELIKE_CACHE_MAX_BYTES` for this collection.
+        #[arg(long)]
+        max_bytes: Option<u64>,
+    },
+
+    /// Check cached data against its integrity hashes.
+    Verify {
+        /// Remove entries that fail the check, so `refetch` can replace them.
+        #[arg(long)]
+        remove: bool,
+    },
+
+    /// Show how much space cached data takes, by content type and by host.
+    Stats,
+}
+
+#[derive(Subcommand, Debug)]
+enum TagsCommand {
+    /// List every tag, including the parents of hierarchical tags.
+    List {
+        /// Only list tags on links matching this query.
+        #[arg(short, long)]
+        query: Option<Query>,
+    },
+
+    /// Rename a tag (and its children) on every link. The old name is recorded as an alias.
+    Rename { from: String, to: String },
+
+    /// Merge several tags (and their children) into one. The old names are recorded as aliases.
+    Merge {
+        #[arg(required = true)]
+        from: Vec<String>,
+
+        #[arg(long)]
+        into: String,
+    },
+
+    /// Remove a tag (and its children) from every link.
+    Delete {
+        tag: String,
+
+        /// Also drop the tag from future imports, by recording an alias to nothing.
+        #[arg(long)]
+        drop: bool,
+    },
+
+    /// List tag aliases.
+    Aliases,
+
+    /// Normalize `alias` to `tag` when importing links.
+    Alias { alias: String, tag: String },
+
+    /// Stop normalizing `alias` when importing links.
+    Unalias { alias: String },
 }
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
     let cli = Args::parse();
 
-    let store = if let Some(db_url) = cli.database_url {
-        SqliteStore::with_connection_string(db_url).await?
-    } else {
-        SqliteStore::new().await
-    };
+    // Report a bad LIKELIKE_TIMEZONE up front, rather than showing dates in local time.
+    DisplayTimezone::try_from_env()?;
+
+    let db_url = cli
+        .database_url
+        .unwrap_or_else(SqliteStore::default_connection_string);
+
+    // `db` manages migrations itself, so it must not migrate on connect.
+    if let Commands::Db { command } = cli.command {
+        let store = AnyStore::connect(db_url).await?;
+        match command {
+            DbCommand::Status => {
+                for migration in store.migration_status().await? {
+                    let state = match migration.state {
+                        MigrationState::Pending => "pending",
+                        MigrationState::Applied => "applied",
+                        MigrationState::Modified => "modified since applied",
+                        MigrationState::Unknown => "unknown (from a newer likelike)",
+                    };
+
+                    let applied_at = migration
+                        .applied_at
+                        .map(|t| {
+                            DisplayTimezone::from_env().localize(t)
+                                .format(" %Y-%m-%d %H:%M")
+                                .to_string()
+                        })
+                        .unwrap_or_default();
+
+                    println!(
+                        "{:04}-{}: {}{}",
+                        migration.version, migration.name, state, applied_at
+                    );
+                }
+            }
+
+            DbCommand::Migrate => {
+                let applied = store.migrate().await?;
+                if applied.is_empty() {
+                    println!("up to date");
+                }
+                for migration in applied {
+                    println!("applied {:04}-{}", migration.version, migration.name);
+                }
+            }
+
+            DbCommand::Rollback { steps } => {
+                for migration in store.rollback(steps).await? {
+                    println!("reverted {:04}-{}", migration.version, migration.name);
+                }
+            }
+
+            DbCommand::Compact => {
+                let compaction = store.compact(&CacacheBlobStore::from_env()).await?;
+                println!(
+                    "moved source data for {} links ({} bytes) out of the database",
+                    compaction.moved, compaction.bytes
+                );
+            }
+        }
+
+        return Ok(());
+    }
+
+    let store = AnyStore::with_connection_string(db_url).await?;
 
     match cli.command {
-        Commands::Tags => {
-            let mut links = store.values().await?;
-            let mut tags = BTreeSet::new();
+        Commands::Tags { command } => match command.unwrap_or(TagsCommand::List { query: None }) {
+            TagsCommand::List { query } => {
+                for tag in store.all_tags(query.as_ref()).await? {
+                    println!("{}", tag);
+                }
+            }
 
+            TagsCommand::Rename { from, to } => {
+                let changed = store
+                    .apply_tag_operation(&TagOperation::Rename { from, to })
+                    .await?;
+                println!("updated {} links", changed);
+            }
+
+            TagsCommand::Merge { from, into } => {
+                let changed = store
+                    .apply_tag_operation(&TagOperation::Merge { from, into })
+                    .await?;
+                println!("updated {} links", changed);
+            }
+
+            TagsCommand::Delete { tag, drop } => {
+                let changed = store
+                    .apply_tag_operation(&TagOperation::Delete { tag, drop })
+                    .await?;
+                println!("updated {} links", changed);
+            }
+
+            TagsCommand::Aliases => {
+                for (alias, tag) in store.tag_aliases().await?.iter() {
+                    if tag.is_empty() {
+                        println!("{} (dropped)", alias);
+                    } else {
+                        println!("{} -> {}", alias, tag);
+                    }
+                }
+            }
+
+            TagsCommand::Alias { alias, tag } => {
+                store.set_tag_alias(alias.as_str(), tag.as_str()).await?;
+            }
+
+            TagsCommand::Unalias { alias } => {
+                if !store.remove_tag_alias(alias.as_str()).await? {
+                    eprintln!("no such alias: {}", alias);
+                }
+            }
+        },
+
+        Commands::SuggestTags {
+            url,
+            limit,
+            all,
+            no_prompt,
+        } => {
+            let store = ExternalWrap::wrap(store);
+            let suggester = TagSuggester::from_store(&store).await?;
+            let mut links = store.glob(url.as_str()).await?;
+
+            let mut v = Vec::new();
             while let Some(link) = links.next().await {
-                tags.extend(link.tags().iter().cloned());
+                if all || link.tags().iter().all(|tag| tag.is_empty()) {
+                    v.push(link);
+                }
             }
+            drop(links);
+
+            let stdin = std::io::stdin();
+            'links: for mut link in v {
+                let suggestions = suggester.suggest(&link, limit);
+                if suggestions.is_empty() {
+                    continue;
+                }
+
+                println!("{}", link.url());
+                if let Some(title) = link.title() {
+                    println!("  {}", title);
+                }
+
+                let mut accepted = Vec::new();
+                let mut quit = false;
+                for suggestion in suggestions {
+                    if no_prompt {
+                        println!("  - {} ({:.3})", suggestion.tag, suggestion.score);
+                        continue;
+                    }
+
+                    print!(
+                        "  - {} ({:.3})? [y]es/[n]o/[s]kip link/[q]uit: ",
+                        suggestion.tag, suggestion.score
+                    );
+                    std::io::stdout().flush()?;
 
-            for tag in tags {
-                println!("{}", tag);
+                    let mut answer = String::new();
+                    if stdin.read_line(&mut answer)? == 0 {
+                        quit = true;
+                        break;
+                    }
+
+                    match answer.trim() {
+                        "y" | "yes" => accepted.push(suggestion.tag),
+                        "s" | "skip" => continue 'links,
+                        "q" | "quit" => {
+                            quit = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+
+                if !accepted.is_empty() {
+                    link.tags_mut().extend(accepted);
+                    store.write(link).await?;
+                    println!("\x1b[32m  saved!\x1b[0m");
+                }
+
+                if quit {
+                    break;
+                }
             }
         }
 
@@ -150,6 +665,7 @@ async fn main() -> eyre::Result<()> {
             //      nvim '+vsplit' '+term likelike show https://xeiaso.net/blog/carcinization-golang -m text' justfile
             // if the file change
             //
+            let store = ExternalWrap::wrap(store).without_sources();
             let mut links = store.glob(url.as_str()).await?;
             let mut v = Vec::new();
             while let Some(link) = links.next().await {
@@ -224,6 +740,8 @@ async fn main() -> eyre::Result<()> {
                     println!("\x1b[33m skip!\x1b[0m");
                 }
             }
+
+            CacacheBlobStore::from_env().evict().await?;
         }
 
         Commands::Rebuild => {
@@ -243,18 +761,35 @@ async fn main() -> eyre::Result<()> {
             }
         }
 
-        Commands::Show { url, mode, tag } => {
-            let store = ExternalWrap::wrap(store);
-            let store = &store;
-            let mut links = store.glob(url.as_str()).await?;
-            let filter = tag.map(|t| wildmatch::WildMatch::new(&t));
+        Commands::Show {
+            url,
+            mode,
+            tag,
+            query,
+            sort,
+        } => {
+            let mut query = query.unwrap_or_default();
+            if url != "*" {
+                query.terms.push(Term {
+                    negated: false,
+                    predicate: Predicate::Url(url),
+                });
+            }
 
-            while let Some(link) = links.next().await {
-                if let Some(ref filter) = filter {
-                    if !link.tags().iter().any(|t| filter.matches(t)) {
-                        continue;
+            let store = ExternalWrap::wrap(store);
+            let params = ListParams::all(Some(query), tag, sort.unwrap_or_default());
+            for link in store.list_all(params).await? {
+                // Listings omit source data, extracted text and headers; only fetch the whole
+                // link when showing those.
+                let link = match mode {
+                    ShowMode::Text | ShowMode::Raw | ShowMode::Metadata => {
+                        match store.get(link.url()).await? {
+                            Some(link) => link,
+                            None => continue,
+                        }
                     }
-                }
+                    _ => link,
+                };
 
                 match mode {
                     ShowMode::Attributions => {
@@ -297,25 +832,25 @@ async fn main() -> eyre::Result<()> {
                         }
 
                         let found_at = link.found_at().map(|t| {
-                            t.with_timezone(&chrono::Local)
+                            DisplayTimezone::from_env().localize(t)
                                 .format("%Y-%m-%d %l:%M%P")
                                 .to_string()
                                 .replace("  ", " ")
                         });
                         let read_at = link.read_at().map(|t| {
-                            t.with_timezone(&chrono::Local)
+                            DisplayTimezone::from_env().localize(t)
                                 .format("%Y-%m-%d %l:%M%P")
                                 .to_string()
                                 .replace("  ", " ")
                         });
                         let fetched = link.last_fetched().map(|t| {
-                            t.with_timezone(&chrono::Local)
+                            DisplayTimezone::from_env().localize(t)
                                 .format("%Y-%m-%d %l:%M%P")
                                 .to_string()
                                 .replace("  ", " ")
                         });
                         let processed = link.last_processed().map(|t| {
-                            t.with_timezone(&chrono::Local)
+                            DisplayTimezone::from_env().localize(t)
                                 .format("%Y-%m-%d %l:%M%P")
                                 .to_string()
                                 .replace("  ", " ")
@@ -390,151 +925,217 @@ async fn main() -> eyre::Result<()> {
                         }
                     }
 
-                    #[cfg(feature = "llm")]
                     ShowMode::Summary => {
-                        use itertools::Itertools;
-                        use llm::{samplers::TopPTopK, Model, ModelParameters};
-                        use std::sync::Arc;
+                        if let Some(summary) = link.summary() {
+                            println!("{}", summary);
+                        }
+                    }
+                }
+            }
+        }
 
-                        if let Some(src) = link.extract_text() {
-                            let ggml = std::env::var("LIKELIKE_GGML")
-                                .ok()
-                                .unwrap_or_else(|| "ggml-vicuna-13B-1.1-q5_1.bin".to_string());
-                            // load a GGML model from disk
-                            let llama = llm::load::<llm::models::Llama>(
-                                // path to GGML file
-                                std::path::Path::new(ggml.as_str()),
-                                llm::VocabularySource::Model,
-                                // llm::ModelParameters
-                                ModelParameters {
-                                    context_size: 8192,
-                                    ..Default::default()
-                                },
-                                // load progress callback
-                                // llm::load_progress_callback_stdout
-                                |_| {},
-                            )
-                            .unwrap_or_else(|err| panic!("Failed to load model: {err}"));
-
-                            // use the model to generate text from a prompt
-                            let mut session = llama.start_session(Default::default());
-
-                            let src: String = itertools::join(
-                                src.lines().filter(|xs| {
-                                    !xs.starts_with('[')
-                                        && !xs.starts_with('#')
-                                        && !xs.trim().is_empty()
-                                }),
-                                "\n",
-                            );
-                            let src: String = src.replace(|c| matches!(c, '*' | '\u{fffd}'), "");
+        Commands::Export {
+            output,
+            format,
+            filename,
+            template,
+            related,
+            query,
+        } => {
+            let exporter = Exporter::new(format, filename.as_deref(), template.as_deref())?;
+            let v = store
+                .list_all(ListParams::all(Some(query), None, Sort::default()))
+                .await?;
+
+            // Only exported links are indexed, so related links never point at missing pages.
+            let index = if related > 0 {
+                let blobs = CacacheBlobStore::from_env();
+                Some(RelatedIndex::with_text_from(v.iter(), &blobs).await?)
+            } else {
+                None
+            };
+
+            let mut export = ExportWriter::open(&output)?;
+            if let Some(marker) = exporter.edit_marker() {
+                export = export.preserving_edits(marker);
+            }
 
-                            let mut output = Vec::with_capacity(2048);
-                            let paras: Vec<_> = src.split('\n').collect();
-                            for paras in &paras.into_iter().chunks(4) {
-                                let next_paras = itertools::join(paras, "\n");
+            for (filename, document) in exporter.extra_documents(v.iter()) {
+                export.write(&filename, document.as_bytes())?;
+            }
 
-                                let prompt = format!(
-                                    indoc::indoc! {r#"Below is an instruction that describes a task. Write a response that appropriately completes the request.
+            for link in v {
+                let related_links = index
+                    .as_ref()
+                    .map(|index| index.related(link.url(), related))
+                    .unwrap_or_default();
+
+                let url = link.url().to_string();
+                let fingerprint = LinkDiff::fingerprint(&link);
+                let (filename, document) = match exporter.render(link, related_links) {
+                    Ok(rendered) => rendered,
+                    Err(e) => {
+                        // Leave the file an earlier export wrote for it in place.
+                        eprintln!("skipping {}: {:#}", url, e);
+                        export.keep_link(&url);
+                        continue;
+                    }
+                };
 
-                                ### Instruction:
+                export.write_link(&filename, document.as_bytes(), &url, fingerprint)?;
+            }
 
-                                Write a concise, 100 word summary of the following markdown text.
+            print!("{}", export.finish()?);
+        }
 
-                                #### Text:
-                                {}
+        Commands::ImportExport {
+            directory,
+            dry_run,
+            overwrite,
+        } => {
+            let reconciler = ExportReconciler { dry_run, overwrite };
+            let store = ExternalWrap::wrap(store).without_sources();
+            print!("{}", reconciler.reconcile(&store, &directory).await?);
+            if dry_run {
+                eprintln!("dry run; nothing was written");
+            }
+        }
 
-                                ### Response:
-                                "#},
-                                    next_paras
-                                );
-                                output.clear();
-
-                                let res = session.infer::<std::convert::Infallible>(
-                                    // model to use for text generation
-                                    &llama,
-                                    // randomness provider
-                                    &mut rand::thread_rng(),
-                                    // the prompt to use for text generation, as well as other
-                                    // inference parameters
-                                    &llm::InferenceRequest {
-                                        prompt: prompt.as_str().into(),
-                                        parameters: &llm::InferenceParameters {
-                                            sampler: Arc::new(TopPTopK {
-                                                top_k: 40,
-                                                top_p: 0.95,
-                                                repeat_penalty: 1.30,
-                                                temperature: 0.50,
-                                                repetition_penalty_last_n: 512,
-                                                ..Default::default()
-                                            }),
-                                            ..Default::default()
-                                        },
-                                        play_back_previous_tokens: true,
-                                        maximum_token_count: None,
-                                    },
-                                    // llm::OutputRequest
-                                    &mut Default::default(),
-                                    // output callback
-                                    |r| match r {
-                                        llm::InferenceResponse::PromptToken(t) => {
-                                            print!("\x1b[31m{t}\x1b[0m");
-                                            std::io::stdout().flush().unwrap();
-                                            Ok(llm::InferenceFeedback::Continue)
-                                        }
-
-                                        llm::InferenceResponse::InferredToken(t) => {
-                                            print!("\x1b[34m{}\x1b[0m", t.as_str());
-                                            output.push(t);
-                                            std::io::stdout().flush().unwrap();
-                                            if output.len() > 128 {
-                                                Ok(llm::InferenceFeedback::Halt)
-                                            } else {
-                                                Ok(llm::InferenceFeedback::Continue)
-                                            }
-                                        }
-
-                                        _ => Ok(llm::InferenceFeedback::Continue),
-                                    },
-                                );
+        Commands::Related { url, limit } => {
+            let store = ExternalWrap::wrap(store).without_sources();
+            let index = RelatedIndex::from_store(&store).await?;
 
-                                match res {
-                                    Ok(result) => println!("\n\nInference stats:\n{result}"),
-                                    Err(err) => println!("\naw heck {err}"),
-                                }
-                            }
-                        }
-                    }
-                }
+            for related in index.related(url.as_str(), limit) {
+                println!(
+                    "{:.3} {} ({})",
+                    related.score,
+                    related.url,
+                    related.reasons.join("; ")
+                );
             }
         }
 
-        Commands::Export { output } => {
-            let mut links = store.values().await?;
+        Commands::Feed {
+            output,
+            format,
+            url,
+            feed_url,
+            title,
+            description,
+            tag,
+            limit,
+        } => {
+            let feed = likelike::Feed {
+                title,
+                home_page_url: url,
+                feed_url,
+                description,
+            };
+            let links = likelike::Feed::links(&store, tag, limit).await?;
+            let document = feed.render(format, &links)?;
+
+            match output {
+                Some(path) => std::fs::write(path, document)?,
+                None => println!("{}", document),
+            }
+        }
 
-            while let Some(link) = links.next().await {
-                if link.read_at().is_none() || link.hidden() {
+        Commands::Site {
+            output,
+            url,
+            title,
+            description,
+            templates,
+            per_page,
+            query,
+        } => {
+            let mut site = Site::new(title, url, templates.as_deref())?;
+            site.description = description;
+            site.per_page = per_page;
+
+            let links = Site::links(&store, query).await?;
+            let mut out = Site::writer(&output)?;
+            site.render(&links, &mut out)?;
+            print!("{}", out.finish()?);
+        }
+
+        Commands::Digest {
+            since,
+            month,
+            output,
+            title,
+            mark_digested,
+        } => {
+            let period = match (since, month) {
+                (Some(since), _) => DigestPeriod::since(&since)?,
+                (None, Some(month)) => DigestPeriod::month(&month)?,
+                (None, None) => unreachable!("clap requires --since or --month"),
+            };
+
+            let now = chrono::Utc::now();
+            let timezone = DisplayTimezone::from_env();
+            let mut digest = Digest::collect(&store, period, mark_digested, now, timezone).await?;
+            if let Some(title) = title {
+                digest.title = title;
+            }
+
+            let post = digest.render()?;
+            match output {
+                Some(path) => std::fs::write(path, post)?,
+                None => print!("{}", post),
+            }
+
+            if mark_digested {
+                // Read through the blob store so that source data left in older databases isn't
+                // written back.
+                let store = ExternalWrap::wrap(store).without_sources();
+                digest.mark_digested(&store, now).await?;
+                eprintln!("marked {} links digested", digest.links.len());
+            }
+        }
+
+        Commands::Import {
+            files,
+            display_links,
+            dry_run: true,
+        } => {
+            let aliases = store.tag_aliases().await?;
+            let existing: Vec<Link> = store.values().await?.collect().await;
+            let memory = InMemoryStore::with_links(existing.iter().cloned());
+
+            let mut resolved_files = Vec::new();
+            _find_markdown_files(&mut resolved_files, files, FindMode::Explicit)?;
+            for file in resolved_files {
+                let link_source = LinkSource::from_path(file.as_path())?;
+                process_input(link_source, &memory, &aliases).await?;
+            }
+
+            let existing: HashMap<_, _> = existing
+                .iter()
+                .map(|link| (link.url(), link))
+                .collect();
+
+            let mut changed = 0;
+            for link in memory.links().await {
+                let Some(diff) = LinkDiff::new(existing.get(link.url()).copied(), &link) else {
                     continue;
+                };
+                print!("{}", diff);
+                if display_links {
+                    eprintln!("{:?}", link);
                 }
-
-                let Ok(frontmatter): Result<Frontmatter, _> = link.try_into() else { continue };
-                let mut path = output.clone();
-                path.push(frontmatter.filename());
-                std::fs::write(
-                    path,
-                    format!(
-                        "+++\n{}\n+++\n{}",
-                        toml::to_string_pretty(&frontmatter)?,
-                        frontmatter.notes()
-                    ),
-                )?;
+                changed += 1;
             }
+            eprintln!("{} links would change", changed);
         }
 
         Commands::Import {
             files,
             display_links,
+            dry_run: false,
         } => {
+            let aliases = store.tag_aliases().await?;
+            let aliases = &aliases;
             let store = HttpClientWrap::wrap(HtmlProcessorWrap::wrap(ExternalWrap::wrap(store)));
             let store = &store;
 
@@ -545,7 +1146,7 @@ async fn main() -> eyre::Result<()> {
             for file in resolved_files.into_iter() {
                 futs.push(async move {
                     let link_source = LinkSource::from_path(file.as_path())?;
-                    process_input(link_source, store).await?;
+                    process_input(link_source, store, aliases).await?;
                     Ok(file) as eyre::Result<PathBuf>
                 });
             }
@@ -555,6 +1156,8 @@ async fn main() -> eyre::Result<()> {
                 eprintln!("processed \"{}\"", file.to_string_lossy());
             }
 
+            CacacheBlobStore::from_env().evict().await?;
+
             if display_links {
                 let mut links = store.values().await?;
 
@@ -564,10 +1167,159 @@ async fn main() -> eyre::Result<()> {
             }
         }
 
+        Commands::Summarize {
+            url,
+            backend,
+            force,
+        } => {
+            let summarizer: Box<dyn Summarizer + Send + Sync> = match backend {
+                #[cfg(feature = "llm")]
+                SummaryBackend::Local => Box::new(LocalSummarizer::from_env()?),
+                SummaryBackend::OpenAi => Box::new(OpenAiSummarizer::from_env()?),
+            };
+
+            let store = ExternalWrap::wrap(store);
+            let mut links = store.glob(url.as_str()).await?;
+
+            let mut v = Vec::new();
+            while let Some(link) = links.next().await {
+                v.push(link);
+            }
+            drop(links);
+
+            for mut link in v {
+                print!("{}...", link.url());
+                if !force && link.has_fresh_summary() {
+                    println!("\x1b[33m skip!\x1b[0m");
+                    continue;
+                }
+
+                let Some(text) = link.extract_text().filter(|xs| !xs.trim().is_empty()) else {
+                    println!("\x1b[33m no text!\x1b[0m");
+                    continue;
+                };
+
+                let summary = summarizer.summarize(text).await;
+                match summary {
+                    Ok(summary) => {
+                        *link.summary_mut() = Some(summary);
+                        *link.summarized_at_mut() = Some(chrono::Utc::now());
+                        store.write(link).await?;
+                        println!("\x1b[32m done!\x1b[0m");
+                    }
+                    Err(e) => {
+                        println!("\x1b[31m error!\x1b[0m {}", e);
+                    }
+                }
+            }
+        }
+
         Commands::Serve { port } => {
             let store = std::sync::Arc::new(store);
             likelike::server::serve(store, port).await?;
         }
+
+        Commands::Dump { output, archive } => {
+            let out: Box<dyn Write> = match output {
+                Some(path) => Box::new(std::fs::File::create(path)?),
+                None => Box::new(std::io::stdout().lock()),
+            };
+
+            let summary = if archive {
+                likelike::dump_archive(&store, &CacacheBlobStore::from_env(), out).await?
+            } else {
+                likelike::dump_jsonl(&store, out).await?
+            };
+            eprintln!(
+                "dumped {} links, {} tag aliases and {} cached blobs",
+                summary.links, summary.tag_aliases, summary.blobs
+            );
+        }
+
+        Commands::Restore { input, replace } => {
+            let input = std::fs::File::open(input)?;
+            let store = ExternalWrap::wrap(store).without_sources();
+            let summary =
+                likelike::restore(&store, &CacacheBlobStore::from_env(), input, replace).await?;
+            eprintln!(
+                "restored {} links, {} tag aliases and {} cached blobs",
+                summary.links, summary.tag_aliases, summary.blobs
+            );
+        }
+
+        Commands::Cache { command } => match command {
+            CacheCommand::Gc { max_bytes } => {
+                let mut blobs = CacacheBlobStore::from_env();
+                if max_bytes.is_some() {
+                    blobs = blobs.with_max_bytes(max_bytes);
+                }
+
+                let urls: HashSet<String> = store
+                    .values()
+                    .await?
+                    .map(|link| link.url().to_string())
+                    .collect()
+                    .await;
+                let collection = blobs.gc(&urls).await?;
+                println!(
+                    "removed {} orphaned entries and evicted source data for {} links; deleted {} files ({} bytes)",
+                    collection.orphaned, collection.evicted, collection.files, collection.bytes
+                );
+            }
+
+            CacheCommand::Verify { remove } => {
+                let verification = CacacheBlobStore::from_env().verify(remove).await?;
+                for (key, reason) in &verification.failed {
+                    println!("{}: {}", key, reason);
+                }
+                println!(
+                    "checked {} entries, {} failed{}",
+                    verification.checked,
+                    verification.failed.len(),
+                    if remove && !verification.failed.is_empty() {
+                        " and were removed"
+                    } else {
+                        ""
+                    }
+                );
+                if !remove && !verification.failed.is_empty() {
+                    std::process::exit(1);
+                }
+            }
+
+            CacheCommand::Stats => {
+                let links: HashMap<String, Link> = store
+                    .values()
+                    .await?
+                    .map(|link| (link.url().to_string(), link))
+                    .collect()
+                    .await;
+                let stats = CacacheBlobStore::from_env().stats(&links)?;
+
+                for (heading, usage) in [
+                    ("content type", stats.by_content_type),
+                    ("host", stats.by_host),
+                ] {
+                    let mut usage: Vec<_> = usage.into_iter().collect();
+                    usage.sort_by_key(|(_, usage)| std::cmp::Reverse(usage.bytes));
+
+                    println!("by {}:", heading);
+                    for (name, usage) in usage {
+                        println!(
+                            "{:>12} bytes {:>6} entries  {}",
+                            usage.bytes, usage.entries, name
+                        );
+                    }
+                    println!();
+                }
+                println!(
+                    "{:>12} bytes {:>6} entries  total",
+                    stats.total.bytes, stats.total.entries
+                );
+            }
+        },
+
+        Commands::Db { .. } => unreachable!("db commands run before migrating"),
     }
 
     Ok(())
@@ -1,4 +1,4 @@
-use crate::{Link, LinkReader};
+use crate::{Link, LinkQuery, LinkReader, ListPage, ListParams, Query, TagAliases, TagOperation};
 use futures::Stream;
 use std::pin::Pin;
 mod html;
@@ -60,3 +60,40 @@ where
         Ok(Box::pin(links))
     }
 }
+
+// Listings go straight to the wrapped store: hydrating every link on a page would be costly, and
+// listings omit source data anyway.
+#[async_trait::async_trait]
+impl<T> LinkQuery for T
+where
+    T: LinkReadProcessor + Send + Sync,
+    T::Inner: LinkQuery + Send + Sync,
+{
+    async fn count(&self, params: &ListParams) -> eyre::Result<i64> {
+        self.inner().count(params).await
+    }
+
+    async fn list(&self, params: &ListParams) -> eyre::Result<ListPage> {
+        self.inner().list(params).await
+    }
+
+    async fn all_tags(&self, query: Option<&Query>) -> eyre::Result<Vec<String>> {
+        self.inner().all_tags(query).await
+    }
+
+    async fn apply_tag_operation(&self, op: &TagOperation) -> eyre::Result<u64> {
+        self.inner().apply_tag_operation(op).await
+    }
+
+    async fn tag_aliases(&self) -> eyre::Result<TagAliases> {
+        self.inner().tag_aliases().await
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str) -> eyre::Result<()> {
+        self.inner().set_tag_alias(alias, tag).await
+    }
+
+    async fn remove_tag_alias(&self, alias: &str) -> eyre::Result<bool> {
+        self.inner().remove_tag_alias(alias).await
+    }
+}
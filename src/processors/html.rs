@@ -1,5 +1,5 @@
-use crate::{processors::LinkReadProcessor, Link, LinkReader, LinkWriter};
-use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use crate::{processors::LinkReadProcessor, DisplayTimezone, Link, LinkReader, LinkWriter};
+use chrono::{DateTime, NaiveDate, Utc};
 
 use scraper::{Html, Selector};
 use std::collections::HashMap;
@@ -57,10 +57,15 @@ fn process_html(mut link: Link) -> eyre::Result<Link> {
     let mut meta = HashMap::new();
 
     let mut update_pubdate = |weight, pd: &str| {
-        let Ok(pd) = NaiveDate::parse_from_str(pd, "%Y-%m-%d") else { return };
-        let Some(pd) = pd.and_hms_milli_opt(0, 0, 0, 0) else { return };
-        let Some(pd) = Local.from_local_datetime(&pd).latest() else { return };
-        let pd = DateTime::<Utc>::from(pd);
+        // Pages give either a full timestamp or a bare date, which is taken as midnight locally.
+        let pd = match DateTime::parse_from_rfc3339(pd.trim()) {
+            Ok(pd) => pd.with_timezone(&Utc),
+            Err(_) => {
+                let Ok(pd) = NaiveDate::parse_from_str(pd.trim(), "%Y-%m-%d") else { return };
+                let Some(pd) = DisplayTimezone::from_env().midnight(pd) else { return };
+                pd
+            }
+        };
 
         if let Some((current, _)) = pubdate {
             if current < weight {
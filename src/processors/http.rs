@@ -1,54 +1,156 @@
 use chrono::Utc;
-use futures::Stream;
-use reqwest::{redirect::Policy, Client, ClientBuilder};
+use futures::{Stream, StreamExt};
+use reqwest::Client;
 use std::collections::HashMap;
-use std::{env, pin::Pin, time::Duration};
+use std::pin::Pin;
 
-use crate::{Link, LinkReader, LinkWriter};
+use crate::{
+    fetcher_from_env, BodyStream, Fetcher, Link, LinkQuery, LinkReader, LinkWriter, ListPage,
+    ListParams, LiveFetcher, Query, TagAliases, TagOperation,
+};
+
+/// How many bytes of a response body to keep, by kind of content. HTML and plain text bodies
+/// past their limit are truncated, since the start of a document is still worth processing. PDF
+/// bodies past their limit are not kept at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLimits {
+    pub html: usize,
+    pub pdf: usize,
+    pub text: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            html: 8 << 20,
+            pdf: 64 << 20,
+            text: 8 << 20,
+        }
+    }
+}
+
+impl BodyLimits {
+    /// Read limits from `LIKELIKE_MAX_HTML_BYTES`, `LIKELIKE_MAX_PDF_BYTES` and
+    /// `LIKELIKE_MAX_TEXT_BYTES`, defaulting to 8MiB, 64MiB and 8MiB respectively.
+    pub fn from_env() -> Self {
+        let limit = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|xs| xs.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let defaults = Self::default();
+        Self {
+            html: limit("LIKELIKE_MAX_HTML_BYTES", defaults.html),
+            pdf: limit("LIKELIKE_MAX_PDF_BYTES", defaults.pdf),
+            text: limit("LIKELIKE_MAX_TEXT_BYTES", defaults.text),
+        }
+    }
+}
+
+/// The kinds of response body that are kept, each with its own limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyKind {
+    Html,
+    Pdf,
+    Text,
+}
+
+impl BodyKind {
+    fn of(link: &Link) -> Option<Self> {
+        if link.is_html() {
+            Some(BodyKind::Html)
+        } else if link.is_pdf() {
+            Some(BodyKind::Pdf)
+        } else if link.is_plaintext() {
+            Some(BodyKind::Text)
+        } else {
+            None
+        }
+    }
+
+    fn limit(&self, limits: &BodyLimits) -> usize {
+        match self {
+            BodyKind::Html => limits.html,
+            BodyKind::Pdf => limits.pdf,
+            BodyKind::Text => limits.text,
+        }
+    }
+
+    /// Whether the start of an oversized body is still worth keeping. A truncated PDF can't be
+    /// read at all.
+    fn keeps_prefix(&self) -> bool {
+        !matches!(self, BodyKind::Pdf)
+    }
+}
+
+impl std::fmt::Display for BodyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BodyKind::Html => "html",
+            BodyKind::Pdf => "pdf",
+            BodyKind::Text => "text",
+        })
+    }
+}
 
 pub struct HttpClientWrap<T> {
-    client: Client,
+    fetcher: Box<dyn Fetcher + Send + Sync>,
+    limits: BodyLimits,
     inner: T,
 }
 
 impl<T> HttpClientWrap<T> {
     pub fn new(client: Client, inner: T) -> Self {
-        Self { client, inner }
+        Self::with_fetcher(LiveFetcher::new(client), inner)
     }
 
-    pub fn wrap(inner: T) -> Self {
-        let agent = concat!(
-            env!("CARGO_PKG_NAME"),
-            "/",
-            env!("CARGO_PKG_VERSION"),
-            " (github.com/chrisdickinson/likelike)"
-        );
-
-        let max_redirects: usize = std::env::var("LIKELIKE_MAX_REDIRECTS")
-            .ok()
-            .and_then(|xs| xs.parse().ok())
-            .unwrap_or(10);
+    pub fn with_fetcher(fetcher: impl Fetcher + Send + Sync + 'static, inner: T) -> Self {
+        Self {
+            fetcher: Box::new(fetcher),
+            limits: BodyLimits::from_env(),
+            inner,
+        }
+    }
 
-        let timeout: u64 = std::env::var("LIKELIKE_REQUEST_TIMEOUT_SECONDS")
-            .ok()
-            .and_then(|xs| xs.parse().ok())
-            .unwrap_or(15);
+    /// Fetch over the network, or record or replay responses, per [`fetcher_from_env`].
+    pub fn wrap(inner: T) -> Self {
+        Self {
+            fetcher: fetcher_from_env(),
+            limits: BodyLimits::from_env(),
+            inner,
+        }
+    }
 
-        let client = ClientBuilder::new()
-            .redirect(Policy::limited(max_redirects))
-            .user_agent(agent)
-            .timeout(Duration::new(timeout, 0))
-            .gzip(true)
-            .brotli(true)
-            .deflate(true)
-            .build()
-            .expect("default reqwest client could not be constructed");
+    pub fn with_limits(mut self, limits: BodyLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
 
-        Self { inner, client }
+/// Read at most `limit` bytes of a body, stopping the download there. Returns the bytes read and
+/// whether the body went on past them.
+async fn read_body(mut body: BodyStream, limit: usize) -> eyre::Result<(Vec<u8>, bool)> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        let room = limit - bytes.len();
+        if chunk.len() > room {
+            bytes.extend_from_slice(&chunk[..room]);
+            return Ok((bytes, true));
+        }
+        bytes.extend_from_slice(&chunk);
     }
+
+    Ok((bytes, false))
 }
 
-pub(crate) async fn fetch_link(mut link: Link, client: &Client) -> eyre::Result<Link> {
+pub(crate) async fn fetch_link(
+    mut link: Link,
+    fetcher: &(dyn Fetcher + Send + Sync),
+    limits: BodyLimits,
+) -> eyre::Result<Link> {
     if link.last_fetched.is_some() {
         eprintln!(
             "not fetching {}, last_fetched is {}",
@@ -58,30 +160,20 @@ pub(crate) async fn fetch_link(mut link: Link, client: &Client) -> eyre::Result<
         return Ok(link);
     }
 
-    let response = match client.get(link.url()).send().await {
-        Ok(response) => response,
-        Err(e) => {
-            // We don't know if there's _really_ a problem if we can't connect: it could be our
-            // local network or the site could be temporarily unavailable. We only really want
-            // to throw up our hands if we're getting "oh no this site is complete garbage!"
-            if e.is_connect() {
-                return Ok(link);
-            } else {
-                return Err(e.into());
-            }
-        }
+    let Some(response) = fetcher.fetch(link.url()).await? else {
+        return Ok(link);
     };
 
-    if !response.status().is_success() {
+    if !response.is_success() {
         return Ok(link);
     }
 
     link.last_fetched = Some(Utc::now());
     let http_headers = response
-        .headers()
-        .into_iter()
+        .headers
+        .iter()
         .filter_map(|(key, value)| {
-            let key = key.as_str().to_lowercase();
+            let key = key.to_lowercase();
             if matches!(
                 key.as_str(),
                 "set-cookie" |
@@ -103,7 +195,7 @@ pub(crate) async fn fetch_link(mut link: Link, client: &Client) -> eyre::Result<
                 return None;
             }
 
-            Some((key, value.to_str().ok()?.to_string()))
+            Some((key, value.to_string()))
         })
         .fold(HashMap::new(), |mut acc, (key, value)| {
             acc.entry(key).or_insert_with(Vec::new).push(value);
@@ -117,11 +209,36 @@ pub(crate) async fn fetch_link(mut link: Link, client: &Client) -> eyre::Result<
         .find_map(|xs| xs.parse().ok());
 
     link.http_headers = Some(http_headers);
+    link.src_truncated = None;
 
-    if link.is_html() || link.is_pdf() || link.is_plaintext() {
-        link.src = response.bytes().await.ok().map(|xs| xs.to_vec());
-    } else {
+    let Some(kind) = BodyKind::of(&link) else {
         eprintln!("skipping link: {} {:?}", link.url(), link.http_headers().and_then(|hdrs| hdrs.get("content-type")).and_then(|xs| xs.last()).map(|xs| xs.as_str()));
+        return Ok(link);
+    };
+
+    let limit = kind.limit(&limits);
+    let keep_prefix = kind.keeps_prefix();
+    if let Some(length) = content_length.filter(|length| *length > limit && !keep_prefix) {
+        link.src_truncated = Some(format!(
+            "{kind} body of {length} bytes is over the {limit} byte limit; not stored"
+        ));
+        return Ok(link);
+    }
+
+    match read_body(response.body, limit).await {
+        Ok((bytes, false)) => link.src = Some(bytes),
+        Ok((bytes, true)) if keep_prefix => {
+            link.src = Some(bytes);
+            link.src_truncated = Some(format!(
+                "{kind} body is over the {limit} byte limit; kept the first {limit} bytes"
+            ));
+        }
+        Ok((_, true)) => {
+            link.src_truncated = Some(format!(
+                "{kind} body is over the {limit} byte limit; not stored"
+            ));
+        }
+        Err(_) => {}
     }
 
     Ok(link)
@@ -138,10 +255,163 @@ impl<T: LinkReader + Send + Sync> LinkReader for HttpClientWrap<T> {
     }
 }
 
+#[async_trait::async_trait]
+impl<T: LinkQuery + Send + Sync> LinkQuery for HttpClientWrap<T> {
+    async fn count(&self, params: &ListParams) -> eyre::Result<i64> {
+        self.inner.count(params).await
+    }
+
+    async fn list(&self, params: &ListParams) -> eyre::Result<ListPage> {
+        self.inner.list(params).await
+    }
+
+    async fn all_tags(&self, query: Option<&Query>) -> eyre::Result<Vec<String>> {
+        self.inner.all_tags(query).await
+    }
+
+    async fn apply_tag_operation(&self, op: &TagOperation) -> eyre::Result<u64> {
+        self.inner.apply_tag_operation(op).await
+    }
+
+    async fn tag_aliases(&self) -> eyre::Result<TagAliases> {
+        self.inner.tag_aliases().await
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str) -> eyre::Result<()> {
+        self.inner.set_tag_alias(alias, tag).await
+    }
+
+    async fn remove_tag_alias(&self, alias: &str) -> eyre::Result<bool> {
+        self.inner.remove_tag_alias(alias).await
+    }
+}
+
 #[async_trait::async_trait]
 impl<T: LinkWriter + Send + Sync> LinkWriter for HttpClientWrap<T> {
     async fn write(&self, link: Link) -> eyre::Result<bool> {
-        let link = fetch_link(link, &self.client).await?;
+        let link = fetch_link(link, self.fetcher.as_ref(), self.limits).await?;
         self.inner.write(link).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExternalWrap, FetchResponse, SqliteStore};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Serves `chunks` 1KiB chunks of body, counting how many were read.
+    struct StubFetcher {
+        content_type: &'static str,
+        content_length: Option<usize>,
+        chunks: usize,
+        read: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Fetcher for StubFetcher {
+        async fn fetch(&self, _url: &str) -> eyre::Result<Option<FetchResponse>> {
+            let mut headers = vec![("content-type".to_string(), self.content_type.to_string())];
+            if let Some(length) = self.content_length {
+                headers.push(("content-length".to_string(), length.to_string()));
+            }
+
+            let read = self.read.clone();
+            let body = futures::stream::iter(0..self.chunks).map(move |_| {
+                read.fetch_add(1, Ordering::SeqCst);
+                Ok(bytes::Bytes::from(vec![b'a'; 1024]))
+            });
+
+            Ok(Some(FetchResponse {
+                status: 200,
+                headers,
+                body: Box::pin(body),
+            }))
+        }
+    }
+
+    async fn fetch(
+        content_type: &'static str,
+        content_length: Option<usize>,
+        chunks: usize,
+    ) -> eyre::Result<(Link, usize)> {
+        let read = Arc::new(AtomicUsize::new(0));
+        let fetcher = StubFetcher {
+            content_type,
+            content_length,
+            chunks,
+            read: read.clone(),
+        };
+        let limits = BodyLimits {
+            html: 4096,
+            pdf: 2048,
+            text: 4096,
+        };
+
+        let cache = tempfile::tempdir()?;
+        let store = HttpClientWrap::with_fetcher(
+            fetcher,
+            ExternalWrap::new(
+                cache.path().to_path_buf(),
+                SqliteStore::with_connection_string("sqlite::memory:").await?,
+            ),
+        )
+        .with_limits(limits);
+        store
+            .write(Link::new("https://example.com/", "example"))
+            .await?;
+
+        let link = store
+            .get("https://example.com/")
+            .await?
+            .expect("link was written");
+        Ok((link, read.load(Ordering::SeqCst)))
+    }
+
+    #[tokio::test]
+    async fn keeps_bodies_under_the_limit() -> eyre::Result<()> {
+        let (link, _) = fetch("text/html", None, 4).await?;
+        assert_eq!(link.src().map(<[u8]>::len), Some(4096));
+        assert_eq!(link.src_truncated(), None);
+
+        let (link, _) = fetch("application/pdf", Some(2048), 2).await?;
+        assert_eq!(link.src().map(<[u8]>::len), Some(2048));
+        assert_eq!(link.src_truncated(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn truncates_documents_past_the_limit() -> eyre::Result<()> {
+        let (link, read) = fetch("text/plain", Some(1 << 30), 1 << 20).await?;
+        assert_eq!(link.src().map(<[u8]>::len), Some(4096));
+        assert_eq!(
+            link.src_truncated(),
+            Some("text body is over the 4096 byte limit; kept the first 4096 bytes")
+        );
+        assert_eq!(read, 5);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn drops_pdfs_past_the_limit() -> eyre::Result<()> {
+        let (link, read) = fetch("application/pdf", Some(1 << 30), 1 << 20).await?;
+        assert_eq!(link.src(), None);
+        assert_eq!(
+            link.src_truncated(),
+            Some("pdf body of 1073741824 bytes is over the 2048 byte limit; not stored")
+        );
+        assert_eq!(read, 0);
+
+        let (link, read) = fetch("application/pdf", None, 1 << 20).await?;
+        assert_eq!(link.src(), None);
+        assert_eq!(
+            link.src_truncated(),
+            Some("pdf body is over the 2048 byte limit; not stored")
+        );
+        assert_eq!(read, 3);
+        Ok(())
+    }
+}
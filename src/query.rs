@@ -0,0 +1,549 @@
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use crate::{Link, TagPattern, Via};
+
+/// A field that may be tested for presence with `has:`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HasField {
+    Title,
+    Notes,
+    Summary,
+    Image,
+    Via,
+    Tags,
+}
+
+/// A timestamp on a link that may be filtered with a [`DateFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Found,
+    Read,
+    Published,
+    Fetched,
+}
+
+/// Matches a timestamp by presence (`yes`/`no`) or against a half-open range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateFilter {
+    Present(bool),
+    Range {
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predicate {
+    /// Free text, matched against the url and title.
+    Text(String),
+    /// `url:` takes a glob pattern, like `show`'s positional argument.
+    Url(String),
+    Title(String),
+    Tag(TagPattern),
+    /// `host:` matches the host and its subdomains.
+    Host(String),
+    Via(String),
+    Has(HasField),
+    Date(DateField, DateFilter),
+    Hidden(bool),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub negated: bool,
+    pub predicate: Predicate,
+}
+
+/// A filter over links, parsed from a string like
+/// `tag:rust -tag:draft read:>2024-01 host:github.com via:@alice has:notes fetched:no`.
+///
+/// Terms are separated by whitespace and must all match; a leading `-` negates a term. Values
+/// may be quoted to include whitespace, and a quoted term without a field is always free text.
+///
+/// - `tag:` takes a [`TagPattern`], which also matches child tags.
+/// - `found:`, `read:`, `published:` and `fetched:` take `yes`, `no`, a date (`2024`, `2024-01`
+///   or `2024-01-31`, matching that whole period), a comparison (`>2024-01`, `<=2024`), or a
+///   range (`2023..2024-06`).
+/// - `has:` takes one of `title`, `notes`, `summary`, `image`, `via` or `tags`.
+/// - `hidden:` takes `yes` or `no`.
+/// - `url:` takes a glob; `title:`, `via:` and free text match substrings, ignoring case.
+///
+/// Queries are compiled to SQL by the SQLite store; [`Query::matches`] evaluates them in memory
+/// for everything else.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+impl Query {
+    /// A query for free text, as if all of `text` had been quoted. Input that doesn't parse as a
+    /// query, like a bare url, may still be searched for this way.
+    pub fn text(text: &str) -> Self {
+        let text = text.trim();
+        if text.is_empty() {
+            return Self::default();
+        }
+
+        Self {
+            terms: vec![Term {
+                negated: false,
+                predicate: Predicate::Text(text.to_string()),
+            }],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, link: &Link) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.predicate.matches(link) != term.negated)
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// The lowercased host of a link's url.
+pub(crate) fn link_host(link: &Link) -> Option<String> {
+    url_host(link.url())
+}
+
+/// The lowercased host of a url.
+pub(crate) fn url_host(url: &str) -> Option<String> {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+}
+
+fn via_text(via: &Via) -> &str {
+    match via {
+        Via::Friend(xs) | Via::Link(xs) | Via::Freeform(xs) => xs.as_str(),
+    }
+}
+
+impl Predicate {
+    pub fn matches(&self, link: &Link) -> bool {
+        match self {
+            Predicate::Text(text) => {
+                contains_ignore_case(link.url(), text)
+                    || link
+                        .title()
+                        .map(|title| contains_ignore_case(title, text))
+                        .unwrap_or(false)
+            }
+            Predicate::Url(pattern) => wildmatch::WildMatch::new(pattern).matches(link.url()),
+            Predicate::Title(text) => link
+                .title()
+                .map(|title| contains_ignore_case(title, text))
+                .unwrap_or(false),
+            Predicate::Tag(pattern) => link.tags().iter().any(|tag| pattern.matches(tag)),
+            Predicate::Host(host) => link_host(link)
+                .map(|candidate| {
+                    candidate == *host
+                        || candidate
+                            .strip_suffix(host.as_str())
+                            .map(|prefix| prefix.ends_with('.'))
+                            .unwrap_or(false)
+                })
+                .unwrap_or(false),
+            Predicate::Via(text) => link
+                .via()
+                .map(|via| contains_ignore_case(via_text(via), text))
+                .unwrap_or(false),
+            Predicate::Has(field) => match field {
+                HasField::Title => link.title().map(|xs| !xs.is_empty()).unwrap_or(false),
+                HasField::Notes => link.notes().map(|xs| !xs.is_empty()).unwrap_or(false),
+                HasField::Summary => link.summary().map(|xs| !xs.is_empty()).unwrap_or(false),
+                HasField::Image => link.image().map(|xs| !xs.is_empty()).unwrap_or(false),
+                HasField::Via => link.via().is_some(),
+                HasField::Tags => link.tags().iter().any(|tag| !tag.is_empty()),
+            },
+            Predicate::Date(field, filter) => {
+                let value = match field {
+                    DateField::Found => link.found_at(),
+                    DateField::Read => link.read_at(),
+                    DateField::Published => link.published_at(),
+                    DateField::Fetched => link.last_fetched(),
+                };
+
+                match (filter, value) {
+                    (DateFilter::Present(present), value) => value.is_some() == *present,
+                    (DateFilter::Range { .. }, None) => false,
+                    (DateFilter::Range { start, end }, Some(value)) => {
+                        start.map(|start| value >= start).unwrap_or(true)
+                            && end.map(|end| value < end).unwrap_or(true)
+                    }
+                }
+            }
+            Predicate::Hidden(hidden) => link.hidden() == *hidden,
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> eyre::Result<bool> {
+    match value.to_lowercase().as_str() {
+        "yes" | "true" | "y" => Ok(true),
+        "no" | "false" | "n" | "none" => Ok(false),
+        _ => Err(eyre::eyre!("expected yes or no, got {:?}", value)),
+    }
+}
+
+/// Parse a year, month or day into the half-open range it covers.
+fn parse_period(value: &str) -> eyre::Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let parts = value
+        .split('-')
+        .map(|part| part.parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| eyre::eyre!("invalid date {:?}", value))?;
+
+    let (start, end) = match parts.as_slice() {
+        [year] => (
+            NaiveDate::from_ymd_opt(*year as i32, 1, 1),
+            NaiveDate::from_ymd_opt(*year as i32 + 1, 1, 1),
+        ),
+        [year, month] => {
+            let start = NaiveDate::from_ymd_opt(*year as i32, *month, 1);
+            let end = start.and_then(|start| {
+                if start.month() == 12 {
+                    NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+                }
+            });
+            (start, end)
+        }
+        [year, month, day] => {
+            let start = NaiveDate::from_ymd_opt(*year as i32, *month, *day);
+            (start, start.and_then(|start| start.succ_opt()))
+        }
+        _ => (None, None),
+    };
+
+    let to_utc = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .map(|datetime| Utc.from_utc_datetime(&datetime))
+    };
+
+    start
+        .and_then(to_utc)
+        .zip(end.and_then(to_utc))
+        .ok_or_else(|| eyre::eyre!("invalid date {:?}", value))
+}
+
+impl FromStr for DateFilter {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(present) = parse_bool(s) {
+            return Ok(DateFilter::Present(present));
+        }
+
+        let (start, end) = if let Some(value) = s.strip_prefix(">=") {
+            (Some(parse_period(value)?.0), None)
+        } else if let Some(value) = s.strip_prefix("<=") {
+            (None, Some(parse_period(value)?.1))
+        } else if let Some(value) = s.strip_prefix('>') {
+            (Some(parse_period(value)?.1), None)
+        } else if let Some(value) = s.strip_prefix('<') {
+            (None, Some(parse_period(value)?.0))
+        } else if let Some((from, to)) = s.split_once("..") {
+            (Some(parse_period(from)?.0), Some(parse_period(to)?.1))
+        } else {
+            let (start, end) = parse_period(s)?;
+            (Some(start), Some(end))
+        };
+
+        Ok(DateFilter::Range { start, end })
+    }
+}
+
+impl FromStr for HasField {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "title" => HasField::Title,
+            "notes" => HasField::Notes,
+            "summary" => HasField::Summary,
+            "image" => HasField::Image,
+            "via" => HasField::Via,
+            "tags" => HasField::Tags,
+            _ => return Err(eyre::eyre!("unknown field {:?}", s)),
+        })
+    }
+}
+
+/// Split a query into terms on whitespace, honoring double quotes. Returns each term along with
+/// whether it began with a quote.
+fn split_terms(s: &str) -> eyre::Result<Vec<(String, bool)>> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+
+    for ch in s.chars() {
+        match ch {
+            '"' => {
+                if current.is_empty() || current == "-" {
+                    quoted = true;
+                }
+                in_quotes = !in_quotes;
+            }
+            ch if ch.is_whitespace() && !in_quotes => {
+                if !current.is_empty() || quoted {
+                    terms.push((std::mem::take(&mut current), quoted));
+                }
+                quoted = false;
+            }
+            ch => current.push(ch),
+        }
+    }
+
+    if in_quotes {
+        return Err(eyre::eyre!("unterminated quote in {:?}", s));
+    }
+
+    if !current.is_empty() || quoted {
+        terms.push((current, quoted));
+    }
+
+    Ok(terms)
+}
+
+impl FromStr for Query {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut terms = Vec::new();
+        for (term, quoted) in split_terms(s)? {
+            let (negated, term) = match term.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() || quoted => (true, rest),
+                _ => (false, term.as_str()),
+            };
+
+            let field = if quoted { None } else { term.split_once(':') };
+            let predicate = match field {
+                None => Predicate::Text(term.to_string()),
+                Some((key, value)) => {
+                    if value.is_empty() {
+                        return Err(eyre::eyre!("missing value for {:?}", key));
+                    }
+
+                    match key.to_lowercase().as_str() {
+                        "url" => Predicate::Url(value.to_string()),
+                        "title" => Predicate::Title(value.to_string()),
+                        "tag" => Predicate::Tag(TagPattern::new(value)),
+                        "host" => Predicate::Host(value.trim_start_matches("www.").to_lowercase()),
+                        "via" => Predicate::Via(value.to_string()),
+                        "has" => Predicate::Has(value.parse()?),
+                        "found" => Predicate::Date(DateField::Found, value.parse()?),
+                        "read" => Predicate::Date(DateField::Read, value.parse()?),
+                        "published" => Predicate::Date(DateField::Published, value.parse()?),
+                        "fetched" => Predicate::Date(DateField::Fetched, value.parse()?),
+                        "hidden" => Predicate::Hidden(parse_bool(value)?),
+                        _ => return Err(eyre::eyre!(
+                            "unknown query field {:?} (quote the term to search for it as text)",
+                            key
+                        )),
+                    }
+                }
+            };
+
+            if matches!(predicate, Predicate::Text(ref text) if text.is_empty()) {
+                continue;
+            }
+
+            terms.push(Term { negated, predicate });
+        }
+
+        Ok(Query { terms })
+    }
+}
+
+/// What to order a listing of links by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Found,
+    Read,
+    Published,
+    Title,
+    Host,
+    Fetched,
+}
+
+impl SortKey {
+    fn name(&self) -> &'static str {
+        match self {
+            SortKey::Found => "found",
+            SortKey::Read => "read",
+            SortKey::Published => "published",
+            SortKey::Title => "title",
+            SortKey::Host => "host",
+            SortKey::Fetched => "fetched",
+        }
+    }
+}
+
+/// The order of a listing of links, written as `key` or `key:asc`/`key:desc`. Dates sort newest
+/// first and text sorts alphabetically unless a direction is given. Links missing the sort key
+/// always come last, and ties are broken by url (or insertion order, in SQL) so that pagination
+/// is stable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            key: SortKey::Found,
+            descending: true,
+        }
+    }
+}
+
+impl Sort {
+    /// Compare two links, mirroring the order the SQLite store produces. Ties are broken by url
+    /// in the sort's direction, where the database stores use insertion order.
+    pub fn compare(&self, lhs: &Link, rhs: &Link) -> Ordering {
+        fn nulls_last<T: Ord>(lhs: Option<T>, rhs: Option<T>, descending: bool) -> Ordering {
+            match (lhs, rhs) {
+                (Some(lhs), Some(rhs)) if descending => rhs.cmp(&lhs),
+                (Some(lhs), Some(rhs)) => lhs.cmp(&rhs),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+
+        let title = |link: &Link| link.title().map(str::to_lowercase);
+        let ordering = match self.key {
+            SortKey::Found => nulls_last(lhs.found_at(), rhs.found_at(), self.descending),
+            SortKey::Read => nulls_last(lhs.read_at(), rhs.read_at(), self.descending),
+            SortKey::Published => {
+                nulls_last(lhs.published_at(), rhs.published_at(), self.descending)
+            }
+            SortKey::Title => nulls_last(title(lhs), title(rhs), self.descending),
+            SortKey::Host => nulls_last(link_host(lhs), link_host(rhs), self.descending),
+            SortKey::Fetched => nulls_last(lhs.last_fetched(), rhs.last_fetched(), self.descending),
+        };
+
+        let ties = lhs.url().cmp(rhs.url());
+        ordering.then(if self.descending { ties.reverse() } else { ties })
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let direction = if self.descending { "desc" } else { "asc" };
+        write!(f, "{}:{}", self.key.name(), direction)
+    }
+}
+
+impl FromStr for Sort {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, direction) = s.split_once(':').unwrap_or((s, ""));
+        let key = match key.to_lowercase().as_str() {
+            "found" => SortKey::Found,
+            "read" => SortKey::Read,
+            "published" => SortKey::Published,
+            "title" => SortKey::Title,
+            "host" => SortKey::Host,
+            "fetched" => SortKey::Fetched,
+            _ => return Err(eyre::eyre!("unknown sort key {:?}", key)),
+        };
+
+        let descending = match direction.to_lowercase().as_str() {
+            "" => !matches!(key, SortKey::Title | SortKey::Host),
+            "asc" => false,
+            "desc" => true,
+            _ => return Err(eyre::eyre!("unknown sort direction {:?}", direction)),
+        };
+
+        Ok(Sort { key, descending })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str, tags: &[&str]) -> Link {
+        Link {
+            url: url.to_string(),
+            tags: tags.iter().map(|xs| xs.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_and_matches_terms() -> eyre::Result<()> {
+        let query: Query =
+            "tag:lang -tag:draft host:github.com read:>2024-01 has:notes fetched:no".parse()?;
+        assert_eq!(query.terms.len(), 6);
+        assert!(query.terms[1].negated);
+
+        let mut target = link("https://www.github.com/rust-lang/rust", &["lang/rust"]);
+        *target.read_at_mut() = Some(Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap());
+        *target.notes_mut() = Some("a note".to_string());
+        assert!(query.matches(&target));
+
+        *target.read_at_mut() = Some(Utc.with_ymd_and_hms(2024, 1, 31, 23, 0, 0).unwrap());
+        assert!(!query.matches(&target));
+
+        let query: Query = r#"-"rust lang" url:https://* hidden:no"#.parse()?;
+        assert_eq!(
+            query.terms[0],
+            Term {
+                negated: true,
+                predicate: Predicate::Text("rust lang".to_string())
+            }
+        );
+        assert!(query.matches(&link("https://example.com/", &[])));
+        assert!(!query.matches(&link("http://example.com/", &[])));
+
+        assert!("https://example.com".parse::<Query>().is_err());
+        assert!("read:2024-13".parse::<Query>().is_err());
+        assert!("has:".parse::<Query>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sorts_missing_values_last() -> eyre::Result<()> {
+        let mut links = [
+            link("https://c.com/", &[]),
+            link("https://b.com/", &[]),
+            link("https://a.com/", &[]),
+        ];
+        links[0].title = Some("beta".to_string());
+        links[1].title = Some("Alpha".to_string());
+
+        let sort: Sort = "title".parse()?;
+        links.sort_by(|lhs, rhs| sort.compare(lhs, rhs));
+        let urls: Vec<_> = links.iter().map(|xs| xs.url()).collect();
+        assert_eq!(
+            urls,
+            vec!["https://b.com/", "https://c.com/", "https://a.com/"]
+        );
+
+        let sort: Sort = "title:desc".parse()?;
+        links.sort_by(|lhs, rhs| sort.compare(lhs, rhs));
+        let urls: Vec<_> = links.iter().map(|xs| xs.url()).collect();
+        assert_eq!(
+            urls,
+            vec!["https://c.com/", "https://b.com/", "https://a.com/"]
+        );
+
+        assert_eq!("read".parse::<Sort>()?.to_string(), "read:desc");
+        assert!("read:sideways".parse::<Sort>().is_err());
+        Ok(())
+    }
+}
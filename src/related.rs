@@ -0,0 +1,249 @@
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::similarity::{cosine, link_text, term_counts, Corpus, TermVector};
+use crate::{text_key, BlobStore, Link, LinkReader, Via};
+
+const TEXT_WEIGHT: f64 = 0.6;
+const TAG_WEIGHT: f64 = 0.25;
+const VIA_WEIGHT: f64 = 0.1;
+const HOST_WEIGHT: f64 = 0.05;
+
+/// Scores below this are considered noise and never reported.
+const MIN_SCORE: f64 = 0.05;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RelatedLink {
+    pub url: String,
+    pub title: Option<String>,
+    pub score: f64,
+    pub reasons: Vec<String>,
+}
+
+struct Entry {
+    url: String,
+    title: Option<String>,
+    vector: TermVector,
+    tags: HashSet<String>,
+    via: Option<String>,
+    host: Option<String>,
+}
+
+/// An index of links, used to find links related to one another by extracted text (TF-IDF),
+/// shared tags, shared "via" attribution, and shared host. Everything is computed locally.
+///
+/// Hidden links are never indexed.
+#[derive(Default)]
+pub struct RelatedIndex {
+    entries: Vec<Entry>,
+}
+
+fn via_key(via: &Via) -> String {
+    match via {
+        Via::Friend(xs) => format!("friend {}", xs),
+        Via::Link(xs) => format!("link {}", xs),
+        Via::Freeform(xs) => format!("text {}", xs),
+    }
+}
+
+impl RelatedIndex {
+    pub fn from_links<'a>(links: impl IntoIterator<Item = &'a Link>) -> Self {
+        let links: Vec<_> = links.into_iter().filter(|link| !link.hidden()).collect();
+
+        let counts: Vec<_> = links
+            .iter()
+            .map(|link| term_counts(link_text(link).as_str()))
+            .collect();
+
+        let mut corpus = Corpus::default();
+        for document in counts.iter() {
+            corpus.add(document);
+        }
+
+        let entries = links
+            .into_iter()
+            .zip(counts)
+            .map(|(link, document)| Entry {
+                url: link.url().to_string(),
+                title: link.title().map(str::to_string),
+                vector: corpus.vectorize(&document),
+                tags: link
+                    .tags()
+                    .iter()
+                    .filter(|tag| !tag.is_empty())
+                    .cloned()
+                    .collect(),
+                via: link.via().map(via_key),
+                host: url::Url::parse(link.url()).ok().and_then(|u| {
+                    u.host_str()
+                        .map(|xs| xs.trim_start_matches("www.").to_string())
+                }),
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    /// Build an index over the given links, reading their extracted text from `blobs`. Source
+    /// data is never read.
+    pub async fn with_text_from<'a>(
+        links: impl IntoIterator<Item = &'a Link>,
+        blobs: &(dyn BlobStore + Send + Sync),
+    ) -> eyre::Result<Self> {
+        let mut hydrated = Vec::new();
+        for link in links {
+            let mut link = link.clone();
+            if link.extracted_text.is_none() {
+                link.extracted_text = blobs
+                    .read(&text_key(link.url()))
+                    .await?
+                    .map(|xs| String::from_utf8_lossy(xs.as_slice()).to_string());
+            }
+            hydrated.push(link);
+        }
+
+        Ok(Self::from_links(hydrated.iter()))
+    }
+
+    /// Build an index from every link in the store. The store should hydrate extracted text
+    /// (e.g., by wrapping it in an `ExternalWrap`), otherwise only titles and notes are compared.
+    pub async fn from_store<S: LinkReader + Send + Sync>(store: &S) -> eyre::Result<Self> {
+        let mut links = store.values().await?;
+        let mut v = Vec::new();
+        while let Some(link) = links.next().await {
+            v.push(link);
+        }
+
+        Ok(Self::from_links(v.iter()))
+    }
+
+    /// Return up to `limit` links related to the given url, most related first.
+    pub fn related(&self, url: &str, limit: usize) -> Vec<RelatedLink> {
+        let Some(target) = self.entries.iter().find(|entry| entry.url == url) else {
+            return Vec::new();
+        };
+
+        let mut related: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.url != target.url)
+            .filter_map(|entry| {
+                let mut reasons = Vec::new();
+
+                let text = cosine(&target.vector, &entry.vector);
+                let mut score = TEXT_WEIGHT * text;
+                if text > 0.1 {
+                    reasons.push("similar text".to_string());
+                }
+
+                let shared_tags: Vec<_> = target.tags.intersection(&entry.tags).collect();
+                if !shared_tags.is_empty() {
+                    let union = target.tags.union(&entry.tags).count();
+                    score += TAG_WEIGHT * shared_tags.len() as f64 / union as f64;
+
+                    let mut shared_tags: Vec<_> =
+                        shared_tags.into_iter().map(String::as_str).collect();
+                    shared_tags.sort_unstable();
+                    reasons.push(format!("tags: {}", shared_tags.join(", ")));
+                }
+
+                if target.via.is_some() && target.via == entry.via {
+                    score += VIA_WEIGHT;
+                    reasons.push("same via".to_string());
+                }
+
+                if target.host.is_some() && target.host == entry.host {
+                    score += HOST_WEIGHT;
+                    reasons.push("same host".to_string());
+                }
+
+                if score < MIN_SCORE {
+                    return None;
+                }
+
+                Some(RelatedLink {
+                    url: entry.url.clone(),
+                    title: entry.title.clone(),
+                    score,
+                    reasons,
+                })
+            })
+            .collect();
+
+        related.sort_by(|lhs, rhs| {
+            rhs.score
+                .total_cmp(&lhs.score)
+                .then_with(|| lhs.url.cmp(&rhs.url))
+        });
+        related.truncate(limit);
+        related
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str, text: &str, tags: &[&str], via: Option<Via>) -> Link {
+        Link {
+            url: url.to_string(),
+            extracted_text: Some(text.to_string()),
+            tags: tags.iter().map(|xs| xs.to_string()).collect(),
+            via,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ranks_links_by_text_tags_and_via() {
+        let friend = || Some(Via::Friend("@alice".to_string()));
+        let mut hidden = link(
+            "https://hidden.dev/",
+            "borrow checker lifetimes",
+            &["rust"],
+            None,
+        );
+        *hidden.hidden_mut() = true;
+
+        let links = [
+            link(
+                "https://a.com/",
+                "borrow checker lifetimes traits",
+                &["rust"],
+                None,
+            ),
+            link(
+                "https://b.com/",
+                "borrow checker lifetimes",
+                &["rust"],
+                friend(),
+            ),
+            link(
+                "https://c.com/",
+                "sourdough starter hydration",
+                &[],
+                friend(),
+            ),
+            link(
+                "https://d.com/",
+                "kubernetes yaml helm charts",
+                &["ops"],
+                None,
+            ),
+            hidden,
+        ];
+
+        let index = RelatedIndex::from_links(links.iter());
+        let related = index.related("https://a.com/", 10);
+        let urls: Vec<_> = related.iter().map(|xs| xs.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://b.com/"]);
+
+        let related = index.related("https://b.com/", 10);
+        let urls: Vec<_> = related.iter().map(|xs| xs.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://a.com/", "https://c.com/"]);
+        assert!(related[1].reasons.contains(&"same via".to_string()));
+
+        assert!(index.related("https://hidden.dev/", 10).is_empty());
+    }
+}
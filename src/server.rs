@@ -1,16 +1,25 @@
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::get,
-    Json, Router,
+    routing::{get, post, put},
+    Extension, Json, Router,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::{LinkReader, LinkWriter, ListParams, SqliteStore};
+use crate::{
+    Cursor, ExternalWrap, LinkQuery, LinkReader, LinkWriter, ListParams, RelatedIndex, Sort,
+    TagFilter, TagOperation, TagSuggester,
+};
 
 // MARK: JSON response types
 
@@ -27,6 +36,7 @@ struct LinkJson {
     from_filename: Option<String>,
     image: Option<String>,
     hidden: bool,
+    summary: Option<String>,
     meta: Option<std::collections::HashMap<String, Vec<String>>>,
 }
 
@@ -44,6 +54,7 @@ impl From<crate::Link> for LinkJson {
             from_filename: link.from_filename().map(|s| s.to_owned()),
             image: link.image().map(|s| s.to_owned()),
             hidden: link.hidden(),
+            summary: link.summary().map(|s| s.to_owned()),
             meta: link.meta().cloned(),
         }
     }
@@ -55,6 +66,18 @@ struct LinkListResponse {
     total: i64,
     page: i64,
     per_page: i64,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TagOperationResponse {
+    updated: u64,
+}
+
+#[derive(Serialize)]
+struct TagAliasJson {
+    alias: String,
+    tag: String,
 }
 
 // MARK: Query/body types
@@ -64,10 +87,27 @@ struct LinkListQuery {
     q: Option<String>,
     tag: Option<String>,
     hidden: Option<bool>,
+    sort: Option<String>,
+    cursor: Option<String>,
     page: Option<i64>,
     per_page: Option<i64>,
 }
 
+#[derive(Deserialize)]
+struct TagListQuery {
+    q: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TagDeleteQuery {
+    drop: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct LimitQuery {
+    limit: Option<usize>,
+}
+
 #[derive(Deserialize)]
 struct LinkPatch {
     title: Option<String>,
@@ -77,20 +117,172 @@ struct LinkPatch {
     hidden: Option<bool>,
 }
 
+#[derive(Deserialize)]
+struct TagRename {
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+struct TagMerge {
+    from: Vec<String>,
+    into: String,
+}
+
+#[derive(Deserialize)]
+struct TagAliasBody {
+    tag: String,
+}
+
+// MARK: Indexes
+
+/// How long a built index is used before the next request rebuilds it. The server only knows
+/// about the changes it makes itself, so links written by another process, like an import run
+/// while the server is up, show up in related links and suggestions within this long.
+const INDEX_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Indexes over every link, built on first use rather than per request. The server drops them
+/// whenever it changes links, and the next request that needs one rebuilds it.
+///
+/// Each change bumps a generation, and an index is only kept if no change happened while it was
+/// being built, so a build that read links from before a change can't outlive it.
+#[derive(Default)]
+struct Indexes {
+    generation: AtomicU64,
+    related: Mutex<Option<Cached<RelatedIndex>>>,
+    suggester: Mutex<Option<Cached<TagSuggester>>>,
+}
+
+struct Cached<T> {
+    index: Arc<T>,
+    generation: u64,
+    built_at: Instant,
+}
+
+impl Indexes {
+    async fn related<S: LinkReader + Send + Sync>(
+        &self,
+        store: Arc<S>,
+    ) -> eyre::Result<Arc<RelatedIndex>> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        if let Some(index) = self.cached(&self.related, generation) {
+            return Ok(index);
+        }
+
+        // Related links compare extracted text, which lives in the external store.
+        let store = ExternalWrap::wrap(store).without_sources();
+        let index = Arc::new(RelatedIndex::from_store(&store).await?);
+        self.cache(&self.related, generation, index.clone());
+        Ok(index)
+    }
+
+    async fn suggester<S: LinkReader + Send + Sync>(
+        &self,
+        store: Arc<S>,
+    ) -> eyre::Result<Arc<TagSuggester>> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        if let Some(suggester) = self.cached(&self.suggester, generation) {
+            return Ok(suggester);
+        }
+
+        let store = ExternalWrap::wrap(store).without_sources();
+        let suggester = Arc::new(TagSuggester::from_store(&store).await?);
+        self.cache(&self.suggester, generation, suggester.clone());
+        Ok(suggester)
+    }
+
+    /// The cached index, if it was built in this generation and is younger than [`INDEX_TTL`].
+    fn cached<T>(&self, slot: &Mutex<Option<Cached<T>>>, generation: u64) -> Option<Arc<T>> {
+        slot.lock()
+            .unwrap()
+            .as_ref()
+            .filter(|cached| {
+                cached.generation == generation && cached.built_at.elapsed() < INDEX_TTL
+            })
+            .map(|cached| cached.index.clone())
+    }
+
+    /// Keep an index built from the links as of `generation`, unless they have changed since.
+    fn cache<T>(&self, slot: &Mutex<Option<Cached<T>>>, generation: u64, index: Arc<T>) {
+        let mut slot = slot.lock().unwrap();
+        // Checked while holding the lock, so an invalidation either comes first and is seen here,
+        // or waits to clear what is stored.
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *slot = Some(Cached {
+                index,
+                generation,
+                built_at: Instant::now(),
+            });
+        }
+    }
+
+    fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.related.lock().unwrap().take();
+        self.suggester.lock().unwrap().take();
+    }
+}
+
 // MARK: Handlers
 
-async fn list_links(
-    State(store): State<Arc<SqliteStore>>,
+/// Parses a search box's worth of input. Input that isn't a valid query, like a bare url or text
+/// with a colon in it, is searched for as text instead.
+fn parse_query(q: &str) -> crate::Query {
+    q.parse().unwrap_or_else(|_| crate::Query::text(q))
+}
+
+async fn list_links<S: LinkQuery + Send + Sync>(
+    State(store): State<Arc<S>>,
     Query(params): Query<LinkListQuery>,
 ) -> impl IntoResponse {
     let page = params.page.unwrap_or(1).max(1);
     let per_page = params.per_page.unwrap_or(50).clamp(1, 200);
     let offset = (page - 1) * per_page;
 
+    let tag = match params
+        .tag
+        .as_deref()
+        .map(str::parse::<TagFilter>)
+        .transpose()
+    {
+        Ok(tag) => tag.filter(|tag| !tag.is_empty()),
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let query = params
+        .q
+        .as_deref()
+        .map(parse_query)
+        .filter(|query| !query.is_empty());
+
+    let sort = match params.sort.as_deref().map(str::parse::<Sort>).transpose() {
+        Ok(sort) => sort.unwrap_or_default(),
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    let cursor = match params
+        .cursor
+        .as_deref()
+        .map(str::parse::<Cursor>)
+        .transpose()
+    {
+        Ok(Some(cursor)) if cursor.sort() != sort => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "cursor does not match the requested sort".to_string(),
+            )
+                .into_response()
+        }
+        Ok(cursor) => cursor,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
     let list_params = ListParams {
-        query: params.q,
-        tag: params.tag,
+        query,
+        tag,
         hidden: params.hidden,
+        sort,
+        cursor,
         offset,
         limit: per_page,
     };
@@ -100,22 +292,23 @@ async fn list_links(
         Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     };
 
-    let links = match store.list(&list_params).await {
+    let list = match store.list(&list_params).await {
         Ok(l) => l,
         Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     };
 
     Json(LinkListResponse {
-        links: links.into_iter().map(LinkJson::from).collect(),
+        links: list.links.into_iter().map(LinkJson::from).collect(),
         total,
         page,
         per_page,
+        next_cursor: list.next.map(|cursor| cursor.to_string()),
     })
     .into_response()
 }
 
-async fn get_link(
-    State(store): State<Arc<SqliteStore>>,
+async fn get_link<S: LinkReader + Send + Sync>(
+    State(store): State<Arc<S>>,
     Path(url): Path<String>,
 ) -> impl IntoResponse {
     eprintln!("uhhh");
@@ -130,8 +323,9 @@ async fn get_link(
     }
 }
 
-async fn patch_link(
-    State(store): State<Arc<SqliteStore>>,
+async fn patch_link<S: LinkReader + LinkWriter + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Extension(indexes): Extension<Arc<Indexes>>,
     Path(url): Path<String>,
     Json(patch): Json<LinkPatch>,
 ) -> impl IntoResponse {
@@ -139,6 +333,8 @@ async fn patch_link(
         .map(|s| s.into_owned())
         .unwrap_or(url);
 
+    // Patches never touch source data, so it is left where it is.
+    let store = ExternalWrap::wrap(store).without_sources();
     let mut link = match store.get(&decoded).await {
         Ok(Some(link)) => link,
         Ok(None) => return StatusCode::NOT_FOUND.into_response(),
@@ -165,7 +361,9 @@ async fn patch_link(
         *link.hidden_mut() = hidden;
     }
 
-    match store.write(link).await {
+    let written = store.write(link).await;
+    indexes.invalidate();
+    match written {
         Ok(_) => match store.get(&decoded).await {
             Ok(Some(link)) => Json(LinkJson::from(link)).into_response(),
             Ok(None) => StatusCode::NOT_FOUND.into_response(),
@@ -175,21 +373,203 @@ async fn patch_link(
     }
 }
 
-async fn list_tags(State(store): State<Arc<SqliteStore>>) -> impl IntoResponse {
-    match store.all_tags().await {
+async fn list_tags<S: LinkQuery + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Query(params): Query<TagListQuery>,
+) -> impl IntoResponse {
+    let query = params.q.as_deref().map(parse_query);
+
+    match store.all_tags(query.as_ref()).await {
         Ok(tags) => Json(tags).into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
 
+async fn apply_tag_operation(
+    store: &impl LinkQuery,
+    indexes: &Indexes,
+    op: TagOperation,
+) -> axum::response::Response {
+    let applied = store.apply_tag_operation(&op).await;
+    indexes.invalidate();
+    match applied {
+        Ok(updated) => Json(TagOperationResponse { updated }).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn rename_tag<S: LinkQuery + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Extension(indexes): Extension<Arc<Indexes>>,
+    Json(body): Json<TagRename>,
+) -> impl IntoResponse {
+    apply_tag_operation(
+        &store,
+        &indexes,
+        TagOperation::Rename {
+            from: body.from,
+            to: body.to,
+        },
+    )
+    .await
+}
+
+async fn merge_tags<S: LinkQuery + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Extension(indexes): Extension<Arc<Indexes>>,
+    Json(body): Json<TagMerge>,
+) -> impl IntoResponse {
+    apply_tag_operation(
+        &store,
+        &indexes,
+        TagOperation::Merge {
+            from: body.from,
+            into: body.into,
+        },
+    )
+    .await
+}
+
+async fn delete_tag<S: LinkQuery + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Extension(indexes): Extension<Arc<Indexes>>,
+    Path(tag): Path<String>,
+    Query(params): Query<TagDeleteQuery>,
+) -> impl IntoResponse {
+    let tag = urlencoding::decode(&tag)
+        .map(|s| s.into_owned())
+        .unwrap_or(tag);
+
+    let drop = params.drop.unwrap_or(false);
+    apply_tag_operation(&store, &indexes, TagOperation::Delete { tag, drop }).await
+}
+
+async fn list_tag_aliases<S: LinkQuery + Send + Sync>(
+    State(store): State<Arc<S>>,
+) -> impl IntoResponse {
+    match store.tag_aliases().await {
+        Ok(aliases) => Json(
+            aliases
+                .iter()
+                .map(|(alias, tag)| TagAliasJson {
+                    alias: alias.to_string(),
+                    tag: tag.to_string(),
+                })
+                .collect::<Vec<_>>(),
+        )
+        .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn put_tag_alias<S: LinkQuery + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Path(alias): Path<String>,
+    Json(body): Json<TagAliasBody>,
+) -> impl IntoResponse {
+    let alias = urlencoding::decode(&alias)
+        .map(|s| s.into_owned())
+        .unwrap_or(alias);
+
+    match store.set_tag_alias(&alias, &body.tag).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn delete_tag_alias<S: LinkQuery + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Path(alias): Path<String>,
+) -> impl IntoResponse {
+    let alias = urlencoding::decode(&alias)
+        .map(|s| s.into_owned())
+        .unwrap_or(alias);
+
+    match store.remove_tag_alias(&alias).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn suggested_tags<S: LinkReader + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Extension(indexes): Extension<Arc<Indexes>>,
+    Path(url): Path<String>,
+    Query(params): Query<LimitQuery>,
+) -> impl IntoResponse {
+    let decoded = urlencoding::decode(&url)
+        .map(|s| s.into_owned())
+        .unwrap_or(url);
+
+    // Suggestions compare extracted text, which lives in the external store.
+    let link = match ExternalWrap::wrap(store.clone())
+        .without_sources()
+        .get(&decoded)
+        .await
+    {
+        Ok(Some(link)) => link,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let suggester = match indexes.suggester(store).await {
+        Ok(suggester) => suggester,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let limit = params.limit.unwrap_or(10).clamp(1, 50);
+    Json(suggester.suggest(&link, limit)).into_response()
+}
+
+async fn related_links<S: LinkReader + Send + Sync>(
+    State(store): State<Arc<S>>,
+    Extension(indexes): Extension<Arc<Indexes>>,
+    Path(url): Path<String>,
+    Query(params): Query<LimitQuery>,
+) -> impl IntoResponse {
+    let decoded = urlencoding::decode(&url)
+        .map(|s| s.into_owned())
+        .unwrap_or(url);
+
+    match store.get(&decoded).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+
+    let index = match indexes.related(store).await {
+        Ok(index) => index,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let limit = params.limit.unwrap_or(10).clamp(1, 50);
+    Json(index.related(&decoded, limit)).into_response()
+}
+
 // MARK: Router
 
-pub fn router(store: Arc<SqliteStore>) -> Router {
+/// Builds the API (and UI, if built) around any store that can be read, written and queried.
+pub fn router<S>(store: Arc<S>) -> Router
+where
+    S: LinkReader + LinkWriter + LinkQuery + Send + Sync + 'static,
+{
     let api = Router::new()
         .route("/api/links", get(list_links))
         .route("/api/links/{url}", get(get_link).patch(patch_link))
+        .route("/api/links/{url}/suggested-tags", get(suggested_tags))
+        .route("/api/links/{url}/related", get(related_links))
         .route("/api/tags", get(list_tags))
-        .with_state(store);
+        .route("/api/tags/rename", post(rename_tag))
+        .route("/api/tags/merge", post(merge_tags))
+        .route("/api/tags/{tag}", axum::routing::delete(delete_tag))
+        .route("/api/tag-aliases", get(list_tag_aliases))
+        .route(
+            "/api/tag-aliases/{alias}",
+            put(put_tag_alias).delete(delete_tag_alias),
+        )
+        .with_state(store)
+        .layer(Extension(Arc::new(Indexes::default())));
 
     // Serve the Svelte UI from ui/dist if it exists.
     let ui_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("ui/dist");
@@ -203,10 +583,288 @@ pub fn router(store: Arc<SqliteStore>) -> Router {
 }
 
 /// Starts the server on the given port.
-pub async fn serve(store: Arc<SqliteStore>, port: u16) -> eyre::Result<()> {
+pub async fn serve<S>(store: Arc<S>, port: u16) -> eyre::Result<()>
+where
+    S: LinkReader + LinkWriter + LinkQuery + Send + Sync + 'static,
+{
     let app = router(store);
     let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
     eprintln!("listening on http://127.0.0.1:{}", port);
     axum::serve(listener, app).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InMemoryStore, Link};
+    use chrono::TimeZone;
+    use reqwest::{Client, Method};
+    use serde_json::{json, Value};
+
+    struct TestServer {
+        base: String,
+        client: Client,
+    }
+
+    impl TestServer {
+        async fn start(links: Vec<Link>) -> eyre::Result<Self> {
+            let store = InMemoryStore::new();
+            for link in links {
+                store.write(link).await?;
+            }
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+            let base = format!("http://{}", listener.local_addr()?);
+            let app = router(Arc::new(store));
+            tokio::spawn(async move { axum::serve(listener, app).await });
+
+            Ok(Self {
+                base,
+                client: Client::new(),
+            })
+        }
+
+        async fn request(
+            &self,
+            method: Method,
+            path: &str,
+            body: Option<Value>,
+        ) -> eyre::Result<(StatusCode, Value)> {
+            let mut request = self
+                .client
+                .request(method, format!("{}{}", self.base, path));
+            if let Some(body) = body {
+                request = request
+                    .header("content-type", "application/json")
+                    .body(body.to_string());
+            }
+
+            let response = request.send().await?;
+            let status = StatusCode::from_u16(response.status().as_u16())?;
+            let text = response.text().await?;
+            Ok((status, serde_json::from_str(&text).unwrap_or(Value::Null)))
+        }
+
+        async fn get(&self, path: &str) -> eyre::Result<(StatusCode, Value)> {
+            self.request(Method::GET, path, None).await
+        }
+    }
+
+    fn links() -> Vec<Link> {
+        [
+            ("https://www.rust-lang.org/", "Rust", &["lang/rust"][..], 1),
+            ("https://go.dev/", "Go", &["lang/go"][..], 2),
+            ("https://tokio.rs/", "tokio", &["lang/rust", "async"][..], 3),
+            ("https://example.com/draft", "a draft", &["draft"][..], 4),
+        ]
+        .into_iter()
+        .map(|(url, title, tags, day)| {
+            let mut link = Link::new(url, title);
+            *link.tags_mut() = tags.iter().map(|xs| xs.to_string()).collect();
+            *link.found_at_mut() = Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).latest();
+            link
+        })
+        .collect()
+    }
+
+    fn urls(page: &Value) -> Vec<&str> {
+        page["links"]
+            .as_array()
+            .map(|links| links.iter().filter_map(|xs| xs["url"].as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn lists_filters_and_pages_links() -> eyre::Result<()> {
+        let server = TestServer::start(links()).await?;
+
+        let (status, page) = server.get("/api/links").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 4);
+        assert_eq!(urls(&page)[0], "https://example.com/draft");
+
+        let (_, page) = server.get("/api/links?tag=lang,-lang/go").await?;
+        assert_eq!(
+            urls(&page),
+            ["https://tokio.rs/", "https://www.rust-lang.org/"]
+        );
+
+        let (_, page) = server.get("/api/links?q=host:go.dev").await?;
+        assert_eq!(urls(&page), ["https://go.dev/"]);
+
+        let q = urlencoding::encode("https://go.dev/");
+        let (status, page) = server.get(&format!("/api/links?q={q}")).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(urls(&page), ["https://go.dev/"]);
+
+        let (status, page) = server.get("/api/links?q=note:%20tokio").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 0);
+
+        let mut seen = Vec::new();
+        let mut path = "/api/links?sort=title&per_page=3".to_string();
+        loop {
+            let (status, page) = server.get(&path).await?;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(page["total"], 4);
+            seen.extend(urls(&page).into_iter().map(str::to_string));
+
+            let Some(cursor) = page["next_cursor"].as_str() else {
+                break;
+            };
+            path = format!("/api/links?sort=title&per_page=3&cursor={cursor}");
+        }
+        assert_eq!(
+            seen,
+            [
+                "https://example.com/draft",
+                "https://go.dev/",
+                "https://www.rust-lang.org/",
+                "https://tokio.rs/",
+            ]
+        );
+
+        let (_, page) = server.get("/api/links?sort=title&per_page=3").await?;
+        let cursor = page["next_cursor"].as_str().unwrap_or_default();
+        for path in [
+            format!("/api/links?sort=found&cursor={cursor}"),
+            "/api/links?cursor=zz".to_string(),
+            "/api/links?sort=sideways".to_string(),
+            "/api/links?tag=-".to_string(),
+        ] {
+            assert_eq!(
+                server.get(&path).await?.0,
+                StatusCode::BAD_REQUEST,
+                "{path}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gets_and_patches_links() -> eyre::Result<()> {
+        let server = TestServer::start(links()).await?;
+        let path = format!("/api/links/{}", urlencoding::encode("https://go.dev/"));
+
+        let (status, link) = server.get(&path).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(link["title"], "Go");
+        assert_eq!(link["read_at"], Value::Null);
+
+        let patch = json!({ "notes": "simple", "tags": ["lang/go", "google"] });
+        let (status, link) = server.request(Method::PATCH, &path, Some(patch)).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(link["notes"], "simple");
+        assert_eq!(link["tags"], json!(["lang/go", "google"]));
+        assert_ne!(link["read_at"], Value::Null);
+
+        let missing = format!("/api/links/{}", urlencoding::encode("https://nope.dev/"));
+        assert_eq!(server.get(&missing).await?.0, StatusCode::NOT_FOUND);
+        let (status, _) = server
+            .request(Method::PATCH, &missing, Some(json!({})))
+            .await?;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn manages_tags_and_aliases() -> eyre::Result<()> {
+        let server = TestServer::start(links()).await?;
+
+        let (_, tags) = server.get("/api/tags").await?;
+        assert_eq!(
+            tags,
+            json!(["async", "draft", "lang", "lang/go", "lang/rust"])
+        );
+        let (_, tags) = server.get("/api/tags?q=host:tokio.rs").await?;
+        assert_eq!(tags, json!(["async", "lang", "lang/rust"]));
+
+        let rename = json!({ "from": "lang", "to": "languages" });
+        let (status, body) = server
+            .request(Method::POST, "/api/tags/rename", Some(rename))
+            .await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["updated"], 3);
+
+        let merge = json!({ "from": ["async", "draft"], "into": "misc" });
+        let (_, body) = server
+            .request(Method::POST, "/api/tags/merge", Some(merge))
+            .await?;
+        assert_eq!(body["updated"], 2);
+
+        let (_, body) = server
+            .request(Method::DELETE, "/api/tags/languages%2Fgo", None)
+            .await?;
+        assert_eq!(body["updated"], 1);
+
+        let (_, tags) = server.get("/api/tags").await?;
+        assert_eq!(tags, json!(["languages", "languages/rust", "misc"]));
+
+        let (status, _) = server
+            .request(
+                Method::PUT,
+                "/api/tag-aliases/Golang",
+                Some(json!({ "tag": "languages/go" })),
+            )
+            .await?;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (_, aliases) = server.get("/api/tag-aliases").await?;
+        let aliases = aliases.as_array().cloned().unwrap_or_default();
+        assert!(aliases.contains(&json!({ "alias": "golang", "tag": "languages/go" })));
+        assert!(aliases.contains(&json!({ "alias": "lang", "tag": "languages" })));
+        assert!(!aliases.iter().any(|xs| xs["alias"] == "languages/go"));
+
+        let (_, body) = server
+            .request(Method::DELETE, "/api/tags/misc?drop=true", None)
+            .await?;
+        assert_eq!(body["updated"], 2);
+        let (_, aliases) = server.get("/api/tag-aliases").await?;
+        let aliases = aliases.as_array().cloned().unwrap_or_default();
+        assert!(aliases.contains(&json!({ "alias": "misc", "tag": "" })));
+
+        let alias = "/api/tag-aliases/golang";
+        let (status, _) = server.request(Method::DELETE, alias, None).await?;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = server.request(Method::DELETE, alias, None).await?;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[test]
+    fn indexes_built_before_a_change_are_not_kept() {
+        let indexes = Indexes::default();
+        let index = Arc::new(TagSuggester::from_links(&links()));
+
+        let generation = indexes.generation.load(Ordering::SeqCst);
+        indexes.cache(&indexes.suggester, generation, index.clone());
+        assert!(indexes.cached(&indexes.suggester, generation).is_some());
+
+        // A build that started before a change finishes after it.
+        let generation = indexes.generation.load(Ordering::SeqCst);
+        indexes.invalidate();
+        indexes.cache(&indexes.suggester, generation, index.clone());
+        assert!(indexes.suggester.lock().unwrap().is_none());
+
+        let generation = indexes.generation.load(Ordering::SeqCst);
+        indexes.cache(&indexes.suggester, generation, index);
+        assert!(indexes.cached(&indexes.suggester, generation).is_some());
+        if let Some(expired) = Instant::now().checked_sub(INDEX_TTL) {
+            indexes.suggester.lock().unwrap().as_mut().unwrap().built_at = expired;
+            assert!(indexes.cached(&indexes.suggester, generation).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn related_links_404_for_unknown_links() -> eyre::Result<()> {
+        let server = TestServer::start(links()).await?;
+        let path = format!(
+            "/api/links/{}/related",
+            urlencoding::encode("https://nope.dev/")
+        );
+
+        assert_eq!(server.get(&path).await?.0, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+}
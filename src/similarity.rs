@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use crate::Link;
+
+/// Words that carry little meaning on their own. These are dropped before weighting terms.
+const STOPWORDS: &[&str] = &[
+    "about", "above", "after", "again", "against", "all", "also", "and", "any", "are", "because",
+    "been", "before", "being", "below", "between", "both", "but", "can", "could", "did", "does",
+    "doing", "down", "during", "each", "few", "for", "from", "further", "had", "has", "have",
+    "having", "her", "here", "hers", "herself", "him", "himself", "his", "how", "http", "https",
+    "into", "its", "itself", "just", "like", "more", "most", "not", "now", "off", "once", "only",
+    "other", "our", "ours", "out", "over", "own", "same", "she", "should", "some", "such", "than",
+    "that", "the", "their", "theirs", "them", "then", "there", "these", "they", "this", "those",
+    "through", "too", "under", "until", "use", "used", "using", "very", "was", "way", "were",
+    "what", "when", "where", "which", "while", "who", "whom", "why", "will", "with", "would",
+    "www", "you", "your", "yours",
+];
+
+/// Split text into lowercased terms, dropping short words, numbers, and stopwords.
+pub(crate) fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|xs| xs.len() > 2 && xs.len() < 32)
+        .map(str::to_lowercase)
+        .filter(|xs| !xs.chars().all(char::is_numeric))
+        .filter(|xs| !STOPWORDS.contains(&xs.as_str()))
+}
+
+/// All of the text we know about a link, for comparison against other links.
+pub(crate) fn link_text(link: &Link) -> String {
+    itertools::join(
+        itertools::chain!(
+            link.title(),
+            link.notes(),
+            link.summary(),
+            link.extract_text()
+        ),
+        "\n",
+    )
+}
+
+pub(crate) type TermCounts = HashMap<String, usize>;
+pub(crate) type TermVector = HashMap<String, f64>;
+
+pub(crate) fn term_counts(text: &str) -> TermCounts {
+    let mut counts = HashMap::new();
+    for term in tokenize(text) {
+        *counts.entry(term).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Document frequencies for a set of documents, used to weight terms by how rare they are.
+#[derive(Default, Debug)]
+pub(crate) struct Corpus {
+    documents: usize,
+    document_frequency: HashMap<String, usize>,
+}
+
+impl Corpus {
+    pub(crate) fn add(&mut self, counts: &TermCounts) {
+        self.documents += 1;
+        for term in counts.keys() {
+            *self.document_frequency.entry(term.clone()).or_insert(0) += 1;
+        }
+    }
+
+    pub(crate) fn idf(&self, term: &str) -> f64 {
+        let df = self.document_frequency.get(term).copied().unwrap_or(0);
+        ((1 + self.documents) as f64 / (1 + df) as f64).ln() + 1.0
+    }
+
+    /// Produce a unit-length TF-IDF vector for the given term counts.
+    pub(crate) fn vectorize(&self, counts: &TermCounts) -> TermVector {
+        let mut vector: TermVector = counts
+            .iter()
+            .map(|(term, count)| (term.clone(), (1.0 + (*count as f64).ln()) * self.idf(term)))
+            .collect();
+
+        let norm = vector.values().map(|xs| xs * xs).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            for weight in vector.values_mut() {
+                *weight /= norm;
+            }
+        }
+
+        vector
+    }
+}
+
+/// Cosine similarity between two unit-length vectors.
+pub(crate) fn cosine(lhs: &TermVector, rhs: &TermVector) -> f64 {
+    let (small, large) = if lhs.len() < rhs.len() {
+        (lhs, rhs)
+    } else {
+        (rhs, lhs)
+    };
+
+    small
+        .iter()
+        .filter_map(|(term, weight)| large.get(term).map(|other| weight * other))
+        .sum()
+}
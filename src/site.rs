@@ -0,0 +1,702 @@
+use include_dir::{include_dir, Dir};
+use serde::Serialize;
+use slugify::slugify;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+use tera::{Context, Tera};
+
+use crate::{
+    export::MANIFEST,
+    feed::{escape, notes_html},
+    query::link_host,
+    tag_ancestors, tag_matches, DisplayTimezone, ExportWriter, Feed, FeedFormat, Link, LinkQuery,
+    ListParams, Query, Sort, SortKey, Via, LIST_PAGE_SIZE,
+};
+
+/// The built-in theme: Tera templates at the top level, and files under `static/` that are copied
+/// into the site as they are.
+static THEME_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/theme");
+
+/// The number of links in each feed.
+const FEED_LIMIT: usize = 50;
+
+/// The feeds written for the whole site, as `(format, filename, mime type)`.
+const FEEDS: [(FeedFormat, &str, &str); 3] = [
+    (FeedFormat::Atom, "atom.xml", "application/atom+xml"),
+    (FeedFormat::Rss, "rss.xml", "application/rss+xml"),
+    (FeedFormat::Json, "feed.json", "application/feed+json"),
+];
+
+/// Renders a static link blog: index pages paginated by read date, a page for each tag and each
+/// friend links came via, and a page for each link, along with feeds and a search index.
+///
+/// Pages are rendered with the templates in `theme/`: `list.html` for the index, tag and via
+/// pages, `link.html` for each link, and `terms.html` for the lists of tags and friends, all
+/// extending `base.html`. A templates directory may override any of them, add templates of its
+/// own for them to include, and add or replace files under `static/`. Every page is given the
+/// `site`, its `feeds`, and `root`, the relative path back to the top of the site, so that the
+/// site works wherever it is published.
+pub struct Site {
+    pub title: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub per_page: usize,
+    /// The timezone pages give read dates in.
+    pub timezone: DisplayTimezone,
+    tera: Tera,
+    statics: BTreeMap<String, Vec<u8>>,
+}
+
+#[derive(Serialize)]
+struct SiteContext<'a> {
+    title: &'a str,
+    url: &'a str,
+    description: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct FeedLink {
+    title: String,
+    path: String,
+    mime_type: &'static str,
+}
+
+#[derive(Serialize)]
+struct LinkPage<'a> {
+    url: &'a str,
+    title: &'a str,
+    host: String,
+    path: String,
+    notes: String,
+    summary: Option<&'a str>,
+    image: Option<&'a str>,
+    read_at: Option<String>,
+    read_on: Option<String>,
+    tags: Vec<TagPage>,
+    via: Option<ViaPage<'a>>,
+}
+
+#[derive(Serialize)]
+struct TagPage {
+    name: String,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct ViaPage<'a> {
+    name: &'a str,
+    url: Option<&'a str>,
+    path: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Term {
+    name: String,
+    path: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct Pagination {
+    current: usize,
+    pages: usize,
+    previous: Option<String>,
+    next: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    title: &'a str,
+    url: &'a str,
+    path: String,
+    tags: &'a [String],
+    via: Option<&'a str>,
+    notes: Option<&'a str>,
+    read_at: Option<String>,
+}
+
+impl Site {
+    pub fn new(
+        title: impl Into<String>,
+        url: impl Into<String>,
+        templates: Option<&Path>,
+    ) -> eyre::Result<Self> {
+        let mut sources = BTreeMap::new();
+        let mut statics = BTreeMap::new();
+        for file in embedded_files(&THEME_DIR) {
+            let name = file.path().to_string_lossy().replace('\\', "/");
+            if name.starts_with("static/") {
+                statics.insert(name, file.contents().to_vec());
+            } else {
+                sources.insert(name, String::from_utf8(file.contents().to_vec())?);
+            }
+        }
+
+        if let Some(templates) = templates {
+            for path in local_files(templates)? {
+                let name = path
+                    .strip_prefix(templates)?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                if name.starts_with("static/") {
+                    statics.insert(name, std::fs::read(&path)?);
+                } else {
+                    sources.insert(name, std::fs::read_to_string(&path)?);
+                }
+            }
+        }
+
+        let mut tera = Tera::default();
+        tera.set_escape_fn(escape);
+        tera.add_raw_templates(sources)?;
+
+        Ok(Self {
+            title: title.into(),
+            url: url.into(),
+            description: None,
+            per_page: 20,
+            timezone: DisplayTimezone::from_env(),
+            tera,
+            statics,
+        })
+    }
+
+    /// Fetch the links a site is built from: those matching `query` that are not hidden, most
+    /// recently read first.
+    pub async fn links<S>(store: &S, query: Query) -> eyre::Result<Vec<Link>>
+    where
+        S: LinkQuery + Send + Sync,
+    {
+        store
+            .list_all(ListParams {
+                query: Some(query),
+                tag: None,
+                hidden: Some(false),
+                sort: Sort {
+                    key: SortKey::Read,
+                    descending: true,
+                },
+                cursor: None,
+                offset: 0,
+                limit: LIST_PAGE_SIZE,
+            })
+            .await
+    }
+
+    /// Open `output` for [`Site::render`]. The export manifest is kept beside the directory, as
+    /// `.<name>.likelike-export.json`, so that it isn't published with the site.
+    pub fn writer(output: &Path) -> eyre::Result<ExportWriter> {
+        std::fs::create_dir_all(output)?;
+        let output = output.canonicalize()?;
+        let (Some(parent), Some(name)) = (output.parent(), output.file_name()) else {
+            eyre::bail!("can't build a site into {}", output.display());
+        };
+
+        let manifest = parent.join(format!(".{}{}", name.to_string_lossy(), MANIFEST));
+        ExportWriter::open_with_manifest(&output, manifest)
+    }
+
+    /// Render every page of the site for `links`, which are listed in the order given.
+    pub fn render(&self, links: &[Link], out: &mut ExportWriter) -> eyre::Result<()> {
+        for (name, contents) in &self.statics {
+            out.write(name, contents)?;
+        }
+
+        let mut tags: BTreeMap<&str, usize> = BTreeMap::new();
+        for link in links {
+            let mut tagged: Vec<&str> = link
+                .tags()
+                .iter()
+                .flat_map(|tag| std::iter::once(tag.as_str()).chain(tag_ancestors(tag)))
+                .collect();
+            tagged.sort();
+            tagged.dedup();
+            for tag in tagged {
+                *tags.entry(tag).or_default() += 1;
+            }
+        }
+        let tag_paths = tag_paths(tags.keys().copied());
+
+        let pages: Vec<LinkPage> = links
+            .iter()
+            .map(|link| LinkPage::new(link, self.timezone, &tag_paths))
+            .collect();
+        let feeds = self.feeds(out, "", None, links.iter())?;
+        self.render_list(out, "", None, &feeds, pages.iter())?;
+
+        for page in &pages {
+            let mut context = Context::new();
+            context.insert("link", page);
+            let filename = format!("{}index.html", page.path);
+            self.render_page(out, &filename, "link.html", &feeds, context)?;
+        }
+
+        for tag in tags.keys() {
+            let tagged = || {
+                links
+                    .iter()
+                    .zip(&pages)
+                    .filter(|(link, _)| link.tags().iter().any(|xs| tag_matches(tag, xs)))
+            };
+            let base = &tag_paths[tag];
+            let feeds = self.feeds(out, base, Some(tag), tagged().map(|(link, _)| link))?;
+            let heading = format!("#{}", tag);
+            let pages = tagged().map(|(_, page)| page);
+            self.render_list(out, base, Some(&heading), &feeds, pages)?;
+        }
+        let tag_path = |tag: &str| tag_paths[tag].clone();
+        self.render_terms(out, "tags", tag_path, &feeds, tags)?;
+
+        // Friends whose names slugify the same way, like "Alice" and "alice", share a page, listed
+        // under the name on their most recent link.
+        let mut friends: BTreeMap<String, (&str, usize)> = BTreeMap::new();
+        for link in links {
+            if let Some(Via::Friend(friend)) = link.via() {
+                friends.entry(friend_path(friend)).or_insert((friend, 0)).1 += 1;
+            }
+        }
+
+        for (path, (friend, _)) in &friends {
+            let pages = links
+                .iter()
+                .zip(&pages)
+                .filter(|(link, _)| {
+                    matches!(link.via(), Some(Via::Friend(xs)) if &friend_path(xs) == path)
+                })
+                .map(|(_, page)| page);
+            let heading = format!("via {}", friend);
+            self.render_list(out, path, Some(&heading), &feeds, pages)?;
+        }
+        let friends = friends.into_values().collect();
+        self.render_terms(out, "via", friend_path, &feeds, friends)?;
+
+        let search: Vec<SearchEntry> = links
+            .iter()
+            .zip(&pages)
+            .map(|(link, page)| SearchEntry {
+                title: page.title,
+                url: link.url(),
+                path: page.path.clone(),
+                tags: link.tags(),
+                via: page.via.as_ref().map(|via| via.name),
+                notes: link.notes(),
+                read_at: page.read_at.clone(),
+            })
+            .collect();
+        out.write("search.json", &serde_json::to_vec(&search)?)?;
+
+        Ok(())
+    }
+
+    /// Write the feeds for a listing, returning links to them for its pages' `<head>`.
+    fn feeds<'a>(
+        &self,
+        out: &mut ExportWriter,
+        base: &str,
+        tag: Option<&str>,
+        links: impl Iterator<Item = &'a Link>,
+    ) -> eyre::Result<Vec<FeedLink>> {
+        let url = format!("{}/", self.url.trim_end_matches('/'));
+        let title = match tag {
+            Some(tag) => format!("{}: #{}", self.title, tag),
+            None => self.title.clone(),
+        };
+        let links: Vec<Link> = links.take(FEED_LIMIT).cloned().collect();
+
+        let mut feed_links = Vec::new();
+        for (format, filename, mime_type) in FEEDS {
+            // Tag pages only get an Atom feed, to keep the number of files down.
+            if tag.is_some() && format != FeedFormat::Atom {
+                continue;
+            }
+
+            let path = format!("{}{}", base, filename);
+            let feed = Feed {
+                title: title.clone(),
+                home_page_url: format!("{}{}", url, base),
+                feed_url: Some(format!("{}{}", url, path)),
+                description: self.description.clone(),
+            };
+            out.write(&path, feed.render(format, &links)?.as_bytes())?;
+            feed_links.push(FeedLink {
+                title: title.clone(),
+                path,
+                mime_type,
+            });
+        }
+
+        Ok(feed_links)
+    }
+
+    /// Write a listing's pages: `{base}index.html`, then `{base}page/2/index.html` and so on.
+    fn render_list<'a>(
+        &self,
+        out: &mut ExportWriter,
+        base: &str,
+        heading: Option<&str>,
+        feeds: &[FeedLink],
+        pages: impl Iterator<Item = &'a LinkPage<'a>>,
+    ) -> eyre::Result<()> {
+        let pages: Vec<&LinkPage> = pages.collect();
+        let per_page = self.per_page.max(1);
+        let count = pages.len().max(1).div_ceil(per_page);
+        let path = |page: usize| match page {
+            1 => base.to_string(),
+            page => format!("{}page/{}/", base, page),
+        };
+
+        for current in 1..=count {
+            let links: Vec<&LinkPage> = pages
+                .iter()
+                .skip((current - 1) * per_page)
+                .take(per_page)
+                .copied()
+                .collect();
+
+            let mut context = Context::new();
+            context.insert("heading", &heading);
+            context.insert("links", &links);
+            context.insert(
+                "pagination",
+                &Pagination {
+                    current,
+                    pages: count,
+                    previous: (current > 1).then(|| path(current - 1)),
+                    next: (current < count).then(|| path(current + 1)),
+                },
+            );
+            self.render_page(
+                out,
+                &format!("{}index.html", path(current)),
+                "list.html",
+                feeds,
+                context,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the list of tags or friends at `{name}/index.html`.
+    fn render_terms(
+        &self,
+        out: &mut ExportWriter,
+        name: &str,
+        path: impl Fn(&str) -> String,
+        feeds: &[FeedLink],
+        terms: BTreeMap<&str, usize>,
+    ) -> eyre::Result<()> {
+        let terms: Vec<Term> = terms
+            .into_iter()
+            .map(|(term, count)| Term {
+                name: term.to_string(),
+                path: path(term),
+                count,
+            })
+            .collect();
+
+        let mut context = Context::new();
+        context.insert("heading", name);
+        context.insert("terms", &terms);
+        self.render_page(
+            out,
+            &format!("{}/index.html", name),
+            "terms.html",
+            feeds,
+            context,
+        )
+    }
+
+    fn render_page(
+        &self,
+        out: &mut ExportWriter,
+        filename: &str,
+        template: &str,
+        feeds: &[FeedLink],
+        mut context: Context,
+    ) -> eyre::Result<()> {
+        context.insert(
+            "site",
+            &SiteContext {
+                title: &self.title,
+                url: &self.url,
+                description: self.description.as_deref(),
+            },
+        );
+        context.insert("feeds", feeds);
+        context.insert("root", &"../".repeat(filename.matches('/').count()));
+
+        let page = self.tera.render(template, &context)?;
+        out.write(filename, page.as_bytes())
+    }
+}
+
+impl<'a> LinkPage<'a> {
+    fn new(link: &'a Link, timezone: DisplayTimezone, tag_paths: &HashMap<&str, String>) -> Self {
+        LinkPage {
+            url: link.url(),
+            title: link.title().unwrap_or_else(|| link.url()),
+            host: link_host(link).unwrap_or_default(),
+            path: format!("links/{}/", slugify!(link.url())),
+            notes: notes_html(link),
+            summary: link.summary(),
+            image: link.image(),
+            read_at: link.read_at().map(|xs| timezone.localize(xs).to_rfc3339()),
+            read_on: link.read_at().map(|xs| timezone.date(xs).to_string()),
+            tags: link
+                .tags()
+                .iter()
+                .map(|tag| TagPage {
+                    name: tag.to_string(),
+                    path: tag_paths[tag.as_str()].clone(),
+                })
+                .collect(),
+            via: link.via().map(|via| match via {
+                Via::Friend(name) => ViaPage {
+                    name,
+                    url: None,
+                    path: Some(friend_path(name)),
+                },
+                Via::Link(url) => ViaPage {
+                    name: url,
+                    url: Some(url),
+                    path: None,
+                },
+                Via::Freeform(name) => ViaPage {
+                    name,
+                    url: None,
+                    path: None,
+                },
+            }),
+        }
+    }
+}
+
+/// The path of each tag's page, and of each of its ancestors'. Hierarchical tags get nested pages:
+/// `lang/rust` is listed at `tags/lang/rust/`. Tags that slugify the same way, like `C++` and `c`,
+/// still get pages of their own, told apart by a numbered suffix in the order they're given;
+/// names that slugify to nothing are called `tag`.
+fn tag_paths<'a>(tags: impl IntoIterator<Item = &'a str>) -> HashMap<&'a str, String> {
+    let mut paths: HashMap<&str, String> = HashMap::new();
+    let mut taken = HashSet::new();
+    for tag in tags {
+        let mut lineage: Vec<&str> = tag_ancestors(tag).collect();
+        lineage.reverse();
+        lineage.push(tag);
+
+        for tag in lineage {
+            if paths.contains_key(tag) {
+                continue;
+            }
+
+            let (parent, name) = match tag.rsplit_once('/') {
+                Some((parent, name)) => (paths[parent].clone(), name),
+                None => ("tags/".to_string(), tag),
+            };
+            let slug = match slugify!(name) {
+                slug if slug.is_empty() => "tag".to_string(),
+                slug => slug,
+            };
+
+            let mut path = format!("{}{}/", parent, slug);
+            let mut suffix = 1;
+            while !taken.insert(path.clone()) {
+                suffix += 1;
+                path = format!("{}{}-{}/", parent, slug, suffix);
+            }
+            paths.insert(tag, path);
+        }
+    }
+
+    paths
+}
+
+fn friend_path(friend: &str) -> String {
+    format!("via/{}/", slugify!(friend))
+}
+
+fn embedded_files<'a>(dir: &'a Dir<'a>) -> Vec<&'a include_dir::File<'a>> {
+    let mut files: Vec<_> = dir.files().collect();
+    for dir in dir.dirs() {
+        files.extend(embedded_files(dir));
+    }
+    files
+}
+
+fn local_files(dir: &Path) -> eyre::Result<Vec<std::path::PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(local_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links() -> Vec<Link> {
+        let mut rust = Link::new("https://example.com/rust", "Rust <3");
+        rust.tags = vec!["lang/rust".to_string()];
+        rust.notes = Some("worth *reading*".to_string());
+        rust.via = Some(Via::Friend("Alice".to_string()));
+        rust.read_at = Some("2023-03-01T00:00:00Z".parse().unwrap());
+
+        let mut go = Link::new("https://example.com/go", "Go");
+        go.tags = vec!["lang/go".to_string()];
+        go.via = Some(Via::Link("https://example.org/".to_string()));
+        go.read_at = Some("2023-02-01T00:00:00Z".parse().unwrap());
+
+        let mut zig = Link::new("https://example.com/zig", "Zig");
+        zig.via = Some(Via::Friend("alice".to_string()));
+        zig.read_at = Some("2023-01-01T00:00:00Z".parse().unwrap());
+
+        vec![rust, go, zig]
+    }
+
+    fn read(dir: &Path, path: &str) -> String {
+        std::fs::read_to_string(dir.join(path)).unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn leaves_out_hidden_links() -> eyre::Result<()> {
+        let mut links = links();
+        links[1].hidden = true;
+        let store = crate::InMemoryStore::with_links(links);
+
+        let links = Site::links(&store, Query::default()).await?;
+        let urls: Vec<&str> = links.iter().map(|link| link.url()).collect();
+        assert_eq!(
+            urls,
+            ["https://example.com/rust", "https://example.com/zig"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn renders_pages_feeds_and_search() -> eyre::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path().join("site");
+        let dir = dir.as_path();
+        let mut site = Site::new("Reading", "https://blog.example.com", None)?;
+        site.per_page = 1;
+        site.timezone = "America/New_York".parse()?;
+
+        let mut out = Site::writer(dir)?;
+        site.render(&links(), &mut out)?;
+        out.finish()?;
+        assert!(!dir.join(MANIFEST).exists());
+        assert!(tmp.path().join(".site.likelike-export.json").exists());
+
+        let index = read(dir, "index.html");
+        assert!(index.contains("Rust &lt;3"));
+        // Read at midnight UTC, which was the evening before in New York.
+        assert!(index.contains(r#"<time datetime="2023-02-28T19:00:00-05:00">2023-02-28</time>"#));
+        assert!(index.contains("<em>reading</em>"));
+        assert!(index.contains(r#"href="page/2/""#));
+        assert!(index.contains(r#"href="via/alice/">Alice</a>"#));
+        assert!(read(dir, "page/2/index.html").contains(r#"href="../../static/style.css""#));
+
+        let lang = read(dir, "tags/lang/index.html");
+        assert!(lang.contains("#lang"));
+        assert!(read(dir, "tags/lang/page/2/index.html").contains("example.com/go"));
+        assert!(read(dir, "tags/index.html").contains(r#"href="../tags/lang/go/""#));
+        assert!(read(dir, "tags/lang/rust/atom.xml").contains("https://example.com/rust"));
+        let alice = read(dir, "via/alice/index.html");
+        assert!(alice.contains("via Alice"));
+        assert!(alice.contains("via/alice/page/2/"));
+        assert!(read(dir, "via/alice/page/2/index.html").contains("example.com/zig"));
+        assert_eq!(read(dir, "via/index.html").matches("via/alice/").count(), 1);
+        assert!(read(dir, "links/https-example-com-go/index.html")
+            .contains(r#"via <a href="https://example.org/">"#));
+
+        for feed in ["atom.xml", "rss.xml", "feed.json"] {
+            assert!(read(dir, feed).contains("https://blog.example.com/"));
+        }
+
+        let search: serde_json::Value = serde_json::from_str(&read(dir, "search.json"))?;
+        assert_eq!(search[0]["path"], "links/https-example-com-rust/");
+        assert_eq!(search[0]["via"], "Alice");
+        Ok(())
+    }
+
+    #[test]
+    fn tags_that_slugify_alike_get_their_own_pages() -> eyre::Result<()> {
+        let tagged = |url: &str, tags: &[&str]| {
+            let mut link = Link::new(url, url);
+            link.tags = tags.iter().map(|xs| xs.to_string()).collect();
+            link
+        };
+        let links = vec![
+            tagged("https://example.com/cpp", &["C++/async"]),
+            tagged("https://example.com/c", &["c"]),
+            tagged("https://example.com/great", &["this is great"]),
+            tagged("https://example.com/dashed", &["this-is-great"]),
+            tagged("https://example.com/symbols", &["++"]),
+        ];
+
+        let paths = tag_paths([
+            "++",
+            "C++",
+            "C++/async",
+            "c",
+            "this is great",
+            "this-is-great",
+        ]);
+        assert_eq!(paths["++"], "tags/tag/");
+        assert_eq!(paths["C++"], "tags/c/");
+        assert_eq!(paths["C++/async"], "tags/c/async/");
+        assert_eq!(paths["c"], "tags/c-2/");
+        assert_eq!(paths["this is great"], "tags/this-is-great/");
+        assert_eq!(paths["this-is-great"], "tags/this-is-great-2/");
+
+        let dir = tempfile::tempdir()?;
+        let site = Site::new("Reading", "https://blog.example.com/", None)?;
+        let mut out = ExportWriter::open(dir.path())?;
+        site.render(&links, &mut out)?;
+        out.finish()?;
+
+        let cpp = read(dir.path(), "tags/c/index.html");
+        assert!(cpp.contains("example.com/cpp"));
+        assert!(!cpp.contains("example.com/c\""));
+        assert!(read(dir.path(), "tags/c-2/index.html").contains("example.com/c\""));
+        assert!(read(dir.path(), "tags/this-is-great-2/atom.xml").contains("example.com/dashed"));
+        assert!(read(dir.path(), "tags/tag/index.html").contains("example.com/symbols"));
+        assert!(read(dir.path(), "links/https-example-com-c/index.html")
+            .contains(r#"href="../../tags/c-2/""#));
+        Ok(())
+    }
+
+    #[test]
+    fn templates_override_the_theme() -> eyre::Result<()> {
+        let templates = tempfile::tempdir()?;
+        std::fs::write(
+            templates.path().join("link.html"),
+            r#"{% extends "base.html" %}{% block content %}custom {{ link.title }}{% endblock %}"#,
+        )?;
+        std::fs::create_dir(templates.path().join("static"))?;
+        std::fs::write(templates.path().join("static/extra.css"), "extra")?;
+
+        let dir = tempfile::tempdir()?;
+        let site = Site::new(
+            "Reading",
+            "https://blog.example.com/",
+            Some(templates.path()),
+        )?;
+        let mut out = ExportWriter::open(dir.path())?;
+        site.render(&links(), &mut out)?;
+        out.finish()?;
+
+        assert!(read(dir.path(), "links/https-example-com-go/index.html").contains("custom Go"));
+        assert_eq!(read(dir.path(), "static/extra.css"), "extra");
+        assert!(!read(dir.path(), "static/style.css").is_empty());
+        Ok(())
+    }
+}
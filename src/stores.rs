@@ -1,15 +1,30 @@
+mod any;
+mod blobs;
 mod external;
 mod memory;
+mod migrations;
+mod postgres;
+mod sql;
 mod sqlite;
 
-use std::pin::Pin;
+use std::{pin::Pin, sync::Arc};
 
+pub use any::*;
+pub use blobs::*;
 pub use external::*;
 use futures::Stream;
+pub(crate) use memory::LinkFixture;
 pub use memory::*;
+pub use migrations::{Migration, MigrationState, MigrationStatus};
+pub use postgres::*;
+pub use sql::{Cursor, ListPage, ListParams};
 pub use sqlite::*;
 
-use crate::Link;
+use crate::{Link, Query, TagAliases, TagOperation};
+
+/// The number of links fetched at a time when listing every link, e.g. with
+/// [`LinkQuery::list_all`].
+pub(crate) const LIST_PAGE_SIZE: i64 = 500;
 
 /// Read link information from the link store.
 #[async_trait::async_trait]
@@ -40,3 +55,102 @@ pub trait LinkReader {
 pub trait LinkWriter {
     async fn write(&self, link: Link) -> eyre::Result<bool>;
 }
+
+/// Query links in bulk, as the server does: filtered, sorted and paginated listings, and the tags
+/// in use along with operations over them.
+#[async_trait::async_trait]
+pub trait LinkQuery {
+    /// Counts links matching the given filters, ignoring pagination.
+    async fn count(&self, params: &ListParams) -> eyre::Result<i64>;
+
+    /// Lists a page of links matching the given filters. Listed links omit their source data.
+    async fn list(&self, params: &ListParams) -> eyre::Result<ListPage>;
+
+    /// Lists every link matching the given filters, a page of `params.limit` links at a time.
+    async fn list_all(&self, mut params: ListParams) -> eyre::Result<Vec<Link>> {
+        let mut links = Vec::new();
+        loop {
+            let page = self.list(&params).await?;
+            links.extend(page.links);
+            match page.next {
+                Some(next) => params.cursor = Some(next),
+                None => return Ok(links),
+            }
+        }
+    }
+
+    /// Returns all distinct tags in use, including the ancestors of hierarchical tags. If a query
+    /// is given, only tags on matching links are returned.
+    async fn all_tags(&self, query: Option<&Query>) -> eyre::Result<Vec<String>>;
+
+    /// Applies a tag operation to every link at once, returning the number of links changed. The
+    /// aliases implied by the operation are recorded so that future imports agree with the
+    /// result, and existing aliases are rewritten to follow it.
+    async fn apply_tag_operation(&self, op: &TagOperation) -> eyre::Result<u64>;
+
+    /// Returns the tag aliases applied when importing links.
+    async fn tag_aliases(&self) -> eyre::Result<TagAliases>;
+
+    /// Records `alias` as an alternate spelling of `tag`. An empty `tag` drops the alias on
+    /// import.
+    async fn set_tag_alias(&self, alias: &str, tag: &str) -> eyre::Result<()>;
+
+    /// Removes an alias, returning whether it existed.
+    async fn remove_tag_alias(&self, alias: &str) -> eyre::Result<bool>;
+}
+
+#[async_trait::async_trait]
+impl<T: LinkReader + Send + Sync> LinkReader for Arc<T> {
+    async fn get(&self, link: &str) -> eyre::Result<Option<Link>> {
+        (**self).get(link).await
+    }
+
+    async fn values<'a>(&'a self) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a + Send>>> {
+        (**self).values().await
+    }
+
+    async fn glob<'a, 'b: 'a>(
+        &'a self,
+        pattern: &'b str,
+    ) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a>>> {
+        (**self).glob(pattern).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: LinkWriter + Send + Sync> LinkWriter for Arc<T> {
+    async fn write(&self, link: Link) -> eyre::Result<bool> {
+        (**self).write(link).await
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: LinkQuery + Send + Sync> LinkQuery for Arc<T> {
+    async fn count(&self, params: &ListParams) -> eyre::Result<i64> {
+        (**self).count(params).await
+    }
+
+    async fn list(&self, params: &ListParams) -> eyre::Result<ListPage> {
+        (**self).list(params).await
+    }
+
+    async fn all_tags(&self, query: Option<&Query>) -> eyre::Result<Vec<String>> {
+        (**self).all_tags(query).await
+    }
+
+    async fn apply_tag_operation(&self, op: &TagOperation) -> eyre::Result<u64> {
+        (**self).apply_tag_operation(op).await
+    }
+
+    async fn tag_aliases(&self) -> eyre::Result<TagAliases> {
+        (**self).tag_aliases().await
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str) -> eyre::Result<()> {
+        (**self).set_tag_alias(alias, tag).await
+    }
+
+    async fn remove_tag_alias(&self, alias: &str) -> eyre::Result<bool> {
+        (**self).remove_tag_alias(alias).await
+    }
+}
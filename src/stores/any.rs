@@ -0,0 +1,130 @@
+use futures::Stream;
+use sqlx::{postgres::PgConnectOptions, sqlite::SqliteConnectOptions};
+use std::{pin::Pin, str::FromStr};
+
+use super::sql::{ListPage, ListParams};
+use crate::{
+    BlobStore, Compaction, Link, LinkQuery, LinkReader, LinkWriter, Migration, MigrationStatus,
+    PostgresStore, Query, SqliteStore, TagAliases, TagOperation,
+};
+
+/// A database-backed store chosen by connection string: `postgres://` and `postgresql://` urls
+/// connect to a [`PostgresStore`], anything else to a [`SqliteStore`].
+#[derive(Debug)]
+pub enum AnyStore {
+    Sqlite(SqliteStore),
+    Postgres(PostgresStore),
+}
+
+macro_rules! dispatch {
+    ($self:expr, $store:ident => $body:expr) => {
+        match $self {
+            AnyStore::Sqlite($store) => $body,
+            AnyStore::Postgres($store) => $body,
+        }
+    };
+}
+
+fn is_postgres(s: &str) -> bool {
+    s.starts_with("postgres://") || s.starts_with("postgresql://")
+}
+
+impl AnyStore {
+    /// Connect to the database and apply any pending migrations.
+    pub async fn with_connection_string(s: impl AsRef<str>) -> eyre::Result<Self> {
+        let s = s.as_ref();
+        Ok(if is_postgres(s) {
+            AnyStore::Postgres(PostgresStore::with_connection_string(s).await?)
+        } else {
+            AnyStore::Sqlite(SqliteStore::with_connection_string(s).await?)
+        })
+    }
+
+    /// Connect to the database without applying migrations.
+    pub async fn connect(s: impl AsRef<str>) -> eyre::Result<Self> {
+        let s = s.as_ref();
+        Ok(if is_postgres(s) {
+            AnyStore::Postgres(PostgresStore::connect(PgConnectOptions::from_str(s)?).await?)
+        } else {
+            AnyStore::Sqlite(SqliteStore::connect(SqliteConnectOptions::from_str(s)?).await?)
+        })
+    }
+
+    pub async fn migration_status(&self) -> eyre::Result<Vec<MigrationStatus>> {
+        dispatch!(self, store => store.migration_status().await)
+    }
+
+    /// Apply pending migrations, returning the ones applied.
+    pub async fn migrate(&self) -> eyre::Result<Vec<Migration>> {
+        dispatch!(self, store => store.migrate().await)
+    }
+
+    /// Revert the `steps` most recently applied migrations, returning the ones reverted.
+    pub async fn rollback(&self, steps: usize) -> eyre::Result<Vec<Migration>> {
+        dispatch!(self, store => store.rollback(steps).await)
+    }
+
+    /// Move any source data out of the database into `blobs` and reclaim the space it used.
+    pub async fn compact(&self, blobs: &(dyn BlobStore + Send + Sync)) -> eyre::Result<Compaction> {
+        match self {
+            AnyStore::Sqlite(store) => store.compact(blobs).await,
+            AnyStore::Postgres(store) => store.compact(blobs).await,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkQuery for AnyStore {
+    async fn count(&self, params: &ListParams) -> eyre::Result<i64> {
+        dispatch!(self, store => store.count(params).await)
+    }
+
+    async fn list(&self, params: &ListParams) -> eyre::Result<ListPage> {
+        dispatch!(self, store => store.list(params).await)
+    }
+
+    async fn all_tags(&self, query: Option<&Query>) -> eyre::Result<Vec<String>> {
+        dispatch!(self, store => store.all_tags(query).await)
+    }
+
+    async fn apply_tag_operation(&self, op: &TagOperation) -> eyre::Result<u64> {
+        dispatch!(self, store => store.apply_tag_operation(op).await)
+    }
+
+    async fn tag_aliases(&self) -> eyre::Result<TagAliases> {
+        dispatch!(self, store => store.tag_aliases().await)
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str) -> eyre::Result<()> {
+        dispatch!(self, store => store.set_tag_alias(alias, tag).await)
+    }
+
+    async fn remove_tag_alias(&self, alias: &str) -> eyre::Result<bool> {
+        dispatch!(self, store => store.remove_tag_alias(alias).await)
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkReader for AnyStore {
+    async fn get(&self, link: &str) -> eyre::Result<Option<Link>> {
+        dispatch!(self, store => store.get(link).await)
+    }
+
+    async fn values<'a>(&'a self) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a + Send>>> {
+        dispatch!(self, store => store.values().await)
+    }
+
+    async fn glob<'a, 'b: 'a>(
+        &'a self,
+        pattern: &'b str,
+    ) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a>>> {
+        dispatch!(self, store => store.glob(pattern).await)
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkWriter for AnyStore {
+    async fn write(&self, link: Link) -> eyre::Result<bool> {
+        dispatch!(self, store => store.write(link).await)
+    }
+}
@@ -0,0 +1,591 @@
+use cacache::{Algorithm, Integrity, WriteOpts};
+use chrono::{DateTime, TimeZone, Utc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{query::url_host, Link};
+
+/// How long a source entry goes between refreshes of its last-used time when it is read.
+/// Refreshing appends to the cache index, so reads within this window don't.
+const TOUCH_INTERVAL_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Storage for the large data associated with links, kept apart from the link database: source
+/// bodies and text extractions. Blobs are looked up by key; see [`src_key`] and [`text_key`].
+#[async_trait::async_trait]
+pub trait BlobStore {
+    async fn read(&self, key: &str) -> eyre::Result<Option<Vec<u8>>>;
+    async fn write(&self, key: &str, data: &[u8]) -> eyre::Result<()>;
+    async fn remove(&self, key: &str) -> eyre::Result<()>;
+}
+
+#[async_trait::async_trait]
+impl<T: BlobStore + Send + Sync + ?Sized> BlobStore for Box<T> {
+    async fn read(&self, key: &str) -> eyre::Result<Option<Vec<u8>>> {
+        (**self).read(key).await
+    }
+
+    async fn write(&self, key: &str, data: &[u8]) -> eyre::Result<()> {
+        (**self).write(key, data).await
+    }
+
+    async fn remove(&self, key: &str) -> eyre::Result<()> {
+        (**self).remove(key).await
+    }
+}
+
+/// What compacting a link database did: how many links had source data moved out to the blob
+/// store, and how many (compressed) bytes that removed from the database.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Compaction {
+    pub moved: u64,
+    pub bytes: u64,
+}
+
+/// The key a link's source body is stored under.
+pub fn src_key(url: &str) -> String {
+    format!("src!{}", url)
+}
+
+/// The key a link's extracted text is stored under.
+pub fn text_key(url: &str) -> String {
+    format!("txt!{}", url)
+}
+
+/// An entry in a [`CacacheBlobStore`].
+#[derive(Debug, Clone)]
+pub struct BlobEntry {
+    pub key: String,
+    pub size: u64,
+    /// When the entry was written, or when it was last read if it holds source data.
+    pub last_used: DateTime<Utc>,
+    integrity: Integrity,
+}
+
+impl BlobEntry {
+    /// The url of the link this entry belongs to, if it was stored under a [`src_key`] or a
+    /// [`text_key`].
+    pub fn url(&self) -> Option<&str> {
+        self.key
+            .strip_prefix("src!")
+            .or_else(|| self.key.strip_prefix("txt!"))
+    }
+
+    pub fn is_source(&self) -> bool {
+        self.key.starts_with("src!")
+    }
+}
+
+/// What [`CacacheBlobStore::gc`] removed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Collection {
+    /// Entries for links that no longer exist.
+    pub orphaned: u64,
+    /// Source entries evicted to bring the cache under its size limit.
+    pub evicted: u64,
+    /// Content files no remaining entry referred to, and their total size.
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// The entries [`CacacheBlobStore::verify`] checked, and the keys (with a reason) of those whose
+/// content is missing or does not match its integrity hash.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub checked: u64,
+    pub failed: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, bytes: u64) {
+        self.entries += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// Cache usage, broken down by content type and by host. Each entry counts in full, even when
+/// identical content is shared between entries and only stored once.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    pub total: Usage,
+    pub by_content_type: BTreeMap<String, Usage>,
+    pub by_host: BTreeMap<String, Usage>,
+}
+
+/// A [`BlobStore`] backed by a cacache directory. Content is stored by hash and checked against
+/// it on read, so identical bodies are stored once and corrupt ones are reported.
+///
+/// The store may be given a size limit, above which [`CacacheBlobStore::evict`] drops source
+/// entries, least recently used first. Extracted text is always kept.
+#[derive(Debug, Clone)]
+pub struct CacacheBlobStore {
+    directory: PathBuf,
+    max_bytes: Option<u64>,
+}
+
+impl CacacheBlobStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            max_bytes: None,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Use `LIKELIKE_CACHE_DIR` if set, otherwise a cache directory in the local data dir. The
+    /// size limit is read from `LIKELIKE_CACHE_MAX_BYTES`; by default there is none.
+    pub fn from_env() -> Self {
+        let directory = env::var("LIKELIKE_CACHE_DIR").ok().unwrap_or_else(|| {
+            dirs::data_local_dir()
+                .map(|mut xs| {
+                    xs.push("likelike");
+                    xs.push("cache");
+                    std::fs::create_dir_all(&xs).expect("Must be able to create XDG_SHARE_HOME");
+                    xs.to_string_lossy().to_string()
+                })
+                .unwrap_or_else(|| "likelike_cache".to_string())
+        });
+
+        let max_bytes = env::var("LIKELIKE_CACHE_MAX_BYTES")
+            .ok()
+            .and_then(|xs| xs.parse().ok());
+
+        Self::new(directory).with_max_bytes(max_bytes)
+    }
+
+    pub fn entries(&self) -> eyre::Result<Vec<BlobEntry>> {
+        let mut entries = Vec::new();
+        for metadata in cacache::list_sync(&self.directory) {
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                // Nothing has been written to the cache yet.
+                Err(cacache::Error::IoError(e, _)) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            entries.push(BlobEntry {
+                key: metadata.key,
+                size: metadata.size as u64,
+                last_used: Utc
+                    .timestamp_millis_opt(metadata.time as i64)
+                    .single()
+                    .unwrap_or_default(),
+                integrity: metadata.integrity,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Remove the entries of links whose urls are not in `urls`, evict source entries if the
+    /// cache is over its size limit, then delete any content that no entry refers to. Content
+    /// written after the collection started is left alone, since an entry for it may be on its
+    /// way.
+    pub async fn gc(&self, urls: &HashSet<String>) -> eyre::Result<Collection> {
+        let started = SystemTime::now();
+        let mut collection = Collection::default();
+        let mut entries = Vec::new();
+        for entry in self.entries()? {
+            match entry.url() {
+                Some(url) if !urls.contains(url) => {
+                    cacache::remove(&self.directory, &entry.key).await?;
+                    collection.orphaned += 1;
+                }
+                _ => entries.push(entry),
+            }
+        }
+
+        (collection.evicted, _) = self.evict_entries(&mut entries).await?;
+        (collection.files, collection.bytes) = self.remove_unreferenced(started).await?;
+        Ok(collection)
+    }
+
+    /// Evict source entries, least recently used first, until the cache is under its size limit.
+    /// Returns the number of entries evicted; without a limit, or under it, this does nothing.
+    pub async fn evict(&self) -> eyre::Result<u64> {
+        if self.max_bytes.is_none() {
+            return Ok(0);
+        }
+
+        let mut entries = self.entries()?;
+        let (evicted, freed) = self.evict_entries(&mut entries).await?;
+        for integrity in freed {
+            cacache::remove_hash(&self.directory, &integrity).await?;
+        }
+        Ok(evicted)
+    }
+
+    /// Check the content of every entry against its integrity hash. If `remove` is set, entries
+    /// that fail are removed, so the next refetch replaces them.
+    pub async fn verify(&self, remove: bool) -> eyre::Result<Verification> {
+        let started = SystemTime::now();
+        let mut verification = Verification::default();
+        for entry in self.entries()? {
+            verification.checked += 1;
+            if let Err(e) = cacache::read_hash(&self.directory, &entry.integrity).await {
+                if remove {
+                    cacache::remove(&self.directory, &entry.key).await?;
+                }
+                verification.failed.push((entry.key, e.to_string()));
+            }
+        }
+
+        if remove {
+            self.remove_unreferenced(started).await?;
+        }
+        Ok(verification)
+    }
+
+    /// Tally cache usage. Source entries are grouped by the content type their link was served
+    /// with, so `links` should map urls to links with their HTTP headers.
+    pub fn stats(&self, links: &HashMap<String, Link>) -> eyre::Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for entry in self.entries()? {
+            let url = entry.url();
+            let content_type = match url {
+                Some(url) if entry.is_source() => links
+                    .get(url)
+                    .and_then(|link| link.http_headers())
+                    .and_then(|hdrs| hdrs.get("content-type"))
+                    .and_then(|xs| xs.last())
+                    .and_then(|xs| xs.split(';').next())
+                    .map(|xs| xs.trim().to_lowercase())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                Some(_) => "extracted text".to_string(),
+                None => "other".to_string(),
+            };
+
+            let host = url
+                .and_then(url_host)
+                .unwrap_or_else(|| "other".to_string());
+
+            stats.total.add(entry.size);
+            stats
+                .by_content_type
+                .entry(content_type)
+                .or_default()
+                .add(entry.size);
+            stats.by_host.entry(host).or_default().add(entry.size);
+        }
+        Ok(stats)
+    }
+
+    /// Evict entries from the index, returning how many were evicted along with the content no
+    /// remaining entry refers to.
+    async fn evict_entries(
+        &self,
+        entries: &mut Vec<BlobEntry>,
+    ) -> eyre::Result<(u64, Vec<Integrity>)> {
+        let Some(max_bytes) = self.max_bytes else {
+            return Ok((0, Vec::new()));
+        };
+
+        // Identical content is only stored once, so only count it once.
+        let mut references: HashMap<Integrity, usize> = HashMap::new();
+        let mut total = 0;
+        for entry in entries.iter() {
+            let count = references.entry(entry.integrity.clone()).or_default();
+            if *count == 0 {
+                total += entry.size;
+            }
+            *count += 1;
+        }
+
+        if total <= max_bytes {
+            return Ok((0, Vec::new()));
+        }
+
+        entries.sort_by_key(|entry| entry.last_used);
+
+        let mut evicted = 0;
+        let mut freed = Vec::new();
+        let mut kept = Vec::with_capacity(entries.len());
+        for entry in entries.drain(..) {
+            if total <= max_bytes || !entry.is_source() {
+                kept.push(entry);
+                continue;
+            }
+
+            cacache::remove(&self.directory, &entry.key).await?;
+            evicted += 1;
+            if let Some(count) = references.get_mut(&entry.integrity) {
+                *count -= 1;
+                if *count == 0 {
+                    total -= entry.size;
+                    freed.push(entry.integrity);
+                }
+            }
+        }
+
+        *entries = kept;
+        Ok((evicted, freed))
+    }
+
+    /// Delete content files written before `before` that no entry refers to. Removing an entry
+    /// only removes it from the index, and replacing one leaves its old content behind, so this is
+    /// what frees space. Returns the number of files deleted and their total size.
+    async fn remove_unreferenced(&self, before: SystemTime) -> eyre::Result<(u64, u64)> {
+        // Read the index again rather than trusting an earlier listing, which would miss entries
+        // written since.
+        let entries = self.entries()?;
+        let referenced: HashSet<_> = entries.iter().map(|entry| &entry.integrity).collect();
+
+        let (mut files, mut bytes) = (0, 0);
+        for (integrity, size, modified) in content_files(&self.directory)? {
+            if referenced.contains(&integrity) || modified >= before {
+                continue;
+            }
+
+            cacache::remove_hash(&self.directory, &integrity).await?;
+            files += 1;
+            bytes += size;
+        }
+        Ok((files, bytes))
+    }
+}
+
+/// List the content stored in a cacache directory, along with the size and modification time of
+/// each file. Content is stored at `content-v2/<algorithm>/<hex digest>`, with the digest split
+/// into directories after its second and fourth characters.
+fn content_files(directory: &Path) -> eyre::Result<Vec<(Integrity, u64, SystemTime)>> {
+    fn read_dir(path: &Path) -> eyre::Result<Vec<(String, PathBuf)>> {
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            names.push((
+                entry.file_name().to_string_lossy().to_string(),
+                entry.path(),
+            ));
+        }
+        Ok(names)
+    }
+
+    let mut files = Vec::new();
+    for (algorithm, path) in read_dir(&directory.join("content-v2"))? {
+        let Ok(algorithm) = algorithm.parse::<Algorithm>() else {
+            continue;
+        };
+
+        for (first, path) in read_dir(&path)? {
+            for (second, path) in read_dir(&path)? {
+                for (rest, path) in read_dir(&path)? {
+                    let hex = format!("{first}{second}{rest}");
+                    let Ok(integrity) = Integrity::from_hex(hex, algorithm) else {
+                        continue;
+                    };
+                    let metadata = std::fs::metadata(path)?;
+                    files.push((integrity, metadata.len(), metadata.modified()?));
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[async_trait::async_trait]
+impl BlobStore for CacacheBlobStore {
+    async fn read(&self, key: &str) -> eyre::Result<Option<Vec<u8>>> {
+        let Some(metadata) = cacache::metadata(&self.directory, key).await? else {
+            return Ok(None);
+        };
+        let data = cacache::read_hash(&self.directory, &metadata.integrity).await?;
+
+        // Source entries are evicted least recently used first, so note that this one was used.
+        let now = Utc::now().timestamp_millis();
+        if key.starts_with("src!") && now - (metadata.time as i64) > TOUCH_INTERVAL_MS {
+            let opts = WriteOpts::new()
+                .integrity(metadata.integrity)
+                .size(metadata.size);
+            cacache::index::insert_async(&self.directory, key, opts).await?;
+        }
+
+        Ok(Some(data))
+    }
+
+    async fn write(&self, key: &str, data: &[u8]) -> eyre::Result<()> {
+        cacache::write(&self.directory, key, data).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> eyre::Result<()> {
+        cacache::remove(&self.directory, key).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_blobs_by_key() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let blobs = CacacheBlobStore::new(dir.path());
+        let key = src_key("https://example.com/");
+
+        assert_eq!(blobs.read(&key).await?, None);
+        blobs.write(&key, b"hello").await?;
+        assert_eq!(blobs.read(&key).await?.as_deref(), Some(&b"hello"[..]));
+
+        blobs.remove(&key).await?;
+        assert_eq!(blobs.read(&key).await?, None);
+        Ok(())
+    }
+
+    const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+    async fn write_days_ago(
+        blobs: &CacacheBlobStore,
+        key: &str,
+        data: &[u8],
+        days: i64,
+    ) -> eyre::Result<()> {
+        let integrity = cacache::write(&blobs.directory, key, data).await?;
+        let time = Utc::now().timestamp_millis() - days * DAY_MS;
+        let opts = WriteOpts::new()
+            .integrity(integrity)
+            .size(data.len())
+            .time(time as u128);
+        cacache::index::insert_async(&blobs.directory, key, opts).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn collects_orphans_and_evicts_old_source() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let blobs = CacacheBlobStore::new(dir.path()).with_max_bytes(Some(12));
+        let (a, b) = ("https://a.example/", "https://b.example/");
+
+        write_days_ago(&blobs, &src_key(a), b"aaaaaaaa", 3).await?;
+        write_days_ago(&blobs, &text_key(a), b"aaa", 3).await?;
+        write_days_ago(&blobs, &src_key(b), b"old b body", 2).await?;
+        write_days_ago(&blobs, &src_key(b), b"bbbbbbbb", 1).await?;
+        write_days_ago(&blobs, &src_key("https://gone.example/"), b"gone!", 0).await?;
+
+        let urls = HashSet::from([a.to_string(), b.to_string()]);
+        let collection = blobs.gc(&urls).await?;
+        assert_eq!(
+            collection,
+            Collection {
+                orphaned: 1,
+                evicted: 1,
+                files: 3,
+                bytes: 23,
+            }
+        );
+
+        assert_eq!(blobs.read(&src_key(a)).await?, None);
+        assert_eq!(
+            blobs.read(&text_key(a)).await?.as_deref(),
+            Some(&b"aaa"[..])
+        );
+        assert_eq!(
+            blobs.read(&src_key(b)).await?.as_deref(),
+            Some(&b"bbbbbbbb"[..])
+        );
+
+        let stats = blobs.stats(&HashMap::new())?;
+        assert_eq!(
+            stats.total,
+            Usage {
+                entries: 2,
+                bytes: 11
+            }
+        );
+        assert_eq!(stats.by_content_type["extracted text"].bytes, 3);
+        assert_eq!(stats.by_host["b.example"].bytes, 8);
+
+        assert_eq!(blobs.gc(&urls).await?, Collection::default());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keeps_content_written_during_collection() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let blobs = CacacheBlobStore::new(dir.path());
+
+        // Content that an import has written but not yet indexed.
+        let started = SystemTime::now() - std::time::Duration::from_secs(60);
+        cacache::write_hash(&blobs.directory, b"not yet indexed").await?;
+        assert_eq!(blobs.remove_unreferenced(started).await?, (0, 0));
+
+        assert_eq!(blobs.remove_unreferenced(SystemTime::now()).await?, (1, 15));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn evicts_only_over_the_limit() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let url = "https://a.example/";
+        CacacheBlobStore::new(dir.path())
+            .write(&src_key(url), b"aaaaaaaa")
+            .await?;
+
+        let blobs = CacacheBlobStore::new(dir.path()).with_max_bytes(Some(8));
+        assert_eq!(blobs.evict().await?, 0);
+
+        let blobs = blobs.with_max_bytes(Some(4));
+        assert_eq!(blobs.evict().await?, 1);
+        assert_eq!(blobs.read(&src_key(url)).await?, None);
+        assert!(content_files(dir.path())?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify_finds_and_removes_corrupt_entries() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let blobs = CacacheBlobStore::new(dir.path());
+        let url = "https://a.example/";
+
+        blobs.write(&src_key(url), b"the original body").await?;
+        blobs.write(&text_key(url), b"the original text").await?;
+
+        let integrity = Integrity::from(b"the original body");
+        let (algorithm, hex) = integrity.to_hex();
+        let path = dir
+            .path()
+            .join("content-v2")
+            .join(algorithm.to_string())
+            .join(&hex[0..2])
+            .join(&hex[2..4])
+            .join(&hex[4..]);
+        std::fs::write(path, b"the garbled body!")?;
+
+        assert!(blobs.read(&src_key(url)).await.is_err());
+
+        let verification = blobs.verify(false).await?;
+        assert_eq!(verification.checked, 2);
+        assert_eq!(verification.failed.len(), 1);
+        assert_eq!(verification.failed[0].0, src_key(url));
+
+        blobs.verify(true).await?;
+        assert_eq!(blobs.read(&src_key(url)).await?, None);
+        assert_eq!(
+            blobs.verify(false).await?,
+            Verification {
+                checked: 1,
+                failed: vec![],
+            }
+        );
+        Ok(())
+    }
+}
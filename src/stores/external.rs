@@ -1,39 +1,47 @@
-use std::env;
 use std::path::PathBuf;
 
-use crate::{processors::LinkReadProcessor, Link, LinkReader, LinkWriter};
+use crate::{
+    processors::LinkReadProcessor, src_key, text_key, BlobStore, CacacheBlobStore, Link,
+    LinkReader, LinkWriter,
+};
 
 /// An external store is used for data associated with the link
 /// that we are unlikely to use when exporting static site data, especially
 /// when that data is large or requires computation. This includes the original source data and text
 /// extractions.
+///
+/// The link stores refuse to write source data themselves, so this wrapper is the only way it is
+/// saved. Source data in the blob store is preferred over any that a database written by an older
+/// version still holds.
 pub struct ExternalWrap<T> {
-    cache_directory: PathBuf,
+    blobs: Box<dyn BlobStore + Send + Sync>,
+    sources: bool,
     inner: T,
 }
 
 impl<T> ExternalWrap<T> {
     pub fn wrap(inner: T) -> Self {
-        let default_dir = env::var("LIKELIKE_CACHE_DIR").ok().unwrap_or_else(|| {
-            dirs::data_local_dir()
-                .map(|mut xs| {
-                    xs.push("likelike");
-                    xs.push("cache");
-                    std::fs::create_dir_all(&xs).expect("Must be able to create XDG_SHARE_HOME");
-                    xs.to_string_lossy().to_string()
-                })
-                .unwrap_or_else(|| "likelike_cache".to_string())
-        });
-
-        Self::new(default_dir.into(), inner)
+        Self::with_blob_store(CacacheBlobStore::from_env(), inner)
     }
 
     pub fn new(cache_directory: PathBuf, inner: T) -> Self {
+        Self::with_blob_store(CacacheBlobStore::new(cache_directory), inner)
+    }
+
+    pub fn with_blob_store(blobs: impl BlobStore + Send + Sync + 'static, inner: T) -> Self {
         Self {
-            cache_directory,
+            blobs: Box::new(blobs),
+            sources: true,
             inner,
         }
     }
+
+    /// Only read extracted text back, leaving source data in the blob store. Comparing links
+    /// needs their text, but reading every source body along with it is costly.
+    pub fn without_sources(mut self) -> Self {
+        self.sources = false;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -44,23 +52,28 @@ where
     type Inner = T;
 
     async fn hydrate(&self, mut link: Link) -> eyre::Result<Link> {
-        if link.src.is_none() {
-            link.src = cacache::read(
-                self.cache_directory.as_path(),
-                format!("src!{}", link.url()),
-            )
-            .await
-            .ok();
+        // Unreadable entries are reported rather than failing the read; `likelike cache verify`
+        // finds and removes them.
+        if self.sources {
+            match self.blobs.read(&src_key(link.url())).await {
+                Ok(Some(src)) => link.src = Some(src),
+                Ok(None) => {}
+                Err(e) => eprintln!("could not read cached source for {}: {}", link.url(), e),
+            }
+        } else {
+            // Don't pass along stale source data from the database either, lest it be written
+            // back over the blob store's.
+            link.src = None;
         }
 
         if link.extracted_text.is_none() {
-            link.extracted_text = cacache::read(
-                self.cache_directory.as_path(),
-                format!("txt!{}", link.url()),
-            )
-            .await
-            .ok()
-            .map(|xs| String::from_utf8_lossy(xs.as_slice()).to_string());
+            match self.blobs.read(&text_key(link.url())).await {
+                Ok(text) => {
+                    link.extracted_text =
+                        text.map(|xs| String::from_utf8_lossy(xs.as_slice()).to_string())
+                }
+                Err(e) => eprintln!("could not read cached text for {}: {}", link.url(), e),
+            }
         }
 
         Ok(link)
@@ -75,21 +88,13 @@ where
 impl<T: LinkWriter + Send + Sync> LinkWriter for ExternalWrap<T> {
     async fn write(&self, mut link: Link) -> eyre::Result<bool> {
         if let Some(src) = link.src.take() {
-            cacache::write(
-                self.cache_directory.as_path(),
-                format!("src!{}", link.url()),
-                src,
-            )
-            .await?;
+            self.blobs.write(&src_key(link.url()), &src).await?;
         }
 
         if let Some(extracted_text) = link.extracted_text.take() {
-            cacache::write(
-                self.cache_directory.as_path(),
-                format!("txt!{}", link.url()),
-                extracted_text,
-            )
-            .await?;
+            self.blobs
+                .write(&text_key(link.url()), extracted_text.as_bytes())
+                .await?;
         }
         self.inner.write(link).await
     }
@@ -1,22 +1,235 @@
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
 use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
 
-use std::{collections::HashMap, fmt::Debug, pin::Pin};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Debug,
+    path::Path,
+    pin::Pin,
+};
 use tokio::sync::Mutex;
 
-use crate::{Link, LinkReader, LinkWriter};
+use crate::{
+    tag_ancestors, Cursor, Link, LinkQuery, LinkReader, LinkWriter, ListPage, ListParams, Query,
+    SortKey, TagAliases, TagOperation, Via,
+};
 
-/// An in-memory link store.
+/// An in-memory link store, for tests and for trying out changes without touching a database.
+///
+/// It behaves like [`crate::SqliteStore`]: timestamps are kept to the millisecond, empty and
+/// repeated tags are dropped on write, extracted text is not stored, and `values()` and `list()`
+/// omit link source data. Unlike the database stores, it keeps source data rather than leaving
+/// it to a blob store. Stores can be loaded from and dumped to JSON fixtures.
 #[derive(Default, Debug)]
-struct InMemoryStore {
-    data: Mutex<HashMap<String, Link>>,
+pub struct InMemoryStore {
+    data: Mutex<BTreeMap<String, Link>>,
+    aliases: Mutex<BTreeMap<String, String>>,
+}
+
+/// Link source data is written as a string when it is valid UTF-8, and as an array of bytes
+/// otherwise.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum SourceFixture {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+/// A link as it is written to JSON fixtures and dumps. Extracted text is left out.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LinkFixture {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    via: Option<Via>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    found_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    read_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    published_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from_filename: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    src: Option<SourceFixture>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    src_truncated: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    meta: Option<HashMap<String, Vec<String>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_fetched: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_processed: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    http_headers: Option<HashMap<String, Vec<String>>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    hidden: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summarized_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    digested_at: Option<DateTime<Utc>>,
+}
+
+impl From<Link> for LinkFixture {
+    fn from(link: Link) -> Self {
+        Self {
+            url: link.url,
+            title: link.title,
+            via: link.via,
+            tags: link.tags,
+            notes: link.notes,
+            found_at: link.found_at,
+            read_at: link.read_at,
+            published_at: link.published_at,
+            from_filename: link.from_filename,
+            image: link.image,
+            src: link.src.map(|src| match String::from_utf8(src) {
+                Ok(text) => SourceFixture::Text(text),
+                Err(e) => SourceFixture::Bytes(e.into_bytes()),
+            }),
+            src_truncated: link.src_truncated,
+            meta: link.meta,
+            last_fetched: link.last_fetched,
+            last_processed: link.last_processed,
+            http_headers: link.http_headers,
+            hidden: link.hidden,
+            summary: link.summary,
+            summarized_at: link.summarized_at,
+            digested_at: link.digested_at,
+        }
+    }
+}
+
+impl From<LinkFixture> for Link {
+    fn from(fixture: LinkFixture) -> Self {
+        Self {
+            url: fixture.url,
+            title: fixture.title,
+            via: fixture.via,
+            tags: fixture.tags,
+            notes: fixture.notes,
+            found_at: fixture.found_at,
+            read_at: fixture.read_at,
+            published_at: fixture.published_at,
+            from_filename: fixture.from_filename,
+            image: fixture.image,
+            src: fixture.src.map(|src| match src {
+                SourceFixture::Text(text) => text.into_bytes(),
+                SourceFixture::Bytes(bytes) => bytes,
+            }),
+            src_truncated: fixture.src_truncated,
+            meta: fixture.meta,
+            last_fetched: fixture.last_fetched,
+            last_processed: fixture.last_processed,
+            http_headers: fixture.http_headers,
+            hidden: fixture.hidden,
+            summary: fixture.summary,
+            summarized_at: fixture.summarized_at,
+            digested_at: fixture.digested_at,
+            ..Default::default()
+        }
+    }
+}
+
+/// The JSON fixture format: a list of links and a map of tag aliases to tags, both optional.
+#[derive(Default, Serialize, Deserialize)]
+struct Fixture {
+    #[serde(default)]
+    links: Vec<LinkFixture>,
+    #[serde(default)]
+    tag_aliases: BTreeMap<String, String>,
+}
+
+/// Brings a link in line with what a database store would hand back after writing it.
+fn normalize(mut link: Link) -> Link {
+    for date in [
+        &mut link.found_at,
+        &mut link.read_at,
+        &mut link.published_at,
+        &mut link.last_fetched,
+        &mut link.last_processed,
+        &mut link.summarized_at,
+        &mut link.digested_at,
+    ] {
+        *date = date.map(|date| date.trunc_subsecs(3));
+    }
+
+    let mut seen = BTreeSet::new();
+    link.tags
+        .retain(|tag| !tag.is_empty() && seen.insert(tag.clone()));
+    link.extracted_text = None;
+    link
 }
 
 impl InMemoryStore {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             ..Default::default()
         }
     }
+
+    /// Create a store holding the given links.
+    pub fn with_links(links: impl IntoIterator<Item = Link>) -> Self {
+        let data = links
+            .into_iter()
+            .map(normalize)
+            .map(|link| (link.url.clone(), link))
+            .collect();
+
+        Self {
+            data: Mutex::new(data),
+            ..Default::default()
+        }
+    }
+
+    /// Parse a store from a JSON fixture.
+    pub fn from_json(json: &str) -> eyre::Result<Self> {
+        let fixture: Fixture = serde_json::from_str(json)?;
+        let mut store = Self::with_links(fixture.links.into_iter().map(Link::from));
+        *store.aliases.get_mut() = fixture
+            .tag_aliases
+            .into_iter()
+            .map(|(alias, tag)| (alias.to_lowercase(), tag))
+            .collect();
+
+        Ok(store)
+    }
+
+    /// Serialize the store's links and tag aliases as a JSON fixture. Links are ordered by url.
+    pub async fn to_json(&self) -> eyre::Result<String> {
+        let fixture = Fixture {
+            links: self.links().await.into_iter().map(Into::into).collect(),
+            tag_aliases: self.aliases.lock().await.clone(),
+        };
+
+        Ok(serde_json::to_string_pretty(&fixture)?)
+    }
+
+    /// Load a store from a JSON fixture file.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Write the store out to a JSON fixture file.
+    pub async fn dump(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        std::fs::write(path, self.to_json().await?)?;
+        Ok(())
+    }
+
+    /// All stored links, including their source data, ordered by url.
+    pub async fn links(&self) -> Vec<Link> {
+        self.data.lock().await.values().cloned().collect()
+    }
 }
 
 #[async_trait::async_trait]
@@ -33,7 +246,30 @@ impl LinkReader for InMemoryStore {
         // This "collect()" seems to be doing something for us, since implementing clippy's suggestion
         // nets us an E0597 lifetime error.
         #[allow(clippy::needless_collect)]
-        let values: Vec<_> = data.values().cloned().collect();
+        let values: Vec<_> = data
+            .values()
+            .cloned()
+            .map(|mut link| {
+                link.src = None;
+                link
+            })
+            .collect();
+
+        Ok(Box::pin(stream::iter(values.into_iter())))
+    }
+
+    async fn glob<'a, 'b: 'a>(
+        &'a self,
+        pattern: &'b str,
+    ) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a>>> {
+        let m = wildmatch::WildMatch::new(pattern);
+        let data = self.data.lock().await;
+
+        let values: Vec<_> = data
+            .values()
+            .filter(|link| m.matches(link.url.as_str()))
+            .cloned()
+            .collect();
 
         Ok(Box::pin(stream::iter(values.into_iter())))
     }
@@ -43,7 +279,352 @@ impl LinkReader for InMemoryStore {
 impl LinkWriter for InMemoryStore {
     async fn write(&self, link: Link) -> eyre::Result<bool> {
         let mut data = self.data.lock().await;
-        data.insert(link.url.clone(), link);
+        data.insert(link.url.clone(), normalize(link));
         Ok(true)
     }
 }
+
+fn matches(params: &ListParams, link: &Link) -> bool {
+    params
+        .query
+        .as_ref()
+        .is_none_or(|query| query.matches(link))
+        && params
+            .tag
+            .as_ref()
+            .is_none_or(|tag| tag.matches(link.tags()))
+        && params.hidden.is_none_or(|hidden| link.hidden() == hidden)
+}
+
+fn date_value(date: Option<DateTime<Utc>>) -> serde_json::Value {
+    date.map(|date| date.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        .into()
+}
+
+/// Memory cursors hold the last link's url and sort value, which is all [`crate::Sort::compare`]
+/// looks at. The link itself may have changed or gone since, so a stand-in is rebuilt from them.
+fn cursor_for(params: &ListParams, link: &Link) -> Cursor {
+    let value = match params.sort.key {
+        SortKey::Found => date_value(link.found_at()),
+        SortKey::Read => date_value(link.read_at()),
+        SortKey::Published => date_value(link.published_at()),
+        SortKey::Fetched => date_value(link.last_fetched()),
+        SortKey::Title => link.title().into(),
+        SortKey::Host => serde_json::Value::Null,
+    };
+
+    Cursor {
+        sort: params.sort,
+        value: serde_json::json!([link.url(), value]),
+        id: 0,
+    }
+}
+
+fn link_for(cursor: &Cursor) -> eyre::Result<Link> {
+    let invalid = || eyre::eyre!("invalid cursor");
+    let (url, value): (String, serde_json::Value) =
+        serde_json::from_value(cursor.value.clone()).map_err(|_| invalid())?;
+
+    let date = || -> eyre::Result<Option<DateTime<Utc>>> {
+        value
+            .as_str()
+            .map(|date| DateTime::parse_from_rfc3339(date).map(|date| date.with_timezone(&Utc)))
+            .transpose()
+            .map_err(|_| invalid())
+    };
+
+    let mut link = Link {
+        url,
+        ..Default::default()
+    };
+    match cursor.sort.key {
+        SortKey::Found => link.found_at = date()?,
+        SortKey::Read => link.read_at = date()?,
+        SortKey::Published => link.published_at = date()?,
+        SortKey::Fetched => link.last_fetched = date()?,
+        SortKey::Title => link.title = value.as_str().map(str::to_string),
+        SortKey::Host => {}
+    }
+    Ok(link)
+}
+
+#[async_trait::async_trait]
+impl LinkQuery for InMemoryStore {
+    async fn count(&self, params: &ListParams) -> eyre::Result<i64> {
+        let data = self.data.lock().await;
+
+        Ok(data.values().filter(|link| matches(params, link)).count() as i64)
+    }
+
+    async fn list(&self, params: &ListParams) -> eyre::Result<ListPage> {
+        params.check_cursor()?;
+        let after = params.cursor.as_ref().map(link_for).transpose()?;
+
+        let data = self.data.lock().await;
+        let mut links: Vec<_> = data
+            .values()
+            .filter(|link| matches(params, link))
+            .filter(|link| {
+                after
+                    .as_ref()
+                    .is_none_or(|after| params.sort.compare(link, after).is_gt())
+            })
+            .cloned()
+            .collect();
+        drop(data);
+
+        links.sort_by(|lhs, rhs| params.sort.compare(lhs, rhs));
+        let mut links: Vec<_> = links
+            .into_iter()
+            .skip(params.effective_offset().max(0) as usize)
+            .map(|mut link| {
+                link.src = None;
+                link
+            })
+            .collect();
+
+        let has_more = links.len() as i64 > params.limit;
+        links.truncate(params.limit.max(0) as usize);
+        let next = links
+            .last()
+            .filter(|_| has_more)
+            .map(|link| cursor_for(params, link));
+
+        Ok(ListPage { links, next })
+    }
+
+    async fn all_tags(&self, query: Option<&Query>) -> eyre::Result<Vec<String>> {
+        let data = self.data.lock().await;
+
+        let mut tags = BTreeSet::new();
+        for link in data.values() {
+            if !query.is_none_or(|query| query.matches(link)) {
+                continue;
+            }
+
+            for tag in link.tags().iter().filter(|tag| !tag.is_empty()) {
+                tags.extend(tag_ancestors(tag).map(str::to_string));
+                tags.insert(tag.clone());
+            }
+        }
+        Ok(tags.into_iter().collect())
+    }
+
+    async fn apply_tag_operation(&self, op: &TagOperation) -> eyre::Result<u64> {
+        let mut data = self.data.lock().await;
+        let mut aliases = self.aliases.lock().await;
+
+        let mut changed = 0;
+        for link in data.values_mut() {
+            if let Some(tags) = op.apply(link.tags()) {
+                link.tags = tags;
+                changed += 1;
+            }
+        }
+
+        // Deleting a tag without dropping it leaves aliases to it in place, for future imports.
+        if op.affects_imports() {
+            for tag in aliases.values_mut() {
+                if let Some(tags) = op.apply(std::slice::from_ref(tag)) {
+                    *tag = tags.into_iter().next().unwrap_or_default();
+                }
+            }
+        }
+
+        for (alias, tag) in op.aliases() {
+            aliases.insert(alias.to_lowercase(), tag);
+        }
+
+        Ok(changed)
+    }
+
+    async fn tag_aliases(&self) -> eyre::Result<TagAliases> {
+        let aliases = self.aliases.lock().await;
+
+        Ok(TagAliases::new(aliases.clone()))
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str) -> eyre::Result<()> {
+        let mut aliases = self.aliases.lock().await;
+        aliases.insert(alias.to_lowercase(), tag.to_string());
+        Ok(())
+    }
+
+    async fn remove_tag_alias(&self, alias: &str) -> eyre::Result<bool> {
+        let mut aliases = self.aliases.lock().await;
+
+        Ok(aliases.remove(&alias.to_lowercase()).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Sort, SqliteStore};
+    use chrono::TimeZone;
+    use futures::StreamExt;
+
+    fn links() -> Vec<Link> {
+        let mut links: Vec<_> = (0..24i64)
+            .map(|idx| {
+                let mut link = Link::new(
+                    format!(
+                        "https://{}.example.com/{idx:02}",
+                        ["www", "docs", "blog"][idx as usize % 3]
+                    ),
+                    format!("link {:02}", (idx * 7) % 24),
+                );
+                *link.tags_mut() = match idx % 4 {
+                    0 => vec!["lang/rust".to_string(), "async".to_string()],
+                    1 => vec!["lang/go".to_string(), "".to_string(), "lang/go".to_string()],
+                    2 => vec!["draft".to_string(), "lang".to_string()],
+                    _ => vec![],
+                };
+                *link.found_at_mut() = Utc.timestamp_opt(idx * 86_400, 123_456_789).latest();
+                if idx % 3 == 0 {
+                    *link.read_at_mut() = Utc.timestamp_millis_opt(idx * 3_600_000).latest();
+                }
+                if idx % 5 == 0 {
+                    *link.via_mut() = Some(Via::Friend(format!("friend {idx}")));
+                }
+                *link.hidden_mut() = idx % 7 == 0;
+                link.src = Some(format!("<p>link {idx}</p>").into_bytes());
+                link
+            })
+            .collect();
+
+        // SQLite breaks sort ties by insertion order, and this store by url; writing in url
+        // order makes those agree.
+        links.sort_by(|lhs, rhs| lhs.url.cmp(&rhs.url));
+        links
+    }
+
+    async fn list_all(store: &impl LinkQuery, params: &ListParams) -> eyre::Result<Vec<String>> {
+        let mut urls = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = store
+                .list(&ListParams {
+                    query: params.query.clone(),
+                    tag: params.tag.clone(),
+                    hidden: params.hidden,
+                    sort: params.sort,
+                    cursor,
+                    offset: 0,
+                    limit: params.limit,
+                })
+                .await?;
+            assert!(page.links.iter().all(|link| link.src.is_none()));
+            urls.extend(page.links.into_iter().map(|link| link.url));
+            let Some(next) = page.next else {
+                return Ok(urls);
+            };
+            cursor = Some(next);
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_behaves_like_sqlite() -> eyre::Result<()> {
+        let memory = InMemoryStore::new();
+        let sqlite = SqliteStore::with_connection_string("sqlite::memory:").await?;
+        for link in links() {
+            memory.write(link.clone()).await?;
+            sqlite.write(link).await?;
+        }
+
+        for url in ["https://docs.example.com/01", "https://www.example.com/00"] {
+            let lhs = memory.get(url).await?.expect("link was written");
+            let rhs = sqlite.get(url).await?.expect("link was written");
+            assert_eq!(lhs.tags(), rhs.tags());
+            assert_eq!(lhs.found_at(), rhs.found_at());
+        }
+
+        let values: Vec<_> = memory.values().await?.collect().await;
+        assert_eq!(values.len(), 24);
+        assert!(values.iter().all(|link| link.src.is_none()));
+
+        for pattern in [
+            "https://docs.*",
+            "*/1?",
+            "*example.com/2*",
+            "https://DOCS.*",
+        ] {
+            let lhs: Vec<_> = memory.glob(pattern).await?.collect().await;
+            let mut rhs: Vec<_> = sqlite.glob(pattern).await?.map(|xs| xs.url).collect().await;
+            rhs.sort();
+            assert_eq!(
+                lhs.iter().map(|xs| xs.url.as_str()).collect::<Vec<_>>(),
+                rhs,
+                "{pattern}"
+            );
+            assert!(lhs.iter().all(|link| link.src.is_some()));
+        }
+
+        let cases = [
+            ("", "found", None),
+            ("tag:lang -tag:lang/go", "title", None),
+            ("host:example.com read:yes", "host", Some(false)),
+            ("via:friend has:via", "read:asc", Some(true)),
+            ("url:https://blog.* -hidden:yes", "published", None),
+            ("\"LINK 1\" found:>=1970-01-05", "title:desc", None),
+        ];
+        for (query, sort, hidden) in cases {
+            let params = ListParams {
+                query: Some(query.parse()?),
+                tag: Some("lang,-draft".parse()?).filter(|_| query.is_empty()),
+                hidden,
+                sort: sort.parse::<Sort>()?,
+                cursor: None,
+                offset: 0,
+                limit: 5,
+            };
+
+            assert_eq!(
+                memory.count(&params).await?,
+                sqlite.count(&params).await?,
+                "{query:?}"
+            );
+            assert_eq!(
+                list_all(&memory, &params).await?,
+                list_all(&sqlite, &params).await?,
+                "{query:?} sorted by {sort}"
+            );
+
+            let query = params.query.as_ref();
+            assert_eq!(memory.all_tags(query).await?, sqlite.all_tags(query).await?);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn round_trips_json_fixtures() -> eyre::Result<()> {
+        let store = InMemoryStore::with_links(links());
+        store.set_tag_alias("Golang", "lang/go").await?;
+        let mut binary = Link::new("https://example.com/a.pdf", "a pdf");
+        binary.src = Some(vec![0x25, 0x50, 0xff, 0x00]);
+        store.write(binary).await?;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("fixture.json");
+        store.dump(&path).await?;
+        let loaded = InMemoryStore::load(&path)?;
+
+        assert_eq!(loaded.to_json().await?, store.to_json().await?);
+        let link = loaded.get("https://example.com/a.pdf").await?;
+        assert_eq!(
+            link.and_then(|link| link.src),
+            Some(vec![0x25, 0x50, 0xff, 0x00])
+        );
+        assert_eq!(
+            loaded.tag_aliases().await?.normalize("golang").as_deref(),
+            Some("lang/go")
+        );
+
+        let empty = InMemoryStore::from_json("{}")?;
+        assert_eq!(empty.values().await?.count().await, 0);
+        assert!(InMemoryStore::from_json(r#"{"links": [{"title": "no url"}]}"#).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use chrono::{DateTime, Utc};
+use include_dir::Dir;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// A schema migration, loaded from `NNNN-name.sql` in a store's migrations directory. If there is
+/// a matching `NNNN-name.down.sql`, the migration may be rolled back.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub(crate) up: &'static str,
+    pub(crate) down: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Pending,
+    Applied,
+    /// Applied, but the migration file has changed since.
+    Modified,
+    /// Applied by a newer version of likelike.
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+pub(crate) struct AppliedMigration {
+    pub(crate) version: i64,
+    pub(crate) name: String,
+    pub(crate) checksum: String,
+    pub(crate) applied_at: Option<DateTime<Utc>>,
+}
+
+fn checksum(contents: &str) -> String {
+    format!("{:x}", Sha256::digest(contents.as_bytes()))
+}
+
+/// Load the migrations in `dir`, ordered by version. Subdirectories are ignored.
+pub(crate) fn load(dir: &'static Dir<'static>) -> eyre::Result<Vec<Migration>> {
+    let mut ups = Vec::new();
+    let mut downs = HashMap::new();
+
+    for file in dir.files() {
+        let path = file.path();
+        let Some(filename) = path.file_name().and_then(|xs| xs.to_str()) else {
+            continue;
+        };
+        let contents = file
+            .contents_utf8()
+            .ok_or_else(|| eyre::eyre!("migration {} is not valid utf-8", filename))?;
+
+        if let Some(stem) = filename.strip_suffix(".down.sql") {
+            downs.insert(stem.to_string(), contents);
+        } else if let Some(stem) = filename.strip_suffix(".sql") {
+            ups.push((stem.to_string(), contents));
+        }
+    }
+
+    let mut migrations = ups
+        .into_iter()
+        .map(|(stem, up)| {
+            let (version, name) = stem
+                .split_once('-')
+                .and_then(|(version, name)| Some((version.parse::<i64>().ok()?, name)))
+                .ok_or_else(|| {
+                    eyre::eyre!(
+                        "migration {:?} must be named like 0001-description.sql",
+                        stem
+                    )
+                })?;
+
+            Ok(Migration {
+                version,
+                name: name.to_string(),
+                checksum: checksum(up),
+                up,
+                down: downs.remove(&stem),
+            })
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    if let Some(stem) = downs.keys().next() {
+        return Err(eyre::eyre!(
+            "down migration {:?} has no matching migration",
+            stem
+        ));
+    }
+
+    migrations.sort_by_key(|migration| migration.version);
+    for pair in migrations.windows(2) {
+        if pair[0].version == pair[1].version {
+            return Err(eyre::eyre!(
+                "migrations {:?} and {:?} share version {}",
+                pair[0].name,
+                pair[1].name,
+                pair[0].version
+            ));
+        }
+    }
+
+    Ok(migrations)
+}
+
+pub(crate) fn check(known: &[Migration], applied: &[AppliedMigration]) -> eyre::Result<()> {
+    for migration in applied {
+        let Some(expected) = known.iter().find(|xs| xs.version == migration.version) else {
+            return Err(eyre::eyre!(
+                "this database was migrated by a newer version of likelike (unknown migration {:04}-{})",
+                migration.version,
+                migration.name
+            ));
+        };
+
+        if expected.checksum != migration.checksum {
+            return Err(eyre::eyre!(
+                "migration {:04}-{} has changed since it was applied to this database",
+                migration.version,
+                migration.name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Describe each known migration and each applied migration this version doesn't know about.
+pub(crate) fn statuses(known: &[Migration], applied: &[AppliedMigration]) -> Vec<MigrationStatus> {
+    let mut statuses: Vec<_> = known
+        .iter()
+        .map(|migration| {
+            let record = applied.iter().find(|xs| xs.version == migration.version);
+            MigrationStatus {
+                version: migration.version,
+                name: migration.name.clone(),
+                state: match record {
+                    None => MigrationState::Pending,
+                    Some(record) if record.checksum != migration.checksum => {
+                        MigrationState::Modified
+                    }
+                    Some(_) => MigrationState::Applied,
+                },
+                applied_at: record.and_then(|xs| xs.applied_at),
+            }
+        })
+        .collect();
+
+    statuses.extend(
+        applied
+            .iter()
+            .filter(|record| known.iter().all(|xs| xs.version != record.version))
+            .map(|record| MigrationStatus {
+                version: record.version,
+                name: record.name.clone(),
+                state: MigrationState::Unknown,
+                applied_at: record.applied_at,
+            }),
+    );
+    statuses.sort_by_key(|xs| xs.version);
+
+    statuses
+}
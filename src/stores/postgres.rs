@@ -0,0 +1,711 @@
+use async_stream::stream;
+use chrono::{TimeZone, Utc};
+use futures::Stream;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions, PgRow},
+    PgConnection, PgPool, Row,
+};
+use std::{fmt::Debug, pin::Pin, str::FromStr};
+
+use super::sql::{Cursor, Dialect, ListPage, ListParams};
+use crate::{
+    src_key, tag_ancestors, BlobStore, Compaction, Link, LinkQuery, LinkReader, LinkWriter,
+    Migration, MigrationStatus, Query, SortKey, TagAliases, TagOperation,
+};
+
+mod migrations;
+
+const DIALECT: Dialect = Dialect::Postgres;
+
+/// Selects a link's tags, in the order they were written, as an array.
+const TAGS_COLUMN: &str = r#"array(
+    SELECT "tags".name FROM "link_tags"
+    JOIN "tags" ON "tags".id = "link_tags".tag_id
+    WHERE "link_tags".link_id = "links".id
+    ORDER BY "link_tags".position
+) AS tags"#;
+
+/// The columns read into a [`Link`]. Source data is not kept in the database (see
+/// [`crate::ExternalWrap`]), though databases written by older versions may still hold some
+/// in `src` until they are compacted.
+const LINK_COLUMNS: &str = r#"url, title, via, notes, found_at, read_at, published_at,
+    from_filename, image, meta, last_fetched, last_processed, http_headers, hidden, summary,
+    summarized_at, src_truncated, digested_at"#;
+
+/// A store backed by a Postgres database, for sharing one set of links between several people or
+/// machines. The schema mirrors [`crate::SqliteStore`]'s and is managed by its own migrations.
+#[derive(Debug, Clone)]
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn with_connection_string(s: impl AsRef<str>) -> eyre::Result<Self> {
+        Self::with_connection_options(PgConnectOptions::from_str(s.as_ref())?).await
+    }
+
+    /// Connect to the database and apply any pending migrations.
+    pub async fn with_connection_options(opts: PgConnectOptions) -> eyre::Result<Self> {
+        let store = Self::connect(opts).await?;
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Connect to the database without applying migrations.
+    pub async fn connect(opts: PgConnectOptions) -> eyre::Result<Self> {
+        let pool = PgPoolOptions::new().connect_with(opts).await?;
+        Ok(Self { pool })
+    }
+
+    pub async fn migration_status(&self) -> eyre::Result<Vec<MigrationStatus>> {
+        migrations::status(&self.pool).await
+    }
+
+    /// Apply pending migrations, returning the ones applied.
+    pub async fn migrate(&self) -> eyre::Result<Vec<Migration>> {
+        migrations::migrate(&self.pool).await
+    }
+
+    /// Revert the `steps` most recently applied migrations, returning the ones reverted.
+    pub async fn rollback(&self, steps: usize) -> eyre::Result<Vec<Migration>> {
+        migrations::rollback(&self.pool, steps).await
+    }
+
+    /// Move source data left in the database by older versions out to `blobs`, then VACUUM to
+    /// reclaim the space. Links that already have source data in `blobs` keep it.
+    pub async fn compact(&self, blobs: &(dyn BlobStore + Send + Sync)) -> eyre::Result<Compaction> {
+        let urls: Vec<String> =
+            sqlx::query_scalar(r#"SELECT url FROM "links" WHERE src IS NOT NULL"#)
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut compaction = Compaction::default();
+        for url in urls {
+            let src: Option<Vec<u8>> =
+                sqlx::query_scalar(r#"SELECT src FROM "links" WHERE url = $1"#)
+                    .bind(&url)
+                    .fetch_one(&self.pool)
+                    .await?;
+            let Some(src) = src else { continue };
+
+            // Source data that doesn't decompress could never be read back, so it is dropped.
+            if let Ok(decoded) = zstd::decode_all(src.as_slice()) {
+                let key = src_key(&url);
+                if blobs.read(&key).await.ok().flatten().is_none() {
+                    blobs.write(&key, &decoded).await?;
+                }
+            }
+
+            sqlx::query(r#"UPDATE "links" SET src = NULL WHERE url = $1"#)
+                .bind(&url)
+                .execute(&self.pool)
+                .await?;
+            compaction.moved += 1;
+            compaction.bytes += src.len() as u64;
+        }
+
+        sqlx::query(r#"VACUUM "links", "link_tags", "tags""#)
+            .execute(&self.pool)
+            .await?;
+        Ok(compaction)
+    }
+}
+
+fn timestamp(row: &PgRow, column: &str) -> Option<chrono::DateTime<Utc>> {
+    row.get::<Option<i64>, _>(column)
+        .and_then(|ts| Utc.timestamp_millis_opt(ts).latest())
+}
+
+/// Reads a link from a row selecting [`LINK_COLUMNS`] and the tags column.
+fn link_from_row(row: &PgRow) -> Link {
+    Link {
+        url: row.get("url"),
+        title: row.get("title"),
+        tags: row.get("tags"),
+        via: row
+            .get::<Option<String>, _>("via")
+            .and_then(|v| serde_json::from_str(&v).ok()),
+        notes: row.get("notes"),
+        found_at: timestamp(row, "found_at"),
+        read_at: timestamp(row, "read_at"),
+        published_at: timestamp(row, "published_at"),
+        from_filename: row.get("from_filename"),
+        image: row.get("image"),
+        meta: row
+            .get::<Option<String>, _>("meta")
+            .and_then(|m| serde_json::from_str(&m).ok()),
+        last_fetched: timestamp(row, "last_fetched"),
+        last_processed: timestamp(row, "last_processed"),
+        http_headers: row
+            .get::<Option<Vec<u8>>, _>("http_headers")
+            .and_then(|src| zstd::decode_all(src.as_slice()).ok())
+            .and_then(|src| serde_json::from_slice(src.as_slice()).ok()),
+        hidden: row.get("hidden"),
+        summary: row.get("summary"),
+        summarized_at: timestamp(row, "summarized_at"),
+        src_truncated: row.get("src_truncated"),
+        digested_at: timestamp(row, "digested_at"),
+        ..Default::default()
+    }
+}
+
+/// Replaces the tags associated with a link. Empty tags are dropped.
+async fn replace_link_tags(
+    conn: &mut PgConnection,
+    link_id: i64,
+    tags: &[String],
+) -> eyre::Result<()> {
+    sqlx::query(r#"DELETE FROM "link_tags" WHERE link_id = $1"#)
+        .bind(link_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for (position, tag) in tags.iter().filter(|tag| !tag.is_empty()).enumerate() {
+        sqlx::query(r#"INSERT INTO "tags" (name) VALUES ($1) ON CONFLICT (name) DO NOTHING"#)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            r#"INSERT INTO "link_tags" (link_id, tag_id, position)
+               SELECT $1, id, $3 FROM "tags" WHERE name = $2
+               ON CONFLICT DO NOTHING"#,
+        )
+        .bind(link_id)
+        .bind(tag)
+        .bind(position as i32)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl LinkQuery for PostgresStore {
+    async fn count(&self, params: &ListParams) -> eyre::Result<i64> {
+        let mut sql = String::from(r#"SELECT COUNT(*) FROM "links" WHERE 1=1"#);
+        let mut binds = Vec::new();
+        DIALECT.push_list_filters(&mut sql, &mut binds, params);
+
+        let sql = DIALECT.placeholders(&sql);
+        let mut q = sqlx::query_scalar::<_, i64>(&sql);
+        for bind in binds {
+            q = q.bind(bind);
+        }
+
+        Ok(q.fetch_one(&self.pool).await?)
+    }
+
+    async fn list(&self, params: &ListParams) -> eyre::Result<ListPage> {
+        params.check_cursor()?;
+
+        let expr = DIALECT.sort_expression(params.sort.key);
+        let mut sql = format!(
+            r#"SELECT id, {expr} AS sort_value, {LINK_COLUMNS}, {TAGS_COLUMN}
+               FROM "links" WHERE 1=1"#,
+        );
+        let mut binds = Vec::new();
+        DIALECT.push_list_filters(&mut sql, &mut binds, params);
+        if let Some(ref cursor) = params.cursor {
+            DIALECT.push_cursor(&mut sql, &mut binds, cursor);
+        }
+
+        let direction = params.direction();
+        sql.push_str(&format!(
+            " ORDER BY sort_value {direction} NULLS LAST, id {direction} LIMIT ? OFFSET ?"
+        ));
+
+        let sql = DIALECT.placeholders(&sql);
+        let mut q = sqlx::query(&sql);
+        for bind in binds {
+            q = q.bind(bind);
+        }
+        // Fetch one extra row to learn whether there is a next page.
+        q = q.bind(params.limit + 1).bind(params.effective_offset());
+
+        let mut rows = q.fetch_all(&self.pool).await?;
+        let has_more = rows.len() as i64 > params.limit;
+        rows.truncate(params.limit.max(0) as usize);
+
+        let next = rows.last().filter(|_| has_more).map(|row| {
+            let value = match params.sort.key {
+                SortKey::Title | SortKey::Host => row
+                    .get::<Option<String>, _>("sort_value")
+                    .map(serde_json::Value::from),
+                _ => row
+                    .get::<Option<i64>, _>("sort_value")
+                    .map(serde_json::Value::from),
+            };
+
+            Cursor {
+                sort: params.sort,
+                value: value.unwrap_or_default(),
+                id: row.get("id"),
+            }
+        });
+
+        let links = rows.iter().map(link_from_row).collect();
+        Ok(ListPage { links, next })
+    }
+
+    async fn all_tags(&self, query: Option<&Query>) -> eyre::Result<Vec<String>> {
+        let mut sql = String::from(
+            r#"SELECT name FROM "tags"
+               WHERE EXISTS (
+                   SELECT 1 FROM "link_tags" JOIN "links" ON "links".id = "link_tags".link_id
+                   WHERE "link_tags".tag_id = "tags".id"#,
+        );
+        let mut binds = Vec::new();
+        if let Some(query) = query {
+            DIALECT.push_query(&mut sql, &mut binds, query);
+        }
+        sql.push(')');
+
+        let sql = DIALECT.placeholders(&sql);
+        let mut q = sqlx::query_scalar::<_, String>(&sql);
+        for bind in binds {
+            q = q.bind(bind);
+        }
+
+        let mut tags = std::collections::BTreeSet::new();
+        for tag in q.fetch_all(&self.pool).await? {
+            tags.extend(tag_ancestors(&tag).map(str::to_string));
+            tags.insert(tag);
+        }
+        Ok(tags.into_iter().collect())
+    }
+
+    // Links are locked for the duration, so that concurrent writers can't slip in tags the
+    // operation would have rewritten.
+    async fn apply_tag_operation(&self, op: &TagOperation) -> eyre::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(&format!(
+            r#"SELECT id, {TAGS_COLUMN} FROM "links" FOR UPDATE"#
+        ))
+        .fetch_all(&mut tx)
+        .await?;
+
+        let mut changed = 0;
+        for row in rows {
+            let tags: Vec<String> = row.get("tags");
+            let Some(tags) = op.apply(&tags) else {
+                continue;
+            };
+
+            replace_link_tags(&mut tx, row.get("id"), &tags).await?;
+            changed += 1;
+        }
+
+        sqlx::query(
+            r#"DELETE FROM "tags"
+               WHERE NOT EXISTS (SELECT 1 FROM "link_tags" WHERE tag_id = "tags".id)"#,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        // Deleting a tag without dropping it leaves aliases to it in place, for future imports.
+        if op.affects_imports() {
+            let aliases = sqlx::query(r#"SELECT alias, tag FROM "tag_aliases" FOR UPDATE"#)
+                .fetch_all(&mut tx)
+                .await?;
+            for row in aliases {
+                let tag: String = row.get("tag");
+                let Some(tags) = op.apply(&[tag]) else {
+                    continue;
+                };
+
+                sqlx::query(r#"UPDATE "tag_aliases" SET tag = $1 WHERE alias = $2"#)
+                    .bind(tags.into_iter().next().unwrap_or_default())
+                    .bind(row.get::<String, _>("alias"))
+                    .execute(&mut tx)
+                    .await?;
+            }
+        }
+
+        for (alias, tag) in op.aliases() {
+            sqlx::query(
+                r#"INSERT INTO "tag_aliases" (alias, tag) VALUES ($1, $2)
+                   ON CONFLICT (alias) DO UPDATE SET tag = excluded.tag"#,
+            )
+            .bind(alias.to_lowercase())
+            .bind(tag)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(changed)
+    }
+
+    async fn tag_aliases(&self) -> eyre::Result<TagAliases> {
+        let rows = sqlx::query(r#"SELECT alias, tag FROM "tag_aliases""#)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(TagAliases::new(
+            rows.into_iter()
+                .map(|row| (row.get("alias"), row.get("tag"))),
+        ))
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str) -> eyre::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO "tag_aliases" (alias, tag) VALUES ($1, $2)
+               ON CONFLICT (alias) DO UPDATE SET tag = excluded.tag"#,
+        )
+        .bind(alias.to_lowercase())
+        .bind(tag)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_tag_alias(&self, alias: &str) -> eyre::Result<bool> {
+        let result = sqlx::query(r#"DELETE FROM "tag_aliases" WHERE alias = $1"#)
+            .bind(alias.to_lowercase())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkWriter for PostgresStore {
+    async fn write(&self, link: Link) -> eyre::Result<bool> {
+        if link.src.is_some() {
+            return Err(eyre::eyre!(
+                "not writing source data for {} to the database; write it through an ExternalWrap",
+                link.url
+            ));
+        }
+
+        let via = serde_json::to_string(&link.via)?;
+
+        let meta = link
+            .meta
+            .iter()
+            .filter_map(|src| serde_json::to_string(src).ok())
+            .next();
+
+        let http_headers = link
+            .http_headers
+            .iter()
+            .filter_map(|http_headers| serde_json::to_vec(http_headers).ok())
+            .filter_map(|src| zstd::encode_all(src.as_slice(), 0).ok())
+            .next();
+
+        let mut tx = self.pool.begin().await?;
+        let link_id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO "links" (
+                title,
+                via,
+                notes,
+                found_at,
+                read_at,
+                published_at,
+                from_filename,
+                url,
+                image,
+                meta,
+                last_fetched,
+                last_processed,
+                http_headers,
+                hidden,
+                summary,
+                summarized_at,
+                src_truncated,
+                digested_at
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18
+            ) ON CONFLICT (url) DO UPDATE
+                SET title=excluded.title,
+                    via=excluded.via,
+                    notes=excluded.notes,
+                    found_at=excluded.found_at,
+                    read_at=excluded.read_at,
+                    published_at=excluded.published_at,
+                    from_filename=excluded.from_filename,
+                    image=excluded.image,
+                    meta=excluded.meta,
+                    last_fetched=excluded.last_fetched,
+                    last_processed=excluded.last_processed,
+                    http_headers=excluded.http_headers,
+                    hidden=excluded.hidden,
+                    summary=excluded.summary,
+                    summarized_at=excluded.summarized_at,
+                    src_truncated=excluded.src_truncated,
+                    digested_at=excluded.digested_at
+            RETURNING id
+            "#,
+        )
+        .bind(&link.title)
+        .bind(via)
+        .bind(&link.notes)
+        .bind(link.found_at.map(|xs| xs.timestamp_millis()))
+        .bind(link.read_at.map(|xs| xs.timestamp_millis()))
+        .bind(link.published_at.map(|xs| xs.timestamp_millis()))
+        .bind(&link.from_filename)
+        .bind(&link.url)
+        .bind(&link.image)
+        .bind(meta)
+        .bind(link.last_fetched.map(|xs| xs.timestamp_millis()))
+        .bind(link.last_processed.map(|xs| xs.timestamp_millis()))
+        .bind(http_headers)
+        .bind(link.hidden)
+        .bind(&link.summary)
+        .bind(link.summarized_at.map(|xs| xs.timestamp_millis()))
+        .bind(&link.src_truncated)
+        .bind(link.digested_at.map(|xs| xs.timestamp_millis()))
+        .fetch_one(&mut tx)
+        .await?;
+
+        replace_link_tags(&mut tx, link_id, &link.tags).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+}
+
+#[async_trait::async_trait]
+impl LinkReader for PostgresStore {
+    async fn get(&self, link: &str) -> eyre::Result<Option<Link>> {
+        let row = sqlx::query(&format!(
+            r#"SELECT {LINK_COLUMNS}, {TAGS_COLUMN}, src FROM "links" WHERE url = $1"#
+        ))
+        .bind(link)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(|row| {
+            let mut link = link_from_row(row);
+            link.src = row
+                .get::<Option<Vec<u8>>, _>("src")
+                .and_then(|src| zstd::decode_all(src.as_slice()).ok());
+            link
+        }))
+    }
+
+    async fn values<'a>(&'a self) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a + Send>>> {
+        let stream = stream! {
+            // explicitly DO NOT FETCH the source data
+            let sql = format!(
+                r#"SELECT {LINK_COLUMNS}, {TAGS_COLUMN} FROM "links""#
+            );
+            let input = sqlx::query(&sql).fetch(&self.pool);
+
+            for await row in input {
+                let Ok(row) = row else { continue };
+                yield link_from_row(&row)
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn glob<'a, 'b: 'a>(
+        &'a self,
+        pattern: &'b str,
+    ) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a>>> {
+        let stream = stream! {
+            let mut binds = Vec::new();
+            let condition = DIALECT.glob("url", pattern, &mut binds);
+            let sql = DIALECT.placeholders(&format!(
+                r#"SELECT {LINK_COLUMNS}, {TAGS_COLUMN} FROM "links" WHERE {condition}"#
+            ));
+
+            let mut q = sqlx::query(&sql);
+            for bind in binds {
+                q = q.bind(bind);
+            }
+
+            for await row in q.fetch(&self.pool) {
+                let Ok(row) = row else { continue };
+                yield link_from_row(&row)
+            }
+        };
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnyStore, Sort, SqliteStore, Via};
+    use futures::StreamExt;
+    use sqlx::{Connection, Executor};
+
+    /// Creates a scratch database on the server named by `LIKELIKE_TEST_POSTGRES_URL`, which
+    /// `just test-postgres` points at a throwaway local server. The tests that need it are
+    /// ignored by default, and `just test-postgres` runs them.
+    async fn scratch_store() -> eyre::Result<(PostgresStore, String)> {
+        let url = std::env::var("LIKELIKE_TEST_POSTGRES_URL")
+            .map_err(|_| eyre::eyre!("LIKELIKE_TEST_POSTGRES_URL is not set"))?;
+
+        let name = format!("likelike_test_{:016x}", rand::random::<u64>());
+        let mut conn = PgConnection::connect(&url).await?;
+        conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str())
+            .await?;
+
+        let opts = PgConnectOptions::from_str(&url)?.database(&name);
+        let store = PostgresStore::with_connection_options(opts).await?;
+        Ok((store, name))
+    }
+
+    async fn drop_scratch(store: PostgresStore, name: &str) -> eyre::Result<()> {
+        store.pool.close().await;
+        let url = std::env::var("LIKELIKE_TEST_POSTGRES_URL")?;
+        let mut conn = PgConnection::connect(&url).await?;
+        conn.execute(format!(r#"DROP DATABASE "{name}" WITH (FORCE)"#).as_str())
+            .await?;
+        Ok(())
+    }
+
+    fn links() -> Vec<Link> {
+        let mut links = Vec::new();
+        for idx in 0..24i64 {
+            let mut link = Link::new(
+                format!(
+                    "https://{}.example.com/{idx}",
+                    ["www", "docs", "blog"][idx as usize % 3]
+                ),
+                format!("link {:02}", (idx * 7) % 24),
+            );
+            *link.tags_mut() = match idx % 4 {
+                0 => vec!["lang/rust".to_string(), "async".to_string()],
+                1 => vec!["lang/go".to_string()],
+                2 => vec!["draft".to_string(), "lang".to_string()],
+                _ => vec![],
+            };
+            *link.found_at_mut() = Utc.timestamp_millis_opt(idx * 86_400_000).latest();
+            if idx % 3 == 0 {
+                *link.read_at_mut() = Utc.timestamp_millis_opt(idx * 3_600_000).latest();
+            }
+            if idx % 5 == 0 {
+                *link.via_mut() = Some(Via::Friend(format!("friend {idx}")));
+            }
+            *link.hidden_mut() = idx % 7 == 0;
+            links.push(link);
+        }
+        links
+    }
+
+    /// Follows cursors from the first page to the last, collecting urls.
+    async fn list_all(store: &AnyStore, params: &ListParams) -> eyre::Result<Vec<String>> {
+        let mut urls = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = store
+                .list(&ListParams {
+                    query: params.query.clone(),
+                    tag: params.tag.clone(),
+                    hidden: params.hidden,
+                    sort: params.sort,
+                    cursor,
+                    offset: 0,
+                    limit: params.limit,
+                })
+                .await?;
+            urls.extend(page.links.into_iter().map(|link| link.url));
+            let Some(next) = page.next else {
+                return Ok(urls);
+            };
+            cursor = Some(next);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Postgres server; run with `just test-postgres`"]
+    async fn postgres_lists_like_sqlite() -> eyre::Result<()> {
+        let (store, name) = scratch_store().await?;
+        let postgres = AnyStore::Postgres(store.clone());
+        let sqlite =
+            AnyStore::Sqlite(SqliteStore::with_connection_string("sqlite::memory:").await?);
+        for link in links() {
+            postgres.write(link.clone()).await?;
+            sqlite.write(link).await?;
+        }
+
+        let original = &links()[5];
+        let fetched = store.get(original.url()).await?.expect("link was written");
+        assert_eq!(fetched.tags(), original.tags());
+        assert!(matches!(fetched.via(), Some(Via::Friend(xs)) if xs == "friend 5"));
+        assert_eq!(fetched.found_at(), original.found_at());
+        assert_eq!(store.values().await?.count().await, 24);
+        assert_eq!(store.glob("https://docs.*").await?.count().await, 8);
+
+        let cases = [
+            ("", "found", None),
+            ("tag:lang -tag:lang/go", "title", None),
+            ("host:example.com read:yes", "host", Some(false)),
+            ("via:friend has:via", "read:asc", Some(true)),
+            ("url:https://blog.* -hidden:yes", "published", None),
+            ("\"LINK 1\" found:>=1970-01-05", "title:desc", None),
+        ];
+        for (query, sort, hidden) in cases {
+            let params = ListParams {
+                query: Some(query.parse()?),
+                tag: Some("lang,-draft".parse()?).filter(|_| query.is_empty()),
+                hidden,
+                sort: sort.parse::<Sort>()?,
+                cursor: None,
+                offset: 0,
+                limit: 5,
+            };
+
+            let expected = sqlite.count(&params).await?;
+            assert_eq!(postgres.count(&params).await?, expected, "{query:?}");
+
+            let postgres_urls = list_all(&postgres, &params).await?;
+            let sqlite_urls = list_all(&sqlite, &params).await?;
+            assert_eq!(postgres_urls.len() as i64, expected, "{query:?}");
+            assert_eq!(postgres_urls, sqlite_urls, "{query:?} sorted by {sort}");
+
+            let query = params.query.as_ref();
+            assert_eq!(
+                postgres.all_tags(query).await?,
+                sqlite.all_tags(query).await?
+            );
+        }
+
+        drop_scratch(store, &name).await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Postgres server; run with `just test-postgres`"]
+    async fn postgres_applies_tag_operations_and_migrations() -> eyre::Result<()> {
+        let (store, name) = scratch_store().await?;
+        for link in links() {
+            store.write(link).await?;
+        }
+
+        store.set_tag_alias("Golang", "lang/go").await?;
+        let op = TagOperation::Rename {
+            from: "lang/go".to_string(),
+            to: "go".to_string(),
+        };
+        assert_eq!(store.apply_tag_operation(&op).await?, 6);
+        assert_eq!(
+            store.tag_aliases().await?.normalize("golang"),
+            Some("go".to_string())
+        );
+        assert!(!store.all_tags(None).await?.contains(&"lang/go".to_string()));
+        assert!(store.remove_tag_alias("golang").await?);
+
+        let known = migrations::migrations()?.len();
+        assert_eq!(store.rollback(known).await?.len(), known);
+        assert_eq!(store.migrate().await?.len(), known);
+        assert!(store
+            .migration_status()
+            .await?
+            .iter()
+            .all(|xs| xs.state == crate::MigrationState::Applied));
+
+        drop_scratch(store, &name).await
+    }
+}
@@ -0,0 +1,159 @@
+use chrono::{TimeZone, Utc};
+use include_dir::{include_dir, Dir};
+use sqlx::{Executor, PgConnection, PgPool, Row};
+
+use crate::stores::migrations::{
+    check, load, statuses, AppliedMigration, Migration, MigrationStatus,
+};
+
+static MIGRATIONS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/migrations/postgres");
+
+/// Key for the advisory lock held while migrating, so that clients sharing a database don't race
+/// to apply the same migrations. ("likelike" in ASCII.)
+const LOCK_KEY: i64 = 0x6c69_6b65_6c69_6b65;
+
+/// Load the migrations embedded in the binary, ordered by version.
+pub fn migrations() -> eyre::Result<Vec<Migration>> {
+    load(&MIGRATIONS_DIR)
+}
+
+async fn applied(conn: &mut PgConnection) -> eyre::Result<Vec<AppliedMigration>> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass('schema_migrations') IS NOT NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query(
+        "SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| AppliedMigration {
+            version: row.get("version"),
+            name: row.get("name"),
+            checksum: row.get("checksum"),
+            applied_at: Utc.timestamp_millis_opt(row.get("applied_at")).latest(),
+        })
+        .collect())
+}
+
+pub(crate) async fn status(pool: &PgPool) -> eyre::Result<Vec<MigrationStatus>> {
+    let known = migrations()?;
+    let mut conn = pool.acquire().await?;
+    let applied = applied(&mut conn).await?;
+
+    Ok(statuses(&known, &applied))
+}
+
+/// Lock out other migrating clients for the rest of the transaction, then read and validate the
+/// applied migrations.
+async fn prepare(conn: &mut PgConnection) -> eyre::Result<(Vec<Migration>, Vec<AppliedMigration>)> {
+    let known = migrations()?;
+
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        create table if not exists "schema_migrations" (
+          version bigint primary key,
+          name text not null,
+          checksum text not null,
+          applied_at bigint not null
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let applied = applied(conn).await?;
+    check(&known, &applied)?;
+    Ok((known, applied))
+}
+
+/// Apply pending migrations. Postgres runs DDL transactionally, so they are applied together: a
+/// failing migration leaves the database as it was.
+pub(crate) async fn migrate(pool: &PgPool) -> eyre::Result<Vec<Migration>> {
+    let mut tx = pool.begin().await?;
+    let (known, applied) = prepare(&mut tx).await?;
+
+    let mut newly_applied = Vec::new();
+    for migration in known {
+        if applied.iter().any(|xs| xs.version == migration.version) {
+            continue;
+        }
+
+        // Executing the bare string uses the simple query protocol, which allows a migration to
+        // hold several statements.
+        tx.execute(migration.up).await.map_err(|e| {
+            eyre::eyre!(
+                "migration {:04}-{} failed: {}",
+                migration.version,
+                migration.name,
+                e
+            )
+        })?;
+
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, name, checksum, applied_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(migration.version)
+        .bind(&migration.name)
+        .bind(&migration.checksum)
+        .bind(Utc::now().timestamp_millis())
+        .execute(&mut tx)
+        .await?;
+
+        newly_applied.push(migration);
+    }
+
+    tx.commit().await?;
+    Ok(newly_applied)
+}
+
+/// Revert the `steps` most recently applied migrations, newest first, in a single transaction.
+pub(crate) async fn rollback(pool: &PgPool, steps: usize) -> eyre::Result<Vec<Migration>> {
+    let mut tx = pool.begin().await?;
+    let (known, applied) = prepare(&mut tx).await?;
+
+    let mut reverted = Vec::new();
+    for record in applied.iter().rev().take(steps) {
+        let Some(migration) = known.iter().find(|xs| xs.version == record.version) else {
+            continue;
+        };
+
+        let down = migration.down.ok_or_else(|| {
+            eyre::eyre!(
+                "migration {:04}-{} cannot be rolled back",
+                migration.version,
+                migration.name
+            )
+        })?;
+
+        tx.execute(down).await.map_err(|e| {
+            eyre::eyre!(
+                "rolling back migration {:04}-{} failed: {}",
+                migration.version,
+                migration.name,
+                e
+            )
+        })?;
+        sqlx::query("DELETE FROM schema_migrations WHERE version = $1")
+            .bind(migration.version)
+            .execute(&mut tx)
+            .await?;
+
+        reverted.push(migration.clone());
+    }
+
+    tx.commit().await?;
+    Ok(reverted)
+}
@@ -0,0 +1,451 @@
+use std::str::FromStr;
+
+use crate::{
+    DateField, DateFilter, HasField, Link, Predicate, Query, Sort, SortKey, TagFilter, TagPattern,
+};
+
+/// Parameters for paginated link listing. When a cursor is given, `offset` is ignored.
+pub struct ListParams {
+    pub query: Option<Query>,
+    pub tag: Option<TagFilter>,
+    pub hidden: Option<bool>,
+    pub sort: Sort,
+    pub cursor: Option<Cursor>,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+/// A page of links, along with a cursor for the next page if there is one.
+pub struct ListPage {
+    pub links: Vec<Link>,
+    pub next: Option<Cursor>,
+}
+
+/// An opaque position in a sorted listing, pointing just past the last link on a page. Cursors
+/// are only valid for the sort they were produced with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub(crate) sort: Sort,
+    pub(crate) value: serde_json::Value,
+    pub(crate) id: i64,
+}
+
+impl Cursor {
+    pub fn sort(&self) -> Sort {
+        self.sort
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let encoded = serde_json::json!([self.sort.to_string(), self.value, self.id]).to_string();
+        for byte in encoded.bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || eyre::eyre!("invalid cursor");
+        let bytes = s
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .filter(|pair| pair.len() == 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+
+        let (sort, value, id): (String, serde_json::Value, i64) =
+            serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+
+        Ok(Cursor {
+            sort: sort.parse()?,
+            value,
+            id,
+        })
+    }
+}
+
+impl ListParams {
+    /// Lists every link matching `query` and `tag` in the given order, for
+    /// [`LinkQuery::list_all`](crate::LinkQuery::list_all).
+    pub fn all(query: Option<Query>, tag: Option<TagFilter>, sort: Sort) -> Self {
+        Self {
+            query,
+            tag,
+            hidden: None,
+            sort,
+            cursor: None,
+            offset: 0,
+            limit: crate::LIST_PAGE_SIZE,
+        }
+    }
+
+    /// Checks that the cursor, if any, belongs to this listing's sort.
+    pub(crate) fn check_cursor(&self) -> eyre::Result<()> {
+        match self.cursor {
+            Some(ref cursor) if cursor.sort != self.sort => Err(eyre::eyre!(
+                "cursor was created for sort {}, not {}",
+                cursor.sort,
+                self.sort
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn direction(&self) -> &'static str {
+        if self.sort.descending {
+            "DESC"
+        } else {
+            "ASC"
+        }
+    }
+
+    /// The offset to query with; cursors take the place of offsets.
+    pub(crate) fn effective_offset(&self) -> i64 {
+        if self.cursor.is_some() {
+            0
+        } else {
+            self.offset
+        }
+    }
+}
+
+/// Translates a `*`/`?` glob into a `LIKE` pattern, for use with `ESCAPE '\'`.
+fn glob_to_like(glob: &str) -> String {
+    let mut like = String::with_capacity(glob.len());
+    for c in glob.chars() {
+        match c {
+            '*' => like.push('%'),
+            '?' => like.push('_'),
+            '%' | '_' | '\\' => {
+                like.push('\\');
+                like.push(c);
+            }
+            c => like.push(c),
+        }
+    }
+    like
+}
+
+/// The SQL flavors the link stores speak. Filters, sorts and cursors are compiled to SQL with `?`
+/// placeholders and string binds; [`Dialect::placeholders`] adapts the result for databases
+/// that number their parameters.
+///
+/// Both schemas store timestamps as milliseconds since the epoch, so dates are inlined as
+/// integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    Sqlite,
+    Postgres,
+}
+
+impl Dialect {
+    /// Rewrites `?` placeholders into the dialect's form. The generated SQL never contains a
+    /// literal `?`.
+    pub(crate) fn placeholders(self, sql: &str) -> String {
+        match self {
+            Dialect::Sqlite => sql.to_string(),
+            Dialect::Postgres => {
+                let mut out = String::with_capacity(sql.len());
+                let mut idx = 0;
+                for c in sql.chars() {
+                    if c == '?' {
+                        idx += 1;
+                        out.push_str(&format!("${idx}"));
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    /// A SQL expression matching `expr` against a glob, binding the pattern.
+    pub(crate) fn glob(self, expr: &str, glob: &str, binds: &mut Vec<String>) -> String {
+        match self {
+            Dialect::Sqlite => {
+                // GLOB has character classes, which our globs do not.
+                binds.push(glob.replace('[', "[[]"));
+                format!("{expr} GLOB ?")
+            }
+            Dialect::Postgres => {
+                binds.push(glob_to_like(glob));
+                format!(r"{expr} LIKE ? ESCAPE '\'")
+            }
+        }
+    }
+
+    /// A case-insensitive substring match of `expr` against a bound value.
+    fn contains(self, expr: &str) -> String {
+        match self {
+            Dialect::Sqlite => format!("{expr} LIKE '%' || ? || '%'"),
+            Dialect::Postgres => format!("{expr} ILIKE '%' || ? || '%'"),
+        }
+    }
+
+    fn hidden(self, hidden: bool) -> &'static str {
+        match (self, hidden) {
+            (Dialect::Sqlite, true) => "hidden = 1",
+            (Dialect::Sqlite, false) => "coalesce(hidden, 0) = 0",
+            (Dialect::Postgres, true) => "hidden",
+            (Dialect::Postgres, false) => "NOT hidden",
+        }
+    }
+
+    fn falsy(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => "0",
+            Dialect::Postgres => "false",
+        }
+    }
+
+    /// The lowercased host of a link's url, without port or credentials handling.
+    fn host_expression(self) -> String {
+        match self {
+            Dialect::Sqlite => {
+                let rest = "substr(url, instr(url, '://') + 3)";
+                let authority = format!("substr({rest}, 1, instr({rest} || '/', '/') - 1)");
+                format!("lower(substr({authority}, 1, instr({authority} || ':', ':') - 1))")
+            }
+            Dialect::Postgres => "lower(substring(url from '://([^/:]*)'))".to_string(),
+        }
+    }
+
+    /// The text of a link's `via` column, whichever kind of via it is.
+    fn via_expression(self) -> &'static str {
+        match self {
+            Dialect::Sqlite => {
+                r#"(CASE WHEN json_valid(via) THEN coalesce(
+                    json_extract(via, '$.Friend'),
+                    json_extract(via, '$.Link'),
+                    json_extract(via, '$.Freeform')
+                ) END)"#
+            }
+            Dialect::Postgres => {
+                r#"coalesce(via::jsonb ->> 'Friend', via::jsonb ->> 'Link', via::jsonb ->> 'Freeform')"#
+            }
+        }
+    }
+
+    /// The SQL expression a listing is ordered by.
+    pub(crate) fn sort_expression(self, key: SortKey) -> String {
+        match key {
+            SortKey::Found => "found_at".to_string(),
+            SortKey::Read => "read_at".to_string(),
+            SortKey::Published => "published_at".to_string(),
+            SortKey::Title => "lower(title)".to_string(),
+            SortKey::Host => self.host_expression(),
+            SortKey::Fetched => "last_fetched".to_string(),
+        }
+    }
+
+    /// Appends a keyset condition selecting the links that sort after the cursor. Links without
+    /// a sort value come last, in id order.
+    pub(crate) fn push_cursor(self, sql: &mut String, binds: &mut Vec<String>, cursor: &Cursor) {
+        let expr = self.sort_expression(cursor.sort.key);
+        let cmp = if cursor.sort.descending { "<" } else { ">" };
+
+        let value = match cursor.value {
+            serde_json::Value::Null => {
+                sql.push_str(&format!(" AND ({expr}) IS NULL AND id {cmp} {}", cursor.id));
+                return;
+            }
+            serde_json::Value::String(ref value) => {
+                binds.extend([value.clone(), value.clone()]);
+                "?".to_string()
+            }
+            ref value => value.as_i64().unwrap_or_default().to_string(),
+        };
+
+        sql.push_str(&format!(
+            " AND (({expr}) IS NULL OR ({expr}) {cmp} {value} OR (({expr}) = {value} AND id {cmp} {}))",
+            cursor.id
+        ));
+    }
+
+    /// Appends a SQL expression matching a tag name against the pattern, including descendants.
+    fn push_tag_pattern(self, sql: &mut String, binds: &mut Vec<String>, pattern: &TagPattern) {
+        if pattern.is_exact() {
+            sql.push_str(
+                r#"("tags".name = ? OR substr("tags".name, 1, length(?) + 1) = ? || '/')"#,
+            );
+            let tag = pattern.as_str();
+            binds.extend([tag.to_string(), tag.to_string(), tag.to_string()]);
+        } else {
+            let glob = pattern.as_str();
+            let itself = self.glob(r#""tags".name"#, glob, binds);
+            let descendants = self.glob(r#""tags".name"#, &format!("{glob}/*"), binds);
+            sql.push_str(&format!("({itself} OR {descendants})"));
+        }
+    }
+
+    /// Appends an `EXISTS` subquery testing whether a link carries a tag matching any of the
+    /// patterns.
+    fn push_has_any_tag(self, sql: &mut String, binds: &mut Vec<String>, patterns: &[TagPattern]) {
+        sql.push_str(
+            r#"EXISTS (SELECT 1 FROM "link_tags" JOIN "tags" ON "tags".id = "link_tags".tag_id
+               WHERE "link_tags".link_id = "links".id AND ("#,
+        );
+        for (idx, pattern) in patterns.iter().enumerate() {
+            if idx > 0 {
+                sql.push_str(" OR ");
+            }
+            self.push_tag_pattern(sql, binds, pattern);
+        }
+        sql.push_str("))");
+    }
+
+    fn push_tag_filter(self, sql: &mut String, binds: &mut Vec<String>, filter: &TagFilter) {
+        for pattern in filter.all.iter() {
+            sql.push_str(" AND ");
+            self.push_has_any_tag(sql, binds, std::slice::from_ref(pattern));
+        }
+
+        if !filter.any.is_empty() {
+            sql.push_str(" AND ");
+            self.push_has_any_tag(sql, binds, filter.any.as_slice());
+        }
+
+        if !filter.none.is_empty() {
+            sql.push_str(" AND NOT ");
+            self.push_has_any_tag(sql, binds, filter.none.as_slice());
+        }
+    }
+
+    /// Appends a SQL expression for a single query predicate. The expression may evaluate to
+    /// NULL where the corresponding column is NULL; callers coalesce it.
+    fn push_predicate(self, sql: &mut String, binds: &mut Vec<String>, predicate: &Predicate) {
+        match predicate {
+            Predicate::Text(text) => {
+                sql.push_str(&format!(
+                    "({} OR {})",
+                    self.contains("url"),
+                    self.contains("title")
+                ));
+                binds.extend([text.clone(), text.clone()]);
+            }
+            Predicate::Url(pattern) => sql.push_str(&self.glob("url", pattern, binds)),
+            Predicate::Title(text) => {
+                sql.push_str(&self.contains("title"));
+                binds.push(text.clone());
+            }
+            Predicate::Tag(pattern) => {
+                self.push_has_any_tag(sql, binds, std::slice::from_ref(pattern));
+            }
+            Predicate::Host(host) => {
+                let expr = self.host_expression();
+                sql.push_str(&format!(
+                    "({expr} = ? OR substr({expr}, length({expr}) - length(?)) = '.' || ?)"
+                ));
+                binds.extend([host.clone(), host.clone(), host.clone()]);
+            }
+            Predicate::Via(text) => {
+                sql.push_str(&self.contains(self.via_expression()));
+                binds.push(text.clone());
+            }
+            Predicate::Has(field) => sql.push_str(match field {
+                HasField::Title => "coalesce(title, '') != ''",
+                HasField::Notes => "coalesce(notes, '') != ''",
+                HasField::Summary => "coalesce(summary, '') != ''",
+                HasField::Image => "coalesce(image, '') != ''",
+                HasField::Via => "coalesce(via, 'null') != 'null'",
+                HasField::Tags => {
+                    r#"EXISTS (SELECT 1 FROM "link_tags" WHERE "link_tags".link_id = "links".id)"#
+                }
+            }),
+            Predicate::Date(field, filter) => {
+                let column = match field {
+                    DateField::Found => "found_at",
+                    DateField::Read => "read_at",
+                    DateField::Published => "published_at",
+                    DateField::Fetched => "last_fetched",
+                };
+
+                match filter {
+                    DateFilter::Present(true) => sql.push_str(&format!("{column} IS NOT NULL")),
+                    DateFilter::Present(false) => sql.push_str(&format!("{column} IS NULL")),
+                    DateFilter::Range { start, end } => {
+                        sql.push_str(&format!("{column} IS NOT NULL"));
+                        if let Some(start) = start {
+                            sql.push_str(&format!(" AND {column} >= {}", start.timestamp_millis()));
+                        }
+                        if let Some(end) = end {
+                            sql.push_str(&format!(" AND {column} < {}", end.timestamp_millis()));
+                        }
+                    }
+                }
+            }
+            Predicate::Hidden(hidden) => sql.push_str(self.hidden(*hidden)),
+        }
+    }
+
+    /// Appends `WHERE` clauses for every term of the query.
+    pub(crate) fn push_query(self, sql: &mut String, binds: &mut Vec<String>, query: &Query) {
+        for term in query.terms.iter() {
+            sql.push_str(if term.negated {
+                " AND NOT coalesce(("
+            } else {
+                " AND coalesce(("
+            });
+            self.push_predicate(sql, binds, &term.predicate);
+            sql.push_str(&format!("), {})", self.falsy()));
+        }
+    }
+
+    /// Appends the `WHERE` clauses for the given parameters, collecting bind values.
+    pub(crate) fn push_list_filters(
+        self,
+        sql: &mut String,
+        binds: &mut Vec<String>,
+        params: &ListParams,
+    ) {
+        if let Some(ref query) = params.query {
+            self.push_query(sql, binds, query);
+        }
+        if let Some(ref filter) = params.tag {
+            self.push_tag_filter(sql, binds, filter);
+        }
+        if let Some(hidden) = params.hidden {
+            sql.push_str(" AND ");
+            sql.push_str(self.hidden(hidden));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postgres_placeholders_are_numbered() {
+        let mut sql = String::from("SELECT 1 FROM links WHERE 1=1");
+        let mut binds = Vec::new();
+        let filter: TagFilter = "rust,-draft".parse().unwrap();
+        Dialect::Postgres.push_tag_filter(&mut sql, &mut binds, &filter);
+        sql.push_str(" LIMIT ?");
+
+        let sql = Dialect::Postgres.placeholders(&sql);
+        assert!(!sql.contains('?'));
+        assert!(sql.contains(&format!("${}", binds.len() + 1)));
+        assert!(!sql.contains(&format!("${}", binds.len() + 2)));
+    }
+
+    #[test]
+    fn globs_become_like_patterns() {
+        assert_eq!(
+            glob_to_like("https://*.example.com/?"),
+            "https://%.example.com/_"
+        );
+        assert_eq!(glob_to_like(r"100%_\"), r"100\%\_\\");
+    }
+}
@@ -1,151 +1,261 @@
 use async_stream::stream;
 use chrono::{TimeZone, Utc};
 use futures::Stream;
-use include_dir::{include_dir, Dir};
-
-use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Row, SqliteConnection};
-use std::{env, fmt::Debug, pin::Pin, str::FromStr};
-use tokio::sync::Mutex;
-
-use crate::{Link, LinkReader, LinkWriter};
-
-static MIGRATIONS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/migrations");
-
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    Row, SqliteConnection, SqlitePool,
+};
+use std::{env, fmt::Debug, pin::Pin, str::FromStr, time::Duration};
+
+use super::sql::{Cursor, Dialect, ListPage, ListParams};
+use crate::{
+    src_key, tag_ancestors, BlobStore, Compaction, Link, LinkQuery, LinkReader, LinkWriter,
+    Migration, MigrationStatus, Query, SortKey, TagAliases, TagOperation,
+};
+
+mod migrations;
+
+const DIALECT: Dialect = Dialect::Sqlite;
+
+/// How many connections may read concurrently.
+const READER_CONNECTIONS: u32 = 8;
+
+/// How long to wait on another process (e.g. `serve` alongside `import`) holding the write lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A store backed by a SQLite database in WAL mode. Reads go through a pool of connections so
+/// that they can run alongside one another and alongside writes; writes go through a single
+/// connection, since SQLite only admits one writer at a time anyway.
+///
+/// Link source data is not written to the database, and writing a link that carries some is an
+/// error: see [`crate::ExternalWrap`]. Databases from older versions may still hold some, which is
+/// read back until [`SqliteStore::compact`] moves it out.
 #[derive(Debug)]
 pub struct SqliteStore {
-    sqlite: Mutex<SqliteConnection>,
+    reader: SqlitePool,
+    writer: SqlitePool,
 }
 
 impl SqliteStore {
     pub async fn new() -> Self {
-        let env_var = env::var("LIKELIKE_DB");
-
-        Self::with_connection_string(if let Ok(db_url) = env_var {
-            db_url
-        } else {
-            let location = dirs::data_local_dir()
-                .map(|mut xs| {
-                    xs.push("likelike");
-                    std::fs::create_dir_all(&xs).expect("Must be able to create XDG_SHARE_HOME");
-                    xs.push("db.sqlite3");
-                    xs.to_string_lossy().to_string()
-                })
-                .unwrap_or_else(|| ":memory:".to_string());
-
-            format!("sqlite://{}", location)
-        })
-        .await
-        .unwrap()
+        Self::with_connection_string(Self::default_connection_string())
+            .await
+            .unwrap()
+    }
+
+    /// The `LIKELIKE_DB` environment variable if set, otherwise a database in the local data dir.
+    pub fn default_connection_string() -> String {
+        if let Ok(db_url) = env::var("LIKELIKE_DB") {
+            return db_url;
+        }
+
+        let location = dirs::data_local_dir()
+            .map(|mut xs| {
+                xs.push("likelike");
+                std::fs::create_dir_all(&xs).expect("Must be able to create XDG_SHARE_HOME");
+                xs.push("db.sqlite3");
+                xs.to_string_lossy().to_string()
+            })
+            .unwrap_or_else(|| ":memory:".to_string());
+
+        format!("sqlite://{}", location)
     }
 
     pub async fn with_connection_string(s: impl AsRef<str>) -> eyre::Result<Self> {
         Self::with_connection_options(SqliteConnectOptions::from_str(s.as_ref())?).await
     }
 
+    /// Connect to the database and apply any pending migrations.
     pub async fn with_connection_options(opts: SqliteConnectOptions) -> eyre::Result<Self> {
-        let mut sqlite = opts.create_if_missing(true).connect().await?;
+        let store = Self::connect(opts).await?;
+        store.migrate().await?;
+        Ok(store)
+    }
 
-        let mut files: Vec<_> = MIGRATIONS_DIR.files().collect();
-        files.sort_by(|lhs, rhs| lhs.path().cmp(rhs.path()));
-        let version = files.len();
+    /// Connect to the database without applying migrations.
+    pub async fn connect(opts: SqliteConnectOptions) -> eyre::Result<Self> {
+        let opts = opts
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT);
+
+        // The writer connection is never reaped: besides saving on reconnects, this keeps
+        // in-memory databases (which live only as long as some connection to them) alive.
+        let writer = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(opts.clone())
+            .await?;
 
-        let last_index = sqlx::query("select version from database_version limit 1")
-            .fetch_one(&mut sqlite)
-            .await
-            .map(|result| result.get("version"))
-            .unwrap_or_else(|_| 0u32);
+        let reader = SqlitePoolOptions::new()
+            .max_connections(READER_CONNECTIONS)
+            .connect_with(opts)
+            .await?;
+
+        Ok(Self { reader, writer })
+    }
+
+    pub async fn migration_status(&self) -> eyre::Result<Vec<MigrationStatus>> {
+        migrations::status(&self.writer).await
+    }
+
+    /// Apply pending migrations, returning the ones applied.
+    pub async fn migrate(&self) -> eyre::Result<Vec<Migration>> {
+        migrations::migrate(&self.writer).await
+    }
+
+    /// Revert the `steps` most recently applied migrations, returning the ones reverted.
+    pub async fn rollback(&self, steps: usize) -> eyre::Result<Vec<Migration>> {
+        migrations::rollback(&self.writer, steps).await
+    }
 
-        let migration_count = files.len() as u32;
-        for file in files.into_iter().skip(last_index as usize) {
-            sqlx::query(unsafe { std::str::from_utf8_unchecked(file.contents()) })
-                .execute(&mut sqlite)
+    /// Move source data left in the database by older versions out to `blobs`, then VACUUM to
+    /// reclaim the space. Links that already have source data in `blobs` keep it.
+    pub async fn compact(&self, blobs: &(dyn BlobStore + Send + Sync)) -> eyre::Result<Compaction> {
+        let urls: Vec<String> =
+            sqlx::query_scalar(r#"SELECT url FROM "links" WHERE src IS NOT NULL"#)
+                .fetch_all(&self.reader)
                 .await?;
-        }
 
-        if migration_count != last_index {
-            sqlx::query(r#"
-                insert into database_version (id, version) values (0, ?) on conflict(id) do update set version = excluded.version
-            "#).bind(migration_count).execute(&mut sqlite)
+        let mut compaction = Compaction::default();
+        for url in urls {
+            let src: Option<Vec<u8>> =
+                sqlx::query_scalar(r#"SELECT src FROM "links" WHERE url = ?"#)
+                    .bind(&url)
+                    .fetch_one(&self.reader)
+                    .await?;
+            let Some(src) = src else { continue };
+
+            // Source data that doesn't decompress could never be read back, so it is dropped.
+            if let Ok(decoded) = zstd::decode_all(src.as_slice()) {
+                let key = src_key(&url);
+                if blobs.read(&key).await.ok().flatten().is_none() {
+                    blobs.write(&key, &decoded).await?;
+                }
+            }
+
+            sqlx::query(r#"UPDATE "links" SET src = NULL WHERE url = ?"#)
+                .bind(&url)
+                .execute(&self.writer)
                 .await?;
+            compaction.moved += 1;
+            compaction.bytes += src.len() as u64;
         }
 
-        Ok(Self {
-            sqlite: Mutex::new(sqlite),
-        })
+        sqlx::query("VACUUM").execute(&self.writer).await?;
+        Ok(compaction)
     }
 }
 
-/// Parameters for paginated link listing.
-pub struct ListParams {
-    pub query: Option<String>,
-    pub tag: Option<String>,
-    pub hidden: Option<bool>,
-    pub offset: i64,
-    pub limit: i64,
+/// Selects a link's tags, in the order they were written, as a JSON array.
+const TAGS_COLUMN: &str = r#"(
+    SELECT json_group_array(name) FROM (
+        SELECT "tags".name FROM "link_tags"
+        JOIN "tags" ON "tags".id = "link_tags".tag_id
+        WHERE "link_tags".link_id = "links".id
+        ORDER BY "link_tags".rowid
+    )
+) AS tags"#;
+
+/// Replaces the tags associated with a link. Empty tags are dropped.
+async fn replace_link_tags(
+    conn: &mut SqliteConnection,
+    link_id: i64,
+    tags: &[String],
+) -> eyre::Result<()> {
+    sqlx::query(r#"DELETE FROM "link_tags" WHERE link_id = ?"#)
+        .bind(link_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for tag in tags.iter().filter(|tag| !tag.is_empty()) {
+        sqlx::query(r#"INSERT INTO "tags" (name) VALUES (?) ON CONFLICT (name) DO NOTHING"#)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            r#"INSERT INTO "link_tags" (link_id, tag_id)
+               SELECT ?, id FROM "tags" WHERE name = ?
+               ON CONFLICT DO NOTHING"#,
+        )
+        .bind(link_id)
+        .bind(tag)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
 }
 
-impl SqliteStore {
-    /// Counts links matching the given filters.
-    pub async fn count(&self, params: &ListParams) -> eyre::Result<i64> {
-        let mut sqlite = self.sqlite.lock().await;
+#[async_trait::async_trait]
+impl LinkQuery for SqliteStore {
+    async fn count(&self, params: &ListParams) -> eyre::Result<i64> {
         let mut sql = String::from(r#"SELECT COUNT(*) as cnt FROM "links" WHERE 1=1"#);
-        if params.query.is_some() {
-            sql.push_str(r#" AND (url LIKE '%' || ? || '%' OR title LIKE '%' || ? || '%')"#);
-        }
-        if params.tag.is_some() {
-            sql.push_str(r#" AND tags LIKE '%' || ? || '%'"#);
-        }
-        if params.hidden.is_some() {
-            sql.push_str(" AND hidden = ?");
-        }
+        let mut binds = Vec::new();
+        DIALECT.push_list_filters(&mut sql, &mut binds, params);
 
         let mut q = sqlx::query_scalar::<_, i32>(&sql);
-        if let Some(ref query) = params.query {
-            q = q.bind(query).bind(query);
-        }
-        if let Some(ref tag) = params.tag {
-            q = q.bind(tag);
-        }
-        if let Some(hidden) = params.hidden {
-            q = q.bind(if hidden { 1i64 } else { 0i64 });
+        for bind in binds {
+            q = q.bind(bind);
         }
 
-        let count = q.fetch_one(&mut *sqlite).await?;
+        let count = q.fetch_one(&self.reader).await?;
         Ok(count as i64)
     }
 
-    /// Lists links with pagination and optional filters.
-    pub async fn list(&self, params: &ListParams) -> eyre::Result<Vec<Link>> {
-        let mut sqlite = self.sqlite.lock().await;
-        let mut sql = String::from(
-            r#"SELECT url, title, tags, via, notes, found_at, read_at, published_at,
-               from_filename, image, meta, last_fetched, last_processed, hidden
+    async fn list(&self, params: &ListParams) -> eyre::Result<ListPage> {
+        params.check_cursor()?;
+
+        let expr = DIALECT.sort_expression(params.sort.key);
+        let mut sql = format!(
+            r#"SELECT id, {expr} AS sort_value, url, title, {TAGS_COLUMN}, via, notes, found_at,
+               read_at, published_at, from_filename, image, meta, last_fetched, last_processed,
+               hidden, summary, summarized_at, src_truncated, digested_at
                FROM "links" WHERE 1=1"#,
         );
-        if params.query.is_some() {
-            sql.push_str(r#" AND (url LIKE '%' || ? || '%' OR title LIKE '%' || ? || '%')"#);
-        }
-        if params.tag.is_some() {
-            sql.push_str(r#" AND tags LIKE '%' || ? || '%'"#);
+        let mut binds = Vec::new();
+        DIALECT.push_list_filters(&mut sql, &mut binds, params);
+        if let Some(ref cursor) = params.cursor {
+            DIALECT.push_cursor(&mut sql, &mut binds, cursor);
         }
-        if params.hidden.is_some() {
-            sql.push_str(" AND hidden = ?");
-        }
-        sql.push_str(" ORDER BY found_at DESC LIMIT ? OFFSET ?");
+
+        let direction = params.direction();
+        sql.push_str(&format!(
+            " ORDER BY sort_value {direction} NULLS LAST, id {direction} LIMIT ? OFFSET ?"
+        ));
 
         let mut q = sqlx::query(&sql);
-        if let Some(ref query) = params.query {
-            q = q.bind(query).bind(query);
+        for bind in binds {
+            q = q.bind(bind);
         }
-        if let Some(ref tag) = params.tag {
-            q = q.bind(tag);
-        }
-        if let Some(hidden) = params.hidden {
-            q = q.bind(if hidden { 1i64 } else { 0i64 });
-        }
-        q = q.bind(params.limit).bind(params.offset);
+        // Fetch one extra row to learn whether there is a next page.
+        q = q.bind(params.limit + 1).bind(params.effective_offset());
+
+        let mut rows = q.fetch_all(&self.reader).await?;
+        let has_more = rows.len() as i64 > params.limit;
+        rows.truncate(params.limit.max(0) as usize);
+
+        let next = rows.last().filter(|_| has_more).map(|row| {
+            let value = match params.sort.key {
+                SortKey::Title | SortKey::Host => row
+                    .get::<Option<String>, _>("sort_value")
+                    .map(serde_json::Value::from),
+                _ => row
+                    .get::<Option<i64>, _>("sort_value")
+                    .map(serde_json::Value::from),
+            };
+
+            Cursor {
+                sort: params.sort,
+                value: value.unwrap_or_default(),
+                id: row.get("id"),
+            }
+        });
 
-        let rows = q.fetch_all(&mut *sqlite).await?;
         let mut links = Vec::with_capacity(rows.len());
         for row in rows {
             let link = Link {
@@ -180,40 +290,155 @@ impl SqliteStore {
                     .get::<Option<i64>, _>("last_processed")
                     .and_then(|ts| Utc.timestamp_millis_opt(ts).latest()),
                 hidden: row.get::<Option<i64>, _>("hidden").unwrap_or(0) != 0,
+                summary: row.get("summary"),
+                summarized_at: row
+                    .get::<Option<i64>, _>("summarized_at")
+                    .and_then(|ts| Utc.timestamp_millis_opt(ts).latest()),
+                src_truncated: row.get("src_truncated"),
+                digested_at: row
+                    .get::<Option<i64>, _>("digested_at")
+                    .and_then(|ts| Utc.timestamp_millis_opt(ts).latest()),
                 ..Default::default()
             };
             links.push(link);
         }
-        Ok(links)
+        Ok(ListPage { links, next })
     }
 
-    /// Returns all distinct tags.
-    pub async fn all_tags(&self) -> eyre::Result<Vec<String>> {
-        let mut sqlite = self.sqlite.lock().await;
-        let rows = sqlx::query(r#"SELECT tags FROM "links""#)
-            .fetch_all(&mut *sqlite)
-            .await?;
+    async fn all_tags(&self, query: Option<&Query>) -> eyre::Result<Vec<String>> {
+        let mut sql = String::from(
+            r#"SELECT name FROM "tags"
+               WHERE EXISTS (
+                   SELECT 1 FROM "link_tags" JOIN "links" ON "links".id = "link_tags".link_id
+                   WHERE "link_tags".tag_id = "tags".id"#,
+        );
+        let mut binds = Vec::new();
+        if let Some(query) = query {
+            DIALECT.push_query(&mut sql, &mut binds, query);
+        }
+        sql.push(')');
+
+        let mut q = sqlx::query(&sql);
+        for bind in binds {
+            q = q.bind(bind);
+        }
+        let rows = q.fetch_all(&self.reader).await?;
 
         let mut tags = std::collections::BTreeSet::new();
+        for row in rows {
+            let tag: String = row.get("name");
+            tags.extend(tag_ancestors(&tag).map(str::to_string));
+            tags.insert(tag);
+        }
+        Ok(tags.into_iter().collect())
+    }
+
+    async fn apply_tag_operation(&self, op: &TagOperation) -> eyre::Result<u64> {
+        let mut tx = self.writer.begin().await?;
+
+        let rows = sqlx::query(&format!(r#"SELECT id, {} FROM "links""#, TAGS_COLUMN))
+            .fetch_all(&mut tx)
+            .await?;
+
+        let mut changed = 0;
         for row in rows {
             let raw: String = row.get("tags");
-            if let Ok(parsed) = serde_json::from_str::<Vec<String>>(&raw) {
-                for tag in parsed {
-                    if !tag.is_empty() {
-                        tags.insert(tag);
-                    }
-                }
+            let Ok(tags) = serde_json::from_str::<Vec<String>>(&raw) else {
+                continue;
+            };
+            let Some(tags) = op.apply(&tags) else {
+                continue;
+            };
+
+            replace_link_tags(&mut tx, row.get("id"), &tags).await?;
+            changed += 1;
+        }
+
+        sqlx::query(
+            r#"DELETE FROM "tags"
+               WHERE NOT EXISTS (SELECT 1 FROM "link_tags" WHERE tag_id = "tags".id)"#,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        // Deleting a tag without dropping it leaves aliases to it in place, for future imports.
+        if op.affects_imports() {
+            let aliases = sqlx::query(r#"SELECT alias, tag FROM "tag_aliases""#)
+                .fetch_all(&mut tx)
+                .await?;
+            for row in aliases {
+                let tag: String = row.get("tag");
+                let Some(tags) = op.apply(&[tag]) else {
+                    continue;
+                };
+
+                sqlx::query(r#"UPDATE "tag_aliases" SET tag = ? WHERE alias = ?"#)
+                    .bind(tags.into_iter().next().unwrap_or_default())
+                    .bind(row.get::<String, _>("alias"))
+                    .execute(&mut tx)
+                    .await?;
             }
         }
-        Ok(tags.into_iter().collect())
+
+        for (alias, tag) in op.aliases() {
+            sqlx::query(
+                r#"INSERT INTO "tag_aliases" (alias, tag) VALUES (?, ?)
+                   ON CONFLICT (alias) DO UPDATE SET tag = excluded.tag"#,
+            )
+            .bind(alias.to_lowercase())
+            .bind(tag)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(changed)
+    }
+
+    async fn tag_aliases(&self) -> eyre::Result<TagAliases> {
+        let rows = sqlx::query(r#"SELECT alias, tag FROM "tag_aliases""#)
+            .fetch_all(&self.reader)
+            .await?;
+
+        Ok(TagAliases::new(
+            rows.into_iter()
+                .map(|row| (row.get("alias"), row.get("tag"))),
+        ))
+    }
+
+    async fn set_tag_alias(&self, alias: &str, tag: &str) -> eyre::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO "tag_aliases" (alias, tag) VALUES (?, ?)
+               ON CONFLICT (alias) DO UPDATE SET tag = excluded.tag"#,
+        )
+        .bind(alias.to_lowercase())
+        .bind(tag)
+        .execute(&self.writer)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_tag_alias(&self, alias: &str) -> eyre::Result<bool> {
+        let result = sqlx::query(r#"DELETE FROM "tag_aliases" WHERE alias = ?"#)
+            .bind(alias.to_lowercase())
+            .execute(&self.writer)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
     }
 }
 
 #[async_trait::async_trait]
 impl LinkWriter for SqliteStore {
     async fn write(&self, link: Link) -> eyre::Result<bool> {
-        let mut sqlite = self.sqlite.lock().await;
-        let tags = serde_json::to_string(&link.tags)?;
+        if link.src.is_some() {
+            return Err(eyre::eyre!(
+                "not writing source data for {} to the database; write it through an ExternalWrap",
+                link.url
+            ));
+        }
+
         let via = serde_json::to_string(&link.via)?;
 
         let found_at = link.found_at.map(|xs| xs.timestamp_millis());
@@ -223,12 +448,6 @@ impl LinkWriter for SqliteStore {
         let last_fetched = link.last_fetched.map(|xs| xs.timestamp_millis());
         let last_processed = link.last_processed.map(|xs| xs.timestamp_millis());
 
-        let src = link
-            .src
-            .iter()
-            .filter_map(|src| zstd::encode_all(src.as_slice(), 0).ok())
-            .next();
-
         let meta = link
             .meta
             .iter()
@@ -243,12 +462,14 @@ impl LinkWriter for SqliteStore {
             .next();
 
         let hidden = if link.hidden { 1i64 } else { 0i64 };
+        let summarized_at = link.summarized_at.map(|xs| xs.timestamp_millis());
+        let digested_at = link.digested_at.map(|xs| xs.timestamp_millis());
 
+        let mut tx = self.writer.begin().await?;
         let results = sqlx::query!(
             r#"
             INSERT INTO "links" (
                 title,
-                tags,
                 via,
                 notes,
                 found_at,
@@ -257,12 +478,15 @@ impl LinkWriter for SqliteStore {
                 from_filename,
                 url,
                 image,
-                src,
                 meta,
                 last_fetched,
                 last_processed,
                 http_headers,
-                hidden
+                hidden,
+                summary,
+                summarized_at,
+                src_truncated,
+                digested_at
             ) VALUES (
                 ?,
                 ?,
@@ -279,10 +503,11 @@ impl LinkWriter for SqliteStore {
                 ?,
                 ?,
                 ?,
+                ?,
+                ?,
                 ?
             ) ON CONFLICT (url) DO UPDATE
                 SET title=excluded.title,
-                    tags=excluded.tags,
                     via=excluded.via,
                     notes=excluded.notes,
                     found_at=excluded.found_at,
@@ -290,15 +515,17 @@ impl LinkWriter for SqliteStore {
                     published_at=excluded.published_at,
                     from_filename=excluded.from_filename,
                     image=excluded.image,
-                    src=excluded.src,
                     meta=excluded.meta,
                     last_fetched=excluded.last_fetched,
                     last_processed=excluded.last_processed,
                     http_headers=excluded.http_headers,
-                    hidden=excluded.hidden
+                    hidden=excluded.hidden,
+                    summary=excluded.summary,
+                    summarized_at=excluded.summarized_at,
+                    src_truncated=excluded.src_truncated,
+                    digested_at=excluded.digested_at
             "#,
             link.title,
-            tags,
             via,
             link.notes,
             found_at,
@@ -307,16 +534,26 @@ impl LinkWriter for SqliteStore {
             link.from_filename,
             link.url,
             link.image,
-            src,
             meta,
             last_fetched,
             last_processed,
             http_headers,
-            hidden
+            hidden,
+            link.summary,
+            summarized_at,
+            link.src_truncated,
+            digested_at
         )
-        .execute(&mut *sqlite)
+        .execute(&mut tx)
         .await?;
 
+        let link_id = sqlx::query_scalar!(r#"SELECT id FROM "links" WHERE url = ?"#, link.url)
+            .fetch_one(&mut tx)
+            .await?;
+
+        replace_link_tags(&mut tx, link_id, &link.tags).await?;
+        tx.commit().await?;
+
         Ok(results.rows_affected() > 0)
     }
 }
@@ -338,6 +575,10 @@ struct LinkRow {
     last_processed: Option<i64>,
     http_headers: Option<Vec<u8>>,
     hidden: Option<i64>,
+    summary: Option<String>,
+    summarized_at: Option<i64>,
+    src_truncated: Option<String>,
+    digested_at: Option<i64>,
 }
 
 impl TryFrom<LinkRow> for Link {
@@ -364,6 +605,14 @@ impl TryFrom<LinkRow> for Link {
             .last_processed
             .and_then(|xs| Utc.timestamp_millis_opt(xs).latest());
 
+        let summarized_at = value
+            .summarized_at
+            .and_then(|xs| Utc.timestamp_millis_opt(xs).latest());
+
+        let digested_at = value
+            .digested_at
+            .and_then(|xs| Utc.timestamp_millis_opt(xs).latest());
+
         let meta = value
             .meta
             .iter()
@@ -400,6 +649,10 @@ impl TryFrom<LinkRow> for Link {
             last_processed,
             http_headers,
             hidden: value.hidden.unwrap_or(0) != 0,
+            summary: value.summary,
+            summarized_at,
+            src_truncated: value.src_truncated,
+            digested_at,
             ..Default::default()
         })
     }
@@ -408,14 +661,20 @@ impl TryFrom<LinkRow> for Link {
 #[async_trait::async_trait]
 impl LinkReader for SqliteStore {
     async fn get(&self, link: &str) -> eyre::Result<Option<Link>> {
-        let mut sqlite = self.sqlite.lock().await;
         let Some(value) = sqlx::query_as!(
             LinkRow,
             r#"
             SELECT
                 url,
                 title,
-                tags,
+                (
+                    SELECT json_group_array(name) FROM (
+                        SELECT "tags".name FROM "link_tags"
+                        JOIN "tags" ON "tags".id = "link_tags".tag_id
+                        WHERE "link_tags".link_id = "links".id
+                        ORDER BY "link_tags".rowid
+                    )
+                ) as "tags!: String",
                 via,
                 notes,
                 found_at,
@@ -428,19 +687,24 @@ impl LinkReader for SqliteStore {
                 last_fetched,
                 last_processed,
                 http_headers,
-                hidden
+                hidden,
+                summary,
+                summarized_at,
+                src_truncated,
+                digested_at
             FROM "links" WHERE "url" = ?"#,
             link
         )
-        .fetch_optional(&mut *sqlite)
-        .await? else { return Ok(None) };
+        .fetch_optional(&self.reader)
+        .await?
+        else {
+            return Ok(None);
+        };
 
         Ok(Some(value.try_into()?))
     }
 
     async fn values<'a>(&'a self) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a + Send>>> {
-        let mut sqlite = self.sqlite.lock().await;
-
         let stream = stream! {
             let input = sqlx::query_as!(
                 LinkRow,
@@ -448,7 +712,14 @@ impl LinkReader for SqliteStore {
                 SELECT
                     url,
                     title,
-                    tags,
+                    (
+                        SELECT json_group_array(name) FROM (
+                            SELECT "tags".name FROM "link_tags"
+                            JOIN "tags" ON "tags".id = "link_tags".tag_id
+                            WHERE "link_tags".link_id = "links".id
+                            ORDER BY "link_tags".rowid
+                        )
+                    ) as "tags!: String",
                     via,
                     notes,
                     found_at,
@@ -461,11 +732,15 @@ impl LinkReader for SqliteStore {
                     last_fetched,
                     last_processed,
                     http_headers,
-                    hidden
+                    hidden,
+                    summary,
+                    summarized_at,
+                    src_truncated,
+                    digested_at
                 FROM "links"
                 "#,
             )
-            .fetch(&mut *sqlite);
+            .fetch(&self.reader);
 
             for await value in input {
                 let Ok(value) = value else { continue };
@@ -482,8 +757,6 @@ impl LinkReader for SqliteStore {
         &'a self,
         pattern: &'b str,
     ) -> eyre::Result<Pin<Box<dyn Stream<Item = Link> + 'a>>> {
-        let mut sqlite = self.sqlite.lock().await;
-
         let stream = stream! {
             let input = sqlx::query_as!(
                 LinkRow,
@@ -491,7 +764,14 @@ impl LinkReader for SqliteStore {
                 SELECT
                     url,
                     title,
-                    tags,
+                    (
+                        SELECT json_group_array(name) FROM (
+                            SELECT "tags".name FROM "link_tags"
+                            JOIN "tags" ON "tags".id = "link_tags".tag_id
+                            WHERE "link_tags".link_id = "links".id
+                            ORDER BY "link_tags".rowid
+                        )
+                    ) as "tags!: String",
                     via,
                     notes,
                     found_at,
@@ -504,13 +784,17 @@ impl LinkReader for SqliteStore {
                     last_fetched,
                     last_processed,
                     http_headers,
-                    hidden
+                    hidden,
+                    summary,
+                    summarized_at,
+                    src_truncated,
+                    digested_at
                 FROM "links"
                 WHERE url GLOB ?
                 "#,
                 pattern
             )
-            .fetch(&mut *sqlite);
+            .fetch(&self.reader);
 
             for await value in input {
                 let Ok(value) = value else { continue };
@@ -523,3 +807,70 @@ impl LinkReader for SqliteStore {
         Ok(Box::pin(stream))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CacacheBlobStore, ExternalWrap};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn keeps_source_data_out_of_the_database() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let blobs = CacacheBlobStore::new(dir.path());
+        let store = Arc::new(SqliteStore::with_connection_string("sqlite::memory:").await?);
+
+        let mut link = Link::new("https://a.com/", "a");
+        link.src = Some(b"<p>a</p>".to_vec());
+        assert!(store.write(link).await.is_err());
+        assert!(store.get("https://a.com/").await?.is_none());
+
+        // Older versions stored compressed source data in the links table.
+        for (url, src) in [
+            ("https://b.com/", "<p>b</p>"),
+            ("https://c.com/", "<p>c</p>"),
+        ] {
+            store.write(Link::new(url, url)).await?;
+            sqlx::query(r#"UPDATE "links" SET src = ? WHERE url = ?"#)
+                .bind(zstd::encode_all(src.as_bytes(), 0)?)
+                .bind(url)
+                .execute(&store.writer)
+                .await?;
+        }
+        blobs
+            .write(&src_key("https://c.com/"), b"<p>c, fetched later</p>")
+            .await?;
+
+        let link = store
+            .get("https://b.com/")
+            .await?
+            .expect("link was written");
+        assert_eq!(link.src(), Some(&b"<p>b</p>"[..]));
+
+        // The blob store's source data is newer than what's left in the database.
+        let wrapped = ExternalWrap::with_blob_store(blobs.clone(), store.clone());
+        let link = wrapped
+            .get("https://c.com/")
+            .await?
+            .expect("link was written");
+        assert_eq!(link.src(), Some(&b"<p>c, fetched later</p>"[..]));
+
+        let compaction = store.compact(&blobs).await?;
+        assert_eq!(compaction.moved, 2);
+        assert!(compaction.bytes > 0);
+        assert_eq!(store.compact(&blobs).await?, Compaction::default());
+
+        let store = ExternalWrap::with_blob_store(blobs, store);
+        let link = store
+            .get("https://b.com/")
+            .await?
+            .expect("link was written");
+        assert_eq!(link.src(), Some(&b"<p>b</p>"[..]));
+        let link = store
+            .get("https://c.com/")
+            .await?
+            .expect("link was written");
+        assert_eq!(link.src(), Some(&b"<p>c, fetched later</p>"[..]));
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+use chrono::{TimeZone, Utc};
+use include_dir::{include_dir, Dir};
+use sqlx::{Row, SqliteConnection, SqlitePool};
+
+use crate::stores::migrations::{
+    check, load, statuses, AppliedMigration, Migration, MigrationStatus,
+};
+
+static MIGRATIONS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/migrations");
+
+/// Load the migrations embedded in the binary, ordered by version.
+pub fn migrations() -> eyre::Result<Vec<Migration>> {
+    load(&MIGRATIONS_DIR)
+}
+
+async fn table_exists(conn: &mut SqliteConnection, table: &str) -> eyre::Result<bool> {
+    let count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_one(&mut *conn)
+            .await?;
+
+    Ok(count > 0)
+}
+
+/// Read the applied migrations. Databases from before migrations were tracked by name record
+/// only a count in `database_version`; those are read as the first `count` known migrations.
+async fn applied(
+    conn: &mut SqliteConnection,
+    known: &[Migration],
+) -> eyre::Result<Vec<AppliedMigration>> {
+    if table_exists(conn, "schema_migrations").await? {
+        let rows = sqlx::query(
+            "SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version",
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        return Ok(rows
+            .into_iter()
+            .map(|row| AppliedMigration {
+                version: row.get("version"),
+                name: row.get("name"),
+                checksum: row.get("checksum"),
+                applied_at: Utc.timestamp_millis_opt(row.get("applied_at")).latest(),
+            })
+            .collect());
+    }
+
+    if !table_exists(conn, "database_version").await? {
+        return Ok(Vec::new());
+    }
+
+    let count: Option<i64> = sqlx::query_scalar("SELECT version FROM database_version LIMIT 1")
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let count = count.unwrap_or(0) as usize;
+    if count > known.len() {
+        return Err(eyre::eyre!(
+            "this database was created by a newer version of likelike (schema version {}, expected at most {})",
+            count,
+            known.len()
+        ));
+    }
+
+    Ok(known[..count]
+        .iter()
+        .map(|migration| AppliedMigration {
+            version: migration.version,
+            name: migration.name.clone(),
+            checksum: migration.checksum.clone(),
+            applied_at: None,
+        })
+        .collect())
+}
+
+pub(crate) async fn status(pool: &SqlitePool) -> eyre::Result<Vec<MigrationStatus>> {
+    let known = migrations()?;
+    let mut conn = pool.acquire().await?;
+    let applied = applied(&mut conn, &known).await?;
+
+    Ok(statuses(&known, &applied))
+}
+
+/// Create the tracking table if it doesn't exist yet, recording the migrations already applied to
+/// a legacy database.
+async fn ensure_tracking_table(
+    pool: &SqlitePool,
+    applied: &[AppliedMigration],
+) -> eyre::Result<()> {
+    let mut tx = pool.begin().await?;
+    if table_exists(&mut tx, "schema_migrations").await? {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        create table "schema_migrations" (
+          version integer primary key,
+          name text not null,
+          checksum text not null,
+          applied_at integer not null
+        ) strict
+        "#,
+    )
+    .execute(&mut tx)
+    .await?;
+
+    for migration in applied {
+        record(
+            &mut tx,
+            migration.version,
+            &migration.name,
+            &migration.checksum,
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn record(
+    conn: &mut SqliteConnection,
+    version: i64,
+    name: &str,
+    checksum: &str,
+) -> eyre::Result<()> {
+    sqlx::query(
+        "INSERT INTO schema_migrations (version, name, checksum, applied_at) VALUES (?, ?, ?, ?)",
+    )
+    .bind(version)
+    .bind(name)
+    .bind(checksum)
+    .bind(Utc::now().timestamp_millis())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Read and validate the applied migrations, refusing databases that have migrations this
+/// version doesn't know about or whose applied migrations have since changed.
+async fn prepare(pool: &SqlitePool) -> eyre::Result<(Vec<Migration>, Vec<AppliedMigration>)> {
+    let known = migrations()?;
+    let mut conn = pool.acquire().await?;
+    let applied = applied(&mut conn, &known).await?;
+    drop(conn);
+
+    check(&known, &applied)?;
+    ensure_tracking_table(pool, &applied).await?;
+    Ok((known, applied))
+}
+
+/// Apply pending migrations, each in its own transaction.
+pub(crate) async fn migrate(pool: &SqlitePool) -> eyre::Result<Vec<Migration>> {
+    let (known, applied) = prepare(pool).await?;
+
+    let mut newly_applied = Vec::new();
+    for migration in known {
+        if applied.iter().any(|xs| xs.version == migration.version) {
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration.up)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| {
+                eyre::eyre!(
+                    "migration {:04}-{} failed: {}",
+                    migration.version,
+                    migration.name,
+                    e
+                )
+            })?;
+        record(
+            &mut tx,
+            migration.version,
+            &migration.name,
+            &migration.checksum,
+        )
+        .await?;
+        tx.commit().await?;
+
+        newly_applied.push(migration);
+    }
+
+    Ok(newly_applied)
+}
+
+/// Revert the `steps` most recently applied migrations, newest first, each in its own
+/// transaction.
+pub(crate) async fn rollback(pool: &SqlitePool, steps: usize) -> eyre::Result<Vec<Migration>> {
+    let (known, applied) = prepare(pool).await?;
+
+    let mut reverted = Vec::new();
+    for record in applied.iter().rev().take(steps) {
+        let Some(migration) = known.iter().find(|xs| xs.version == record.version) else {
+            continue;
+        };
+
+        let down = migration.down.ok_or_else(|| {
+            eyre::eyre!(
+                "migration {:04}-{} cannot be rolled back",
+                migration.version,
+                migration.name
+            )
+        })?;
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(down).execute(&mut tx).await.map_err(|e| {
+            eyre::eyre!(
+                "rolling back migration {:04}-{} failed: {}",
+                migration.version,
+                migration.name,
+                e
+            )
+        })?;
+        sqlx::query("DELETE FROM schema_migrations WHERE version = ?")
+            .bind(migration.version)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        reverted.push(migration.clone());
+    }
+
+    Ok(reverted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MigrationState, SqliteStore};
+
+    fn states(statuses: &[MigrationStatus]) -> Vec<MigrationState> {
+        statuses.iter().map(|xs| xs.state).collect()
+    }
+
+    #[tokio::test]
+    async fn migrations_roll_back_and_refuse_unknown_history() -> eyre::Result<()> {
+        let known = migrations()?.len();
+        let store = SqliteStore::with_connection_string("sqlite::memory:").await?;
+        let pool = &store.writer;
+
+        assert!(states(&status(pool).await?)
+            .iter()
+            .all(|xs| *xs == MigrationState::Applied));
+
+        assert_eq!(rollback(pool, known).await?.len(), known);
+        assert!(states(&status(pool).await?)
+            .iter()
+            .all(|xs| *xs == MigrationState::Pending));
+        assert_eq!(migrate(pool).await?.len(), known);
+
+        // Databases from before migrations were tracked by name only record a count.
+        sqlx::query("DROP TABLE schema_migrations; INSERT INTO database_version (id, version) VALUES (0, ?)")
+            .bind(known as i64)
+            .execute(pool)
+            .await?;
+        assert!(migrate(pool).await?.is_empty());
+        assert_eq!(status(pool).await?.len(), known);
+
+        sqlx::query("UPDATE schema_migrations SET checksum = 'x' WHERE version = 1")
+            .execute(pool)
+            .await?;
+        assert_eq!(status(pool).await?[0].state, MigrationState::Modified);
+        assert!(migrate(pool).await.is_err());
+
+        sqlx::query("UPDATE schema_migrations SET checksum = ? WHERE version = 1")
+            .bind(migrations()?[0].checksum.clone())
+            .execute(pool)
+            .await?;
+        sqlx::query("INSERT INTO schema_migrations VALUES (9999, 'from-the-future', '', 0)")
+            .execute(pool)
+            .await?;
+        assert!(migrate(pool).await.is_err());
+        assert!(rollback(pool, 1).await.is_err());
+        Ok(())
+    }
+}
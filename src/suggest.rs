@@ -0,0 +1,156 @@
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::similarity::{cosine, link_text, term_counts, tokenize, Corpus, TermCounts, TermVector};
+use crate::{Link, LinkReader};
+
+/// Added to a tag's score when the tag's own name shows up in the link's text.
+const KEYWORD_BOOST: f64 = 0.1;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TagSuggestion {
+    pub tag: String,
+    pub score: f64,
+}
+
+/// Ranks existing tags for a link by comparing the link's text against the combined text of the
+/// links already carrying each tag.
+#[derive(Default, Debug)]
+pub struct TagSuggester {
+    corpus: Corpus,
+    tags: HashMap<String, TermVector>,
+}
+
+impl TagSuggester {
+    pub fn from_links<'a>(links: impl IntoIterator<Item = &'a Link>) -> Self {
+        let mut documents: HashMap<String, TermCounts> = HashMap::new();
+        for link in links {
+            if link.tags().iter().all(|tag| tag.is_empty()) {
+                continue;
+            }
+
+            let counts = term_counts(link_text(link).as_str());
+            for tag in link.tags().iter().filter(|tag| !tag.is_empty()) {
+                let document = documents.entry(tag.clone()).or_default();
+                for (term, count) in counts.iter() {
+                    *document.entry(term.clone()).or_insert(0) += count;
+                }
+            }
+        }
+
+        let mut corpus = Corpus::default();
+        for document in documents.values() {
+            corpus.add(document);
+        }
+
+        let tags = documents
+            .into_iter()
+            .map(|(tag, document)| {
+                let vector = corpus.vectorize(&document);
+                (tag, vector)
+            })
+            .collect();
+
+        Self { corpus, tags }
+    }
+
+    /// Build a suggester from every link in the store. The store should hydrate extracted text
+    /// (e.g., by wrapping it in an `ExternalWrap`), otherwise only titles and notes are compared.
+    pub async fn from_store<S: LinkReader + Send + Sync>(store: &S) -> eyre::Result<Self> {
+        let mut links = store.values().await?;
+        let mut v = Vec::new();
+        while let Some(link) = links.next().await {
+            v.push(link);
+        }
+
+        Ok(Self::from_links(v.iter()))
+    }
+
+    /// Return up to `limit` tags the link does not already carry, best match first.
+    pub fn suggest(&self, link: &Link, limit: usize) -> Vec<TagSuggestion> {
+        let counts = term_counts(link_text(link).as_str());
+        let vector = self.corpus.vectorize(&counts);
+
+        let mut suggestions: Vec<_> = self
+            .tags
+            .iter()
+            .filter(|(tag, _)| !link.tags().contains(tag))
+            .filter_map(|(tag, tag_vector)| {
+                let mut score = cosine(&vector, tag_vector);
+                if tokenize(tag).any(|term| counts.contains_key(&term)) {
+                    score += KEYWORD_BOOST;
+                }
+
+                if score > 0.0 {
+                    Some(TagSuggestion {
+                        tag: tag.clone(),
+                        score,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        suggestions.sort_by(|lhs, rhs| {
+            rhs.score
+                .total_cmp(&lhs.score)
+                .then_with(|| lhs.tag.cmp(&rhs.tag))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str, text: &str, tags: &[&str]) -> Link {
+        Link {
+            url: url.to_string(),
+            extracted_text: Some(text.to_string()),
+            tags: tags.iter().map(|xs| xs.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ranks_tags_by_shared_vocabulary() {
+        let links = [
+            link(
+                "https://a.com/",
+                "borrow checker lifetimes traits cargo crates",
+                &["rust"],
+            ),
+            link(
+                "https://b.com/",
+                "goroutines channels gofmt modules interfaces",
+                &["go"],
+            ),
+            link(
+                "https://c.com/",
+                "sourdough starter hydration crumb oven",
+                &["baking"],
+            ),
+        ];
+
+        let suggester = TagSuggester::from_links(links.iter());
+        let target = link(
+            "https://d.com/",
+            "fighting the borrow checker over lifetimes in async traits",
+            &[],
+        );
+
+        let suggestions = suggester.suggest(&target, 3);
+        assert_eq!(suggestions.first().map(|xs| xs.tag.as_str()), Some("rust"));
+        assert!(suggestions.iter().all(|xs| xs.tag != "baking"));
+
+        let tagged = link("https://e.com/", "borrow checker", &["rust"]);
+        assert!(suggester
+            .suggest(&tagged, 3)
+            .iter()
+            .all(|xs| xs.tag != "rust"));
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(feature = "llm")]
+mod local;
+mod openai;
+
+#[cfg(feature = "llm")]
+pub use local::*;
+pub use openai::*;
+
+/// Produce a short prose summary of a link's extracted text.
+#[async_trait::async_trait]
+pub trait Summarizer {
+    async fn summarize(&self, text: &str) -> eyre::Result<String>;
+}
+
+#[async_trait::async_trait]
+impl<T: Summarizer + Send + Sync + ?Sized> Summarizer for Box<T> {
+    async fn summarize(&self, text: &str) -> eyre::Result<String> {
+        (**self).summarize(text).await
+    }
+}
+
+pub(crate) const SUMMARY_INSTRUCTION: &str =
+    "Write a concise, 100 word summary of the following markdown text.";
+
+/// Strip link reference lines, headings, blank lines, and markdown emphasis from extracted text
+/// so that we don't spend context on them.
+pub(crate) fn prepare_text(src: &str) -> String {
+    let src: String = itertools::join(
+        src.lines()
+            .filter(|xs| !xs.starts_with('[') && !xs.starts_with('#') && !xs.trim().is_empty()),
+        "\n",
+    );
+
+    src.replace(['*', '\u{fffd}'], "")
+}
@@ -0,0 +1,121 @@
+use itertools::Itertools;
+use llm::{samplers::TopPTopK, Model, ModelParameters};
+use std::{path::Path, sync::Arc};
+
+use super::{prepare_text, Summarizer, SUMMARY_INSTRUCTION};
+
+const MAX_TOKENS_PER_CHUNK: usize = 128;
+
+/// Summarize text using a GGML model loaded from disk.
+pub struct LocalSummarizer {
+    model: Arc<llm::models::Llama>,
+}
+
+impl LocalSummarizer {
+    pub fn new(path: &Path) -> eyre::Result<Self> {
+        let model = llm::load::<llm::models::Llama>(
+            path,
+            llm::VocabularySource::Model,
+            ModelParameters {
+                context_size: 8192,
+                ..Default::default()
+            },
+            |_| {},
+        )
+        .map_err(|err| eyre::eyre!("Failed to load model: {err}"))?;
+
+        Ok(Self {
+            model: Arc::new(model),
+        })
+    }
+
+    /// Load the model named by `LIKELIKE_GGML`.
+    pub fn from_env() -> eyre::Result<Self> {
+        let ggml = std::env::var("LIKELIKE_GGML")
+            .ok()
+            .unwrap_or_else(|| "ggml-vicuna-13B-1.1-q5_1.bin".to_string());
+
+        Self::new(Path::new(ggml.as_str()))
+    }
+}
+
+#[async_trait::async_trait]
+impl Summarizer for LocalSummarizer {
+    async fn summarize(&self, text: &str) -> eyre::Result<String> {
+        // Generation runs on the CPU for as long as it takes, so keep it off the async workers.
+        let model = Arc::clone(&self.model);
+        let src = prepare_text(text);
+        tokio::task::spawn_blocking(move || summarize_with(&model, &src)).await?
+    }
+}
+
+fn summarize_with(model: &llm::models::Llama, src: &str) -> eyre::Result<String> {
+    let mut session = model.start_session(Default::default());
+
+    let mut summaries = Vec::new();
+    let paras: Vec<_> = src.split('\n').collect();
+    for paras in &paras.into_iter().chunks(4) {
+        let next_paras = itertools::join(paras, "\n");
+
+        let prompt = format!(
+            indoc::indoc! {r#"Below is an instruction that describes a task. Write a response that appropriately completes the request.
+
+            ### Instruction:
+
+            {}
+
+            #### Text:
+            {}
+
+            ### Response:
+            "#},
+            SUMMARY_INSTRUCTION, next_paras
+        );
+
+        let mut output = String::with_capacity(2048);
+        let mut token_count = 0;
+        session
+            .infer::<std::convert::Infallible>(
+                model,
+                &mut rand::thread_rng(),
+                &llm::InferenceRequest {
+                    prompt: prompt.as_str().into(),
+                    parameters: &llm::InferenceParameters {
+                        sampler: Arc::new(TopPTopK {
+                            top_k: 40,
+                            top_p: 0.95,
+                            repeat_penalty: 1.30,
+                            temperature: 0.50,
+                            repetition_penalty_last_n: 512,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                    play_back_previous_tokens: false,
+                    maximum_token_count: None,
+                },
+                &mut Default::default(),
+                |r| match r {
+                    llm::InferenceResponse::InferredToken(t) => {
+                        output.push_str(t.as_str());
+                        token_count += 1;
+                        if token_count > MAX_TOKENS_PER_CHUNK {
+                            Ok(llm::InferenceFeedback::Halt)
+                        } else {
+                            Ok(llm::InferenceFeedback::Continue)
+                        }
+                    }
+
+                    _ => Ok(llm::InferenceFeedback::Continue),
+                },
+            )
+            .map_err(|err| eyre::eyre!("inference failed: {err}"))?;
+
+        let output = output.trim();
+        if !output.is_empty() {
+            summaries.push(output.to_string());
+        }
+    }
+
+    Ok(summaries.join("\n\n"))
+}
@@ -0,0 +1,192 @@
+use reqwest::{header, Client, ClientBuilder};
+use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
+
+use super::{prepare_text, Summarizer, SUMMARY_INSTRUCTION};
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+const DEFAULT_MAX_INPUT_CHARS: usize = 16_000;
+
+/// Summarize text using any server that speaks the OpenAI chat completions API. This includes
+/// OpenAI itself as well as local servers like llama.cpp's `server`, ollama, or vLLM.
+pub struct OpenAiSummarizer {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    max_input_chars: usize,
+}
+
+impl OpenAiSummarizer {
+    pub fn new(client: Client, base_url: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            base_url: base_url.into(),
+            api_key: None,
+            model: model.into(),
+            max_input_chars: DEFAULT_MAX_INPUT_CHARS,
+        }
+    }
+
+    /// Configure the summarizer using `LIKELIKE_OPENAI_BASE_URL`, `LIKELIKE_OPENAI_MODEL`, and
+    /// `LIKELIKE_OPENAI_API_KEY` (falling back to `OPENAI_API_KEY`.)
+    pub fn from_env() -> eyre::Result<Self> {
+        let timeout: u64 = env::var("LIKELIKE_SUMMARY_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|xs| xs.parse().ok())
+            .unwrap_or(120);
+
+        let client = ClientBuilder::new()
+            .timeout(Duration::new(timeout, 0))
+            .build()?;
+
+        let base_url =
+            env::var("LIKELIKE_OPENAI_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+        let model = env::var("LIKELIKE_OPENAI_MODEL").unwrap_or_else(|_| DEFAULT_MODEL.to_string());
+        let api_key = env::var("LIKELIKE_OPENAI_API_KEY")
+            .or_else(|_| env::var("OPENAI_API_KEY"))
+            .ok();
+
+        let mut summarizer = Self::new(client, base_url, model);
+        summarizer.api_key = api_key;
+        if let Some(max_input_chars) = env::var("LIKELIKE_SUMMARY_MAX_CHARS")
+            .ok()
+            .and_then(|xs| xs.parse().ok())
+        {
+            summarizer.max_input_chars = max_input_chars;
+        }
+
+        Ok(summarizer)
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    temperature: f32,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatResponseMessage {
+    content: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Summarizer for OpenAiSummarizer {
+    async fn summarize(&self, text: &str) -> eyre::Result<String> {
+        let text = prepare_text(text);
+        let text = match text.char_indices().nth(self.max_input_chars) {
+            Some((idx, _)) => &text[..idx],
+            None => text.as_str(),
+        };
+
+        let body = serde_json::to_vec(&ChatRequest {
+            model: self.model.as_str(),
+            messages: vec![
+                ChatMessage {
+                    role: "system",
+                    content: SUMMARY_INSTRUCTION,
+                },
+                ChatMessage {
+                    role: "user",
+                    content: text,
+                },
+            ],
+            temperature: 0.5,
+        })?;
+
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/chat/completions",
+                self.base_url.trim_end_matches('/')
+            ))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body);
+
+        if let Some(ref api_key) = self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let bytes = response.bytes().await?;
+        if !status.is_success() {
+            return Err(eyre::eyre!(
+                "summary request failed with {}: {}",
+                status,
+                String::from_utf8_lossy(&bytes)
+            ));
+        }
+
+        let response: ChatResponse = serde_json::from_slice(&bytes)?;
+        let summary = response
+            .choices
+            .into_iter()
+            .find_map(|choice| choice.message.content)
+            .map(|xs| xs.trim().to_string())
+            .filter(|xs| !xs.is_empty())
+            .ok_or_else(|| eyre::eyre!("summary response contained no content"))?;
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+
+    #[tokio::test]
+    async fn summarizes_against_a_stub_server() -> eyre::Result<()> {
+        let app = Router::new().route(
+            "/v1/chat/completions",
+            post(|Json(body): Json<serde_json::Value>| async move {
+                assert_eq!(body["model"], "stub-model");
+                assert_eq!(body["messages"][1]["content"], "first line\nsecond line");
+
+                Json(serde_json::json!({
+                    "choices": [{ "message": { "role": "assistant", "content": " a summary \n" } }]
+                }))
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let summarizer =
+            OpenAiSummarizer::new(Client::new(), format!("http://{}/v1", addr), "stub-model");
+
+        let summary = summarizer
+            .summarize("# heading\n\nfirst line\n[1]: https://example.com\nsecond *line*")
+            .await?;
+
+        assert_eq!(summary, "a summary");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+
+/// Tags may be arranged in a hierarchy using `/` as a separator, e.g. `lang/rust`. A link tagged
+/// `lang/rust` is considered to be tagged `lang` as well when filtering.
+pub const TAG_SEPARATOR: char = '/';
+
+/// Yields the ancestors of a hierarchical tag, nearest first: `a/b/c` yields `a/b`, then `a`.
+pub fn tag_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.rmatch_indices(TAG_SEPARATOR)
+        .map(move |(idx, _)| &tag[..idx])
+}
+
+/// Returns true if `tag` is `filter` or one of its descendants.
+pub fn tag_matches(filter: &str, tag: &str) -> bool {
+    tag == filter
+        || (tag.len() > filter.len()
+            && tag.starts_with(filter)
+            && tag[filter.len()..].starts_with(TAG_SEPARATOR))
+}
+
+/// Replace `from` (or a prefix of the tag at a hierarchy boundary) with `to`.
+fn replace_prefix(tag: &str, from: &str, to: &str) -> Option<String> {
+    if !tag_matches(from, tag) {
+        return None;
+    }
+
+    Some(format!("{}{}", to, &tag[from.len()..]))
+}
+
+/// A bulk change to the tags of every link in a store.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TagOperation {
+    /// Rename a tag and its descendants: renaming `lang` to `languages` turns `lang/rust` into
+    /// `languages/rust`.
+    Rename { from: String, to: String },
+
+    /// Fold several tags (and their descendants) into one.
+    Merge { from: Vec<String>, into: String },
+
+    /// Remove a tag and its descendants. If `drop` is set, the tag is also dropped from future
+    /// imports; otherwise it may be used again.
+    Delete {
+        tag: String,
+        #[serde(default)]
+        drop: bool,
+    },
+}
+
+impl TagOperation {
+    /// Apply the operation to a list of tags, returning `None` if nothing changed.
+    pub fn apply(&self, tags: &[String]) -> Option<Vec<String>> {
+        let mut changed = false;
+        let mut seen = HashSet::new();
+        let mut output = Vec::with_capacity(tags.len());
+
+        for tag in tags {
+            let replacement = match self {
+                TagOperation::Rename { from, to } => {
+                    replace_prefix(tag, from, to).map(Option::Some)
+                }
+                TagOperation::Merge { from, into } => from
+                    .iter()
+                    .find_map(|from| replace_prefix(tag, from, into))
+                    .map(Option::Some),
+                TagOperation::Delete { tag: deleted, .. } => {
+                    tag_matches(deleted, tag).then_some(None)
+                }
+            };
+
+            let tag = match replacement {
+                Some(replacement) => {
+                    changed = true;
+                    replacement
+                }
+                None => Some(tag.clone()),
+            };
+
+            if let Some(tag) = tag {
+                if seen.insert(tag.clone()) {
+                    output.push(tag);
+                } else {
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            Some(output)
+        } else {
+            None
+        }
+    }
+
+    /// Whether future imports should follow this operation, both through the [`aliases`] it
+    /// records and by rewriting existing aliases. Deleting a tag only does so if asked to drop it.
+    ///
+    /// [`aliases`]: TagOperation::aliases
+    pub fn affects_imports(&self) -> bool {
+        !matches!(self, TagOperation::Delete { drop: false, .. })
+    }
+
+    /// The aliases that keep future imports consistent with this operation. An alias pointing
+    /// at the empty string drops the tag on import.
+    pub fn aliases(&self) -> Vec<(String, String)> {
+        match self {
+            TagOperation::Rename { from, to } => vec![(from.clone(), to.clone())],
+            TagOperation::Merge { from, into } => from
+                .iter()
+                .filter(|from| *from != into)
+                .map(|from| (from.clone(), into.clone()))
+                .collect(),
+            TagOperation::Delete { tag, drop: true } => vec![(tag.clone(), String::new())],
+            TagOperation::Delete { drop: false, .. } => Vec::new(),
+        }
+    }
+}
+
+/// Maps alternate spellings of tags onto their canonical form. Aliases are matched
+/// case-insensitively, and apply to descendants: if `rustlang` is an alias for `lang/rust`, then
+/// `rustlang/async` becomes `lang/rust/async`.
+#[derive(Default, Debug, Clone)]
+pub struct TagAliases {
+    aliases: BTreeMap<String, String>,
+}
+
+impl TagAliases {
+    pub fn new(aliases: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            aliases: aliases
+                .into_iter()
+                .map(|(alias, tag)| (alias.to_lowercase(), tag))
+                .collect(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases
+            .iter()
+            .map(|(alias, tag)| (alias.as_str(), tag.as_str()))
+    }
+
+    /// Return the canonical form of the tag, or `None` if the tag should be dropped.
+    pub fn normalize(&self, tag: &str) -> Option<String> {
+        let lowered = tag.to_lowercase();
+        let prefix = std::iter::once(lowered.as_str())
+            .chain(tag_ancestors(lowered.as_str()))
+            .find(|prefix| self.aliases.contains_key(*prefix));
+
+        let Some(prefix) = prefix else {
+            return Some(tag.to_string());
+        };
+
+        let canonical = &self.aliases[prefix];
+        if canonical.is_empty() {
+            return None;
+        }
+
+        let rest = tag.get(prefix.len()..).unwrap_or(&lowered[prefix.len()..]);
+        Some(format!("{}{}", canonical, rest))
+    }
+}
+
+/// A single term of a [`TagFilter`]. Patterns may use `*` and `?` wildcards; a pattern without
+/// wildcards matches a tag exactly. Either way, a pattern also matches the descendants of the tags
+/// it matches, so `lang` matches `lang/rust`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagPattern(String);
+
+impl TagPattern {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self(pattern.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_exact(&self) -> bool {
+        !self.0.contains(['*', '?'])
+    }
+
+    pub fn matches(&self, tag: &str) -> bool {
+        if self.is_exact() {
+            return tag_matches(self.0.as_str(), tag);
+        }
+
+        let m = wildmatch::WildMatch::new(self.0.as_str());
+        m.matches(tag) || tag_ancestors(tag).any(|parent| m.matches(parent))
+    }
+}
+
+/// Filters links by their tags. A link matches if it carries every `all` pattern, at least one
+/// `any` pattern (if there are any), and no `none` pattern.
+///
+/// The string form is a list of patterns separated by commas, with whitespace around each one
+/// ignored so that tags may contain spaces. A leading `+` marks a pattern as required and a
+/// leading `-` or `!` excludes it: `rust, go, -draft` finds links tagged either `rust` or `go` but
+/// not `draft`; `+rust,+async` finds links tagged both. (Remember to encode `+` as `%2B` in URLs.)
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    pub all: Vec<TagPattern>,
+    pub any: Vec<TagPattern>,
+    pub none: Vec<TagPattern>,
+}
+
+impl TagFilter {
+    pub fn is_empty(&self) -> bool {
+        self.all.is_empty() && self.any.is_empty() && self.none.is_empty()
+    }
+
+    pub fn matches<S: AsRef<str>>(&self, tags: &[S]) -> bool {
+        let has = |pattern: &TagPattern| tags.iter().any(|tag| pattern.matches(tag.as_ref()));
+
+        self.all.iter().all(has)
+            && (self.any.is_empty() || self.any.iter().any(has))
+            && !self.none.iter().any(has)
+    }
+}
+
+impl FromStr for TagFilter {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = TagFilter::default();
+        for term in s.split(',').map(str::trim) {
+            let (bucket, pattern) = if let Some(pattern) = term.strip_prefix('+') {
+                (&mut filter.all, pattern)
+            } else if let Some(pattern) = term.strip_prefix(['-', '!']) {
+                (&mut filter.none, pattern)
+            } else {
+                (&mut filter.any, term)
+            };
+
+            if pattern.is_empty() {
+                if !term.is_empty() {
+                    return Err(eyre::eyre!("empty tag pattern in {:?}", s));
+                }
+                continue;
+            }
+
+            bucket.push(TagPattern::new(pattern));
+        }
+
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(xs: &[&str]) -> Vec<String> {
+        xs.iter().map(|xs| xs.to_string()).collect()
+    }
+
+    #[test]
+    fn operations_respect_hierarchy() {
+        let op = TagOperation::Rename {
+            from: "lang".to_string(),
+            to: "languages".to_string(),
+        };
+        assert_eq!(
+            op.apply(&tags(&["lang/rust", "language", "lang"])),
+            Some(tags(&["languages/rust", "language", "languages"]))
+        );
+        assert_eq!(op.apply(&tags(&["language"])), None);
+
+        let op = TagOperation::Merge {
+            from: tags(&["Rust", "rustlang"]),
+            into: "rust".to_string(),
+        };
+        assert_eq!(
+            op.apply(&tags(&["Rust", "rust", "rustlang/async"])),
+            Some(tags(&["rust", "rust/async"]))
+        );
+
+        let op = TagOperation::Delete {
+            tag: "draft".to_string(),
+            drop: false,
+        };
+        assert_eq!(
+            op.apply(&tags(&["draft/wip", "drafting"])),
+            Some(tags(&["drafting"]))
+        );
+        assert!(op.aliases().is_empty());
+
+        let op = TagOperation::Delete {
+            tag: "draft".to_string(),
+            drop: true,
+        };
+        assert_eq!(op.aliases(), vec![("draft".to_string(), String::new())]);
+    }
+
+    #[test]
+    fn aliases_normalize_tags() {
+        let aliases = TagAliases::new([
+            ("RustLang".to_string(), "lang/rust".to_string()),
+            ("draft".to_string(), String::new()),
+        ]);
+
+        assert_eq!(aliases.normalize("rustlang").as_deref(), Some("lang/rust"));
+        assert_eq!(
+            aliases.normalize("rustlang/async").as_deref(),
+            Some("lang/rust/async")
+        );
+        assert_eq!(aliases.normalize("rust").as_deref(), Some("rust"));
+        assert_eq!(aliases.normalize("draft"), None);
+        assert_eq!(tag_ancestors("a/b/c").collect::<Vec<_>>(), vec!["a/b", "a"]);
+    }
+
+    #[test]
+    fn filters_combine_patterns() -> eyre::Result<()> {
+        let filter: TagFilter = "go, rust*, -draft".parse()?;
+        assert!(filter.matches(&["go"]));
+        assert!(filter.matches(&["rustlang"]));
+        assert!(!filter.matches(&["google"]));
+        assert!(!filter.matches(&["go", "draft/wip"]));
+
+        let filter: TagFilter = "+lang, +async".parse()?;
+        assert!(filter.matches(&["lang/rust", "async"]));
+        assert!(!filter.matches(&["lang/rust"]));
+
+        let filter: TagFilter = "this is great, -to read".parse()?;
+        assert_eq!(filter.any, vec![TagPattern::new("this is great")]);
+        assert!(filter.matches(&["this is great"]));
+        assert!(!filter.matches(&["this"]));
+        assert!(!filter.matches(&["this is great", "to read"]));
+
+        assert!("rust, -".parse::<TagFilter>().is_err());
+        assert!("".parse::<TagFilter>()?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use once_cell::sync::Lazy;
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+static FROM_ENV: Lazy<Result<DisplayTimezone, String>> = Lazy::new(|| {
+    let Ok(name) = std::env::var("LIKELIKE_TIMEZONE") else {
+        return Ok(DisplayTimezone::default());
+    };
+
+    name.parse()
+        .map_err(|e| format!("LIKELIKE_TIMEZONE: {}", e))
+});
+
+/// The timezone dates are shown in and read in: dates in link dump filenames and page metadata,
+/// which carry no time or offset of their own, are taken as midnight here. Timestamps are stored
+/// in UTC regardless.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayTimezone {
+    /// The system's timezone.
+    #[default]
+    Local,
+    /// An IANA timezone, e.g. `Europe/Berlin` or `UTC`.
+    Named(Tz),
+}
+
+impl FromStr for DisplayTimezone {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(DisplayTimezone::Local);
+        }
+
+        s.parse().map(DisplayTimezone::Named).map_err(|_| {
+            eyre::eyre!(
+                "expected \"local\" or an IANA timezone like Europe/Berlin; got {:?}",
+                s
+            )
+        })
+    }
+}
+
+impl Display for DisplayTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayTimezone::Local => f.write_str("local"),
+            DisplayTimezone::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+impl DisplayTimezone {
+    /// The timezone named by `LIKELIKE_TIMEZONE`, either "local" (the default) or an IANA name.
+    /// The variable is read once. An unrecognized name is an error.
+    pub fn try_from_env() -> eyre::Result<Self> {
+        FROM_ENV.clone().map_err(|e| eyre::eyre!(e))
+    }
+
+    /// Like [`DisplayTimezone::try_from_env`], but falling back to the local timezone when the
+    /// name is unrecognized. Check it with `try_from_env` first to report that.
+    pub fn from_env() -> Self {
+        Self::try_from_env().unwrap_or_default()
+    }
+
+    /// A UTC timestamp as the wall clock time here, with its offset.
+    pub fn localize(&self, at: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            DisplayTimezone::Local => {
+                let at = at.with_timezone(&Local);
+                at.with_timezone(&at.offset().fix())
+            }
+            DisplayTimezone::Named(tz) => {
+                let at = at.with_timezone(tz);
+                at.with_timezone(&at.offset().fix())
+            }
+        }
+    }
+
+    /// The calendar date here at a UTC timestamp.
+    pub fn date(&self, at: DateTime<Utc>) -> NaiveDate {
+        self.localize(at).date_naive()
+    }
+
+    /// The UTC timestamp of a wall clock time here. Ambiguous times, when clocks go back, are
+    /// taken at their latest; times skipped when clocks go forward have none.
+    pub fn from_local(&self, at: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            DisplayTimezone::Local => Local
+                .from_local_datetime(&at)
+                .latest()
+                .map(|at| at.with_timezone(&Utc)),
+            DisplayTimezone::Named(tz) => tz
+                .from_local_datetime(&at)
+                .latest()
+                .map(|at| at.with_timezone(&Utc)),
+        }
+    }
+
+    /// The UTC timestamp of the start of a day here.
+    pub fn midnight(&self, date: NaiveDate) -> Option<DateTime<Utc>> {
+        self.from_local(date.and_hms_opt(0, 0, 0)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_between_utc_and_named_timezones() -> eyre::Result<()> {
+        let berlin: DisplayTimezone = "Europe/Berlin".parse()?;
+        assert_eq!(berlin.to_string(), "Europe/Berlin");
+        assert_eq!("LOCAL".parse::<DisplayTimezone>()?, DisplayTimezone::Local);
+        assert!("Mars/Olympus_Mons".parse::<DisplayTimezone>().is_err());
+
+        let at: DateTime<Utc> = "2024-03-30T23:30:00Z".parse()?;
+        assert_eq!(
+            berlin.localize(at).to_rfc3339(),
+            "2024-03-31T00:30:00+01:00"
+        );
+        assert_eq!(
+            berlin.date(at),
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap()
+        );
+
+        let midnight = berlin.midnight(NaiveDate::from_ymd_opt(2024, 7, 1).unwrap());
+        assert_eq!(midnight, Some("2024-06-30T22:00:00Z".parse()?));
+
+        // Clocks went forward past 02:00 that morning.
+        let skipped = NaiveDate::from_ymd_opt(2024, 3, 31)
+            .unwrap()
+            .and_hms_opt(2, 30, 0)
+            .unwrap();
+        assert_eq!(berlin.from_local(skipped), None);
+        Ok(())
+    }
+}